@@ -0,0 +1,334 @@
+//! This module implements centrality measures on a [`DirectedGraph`], to rank its most important
+//! vertices: PageRank, in- and out-degree centrality, and betweenness centrality.
+//!
+//! All results are deterministic: the scores are returned as `(VertexId, score)` pairs sorted by
+//! [`VertexId`], and [`ranking`] sorts them from the highest score.
+//!
+//! [`DirectedGraph`]: ../directed_graph/struct.DirectedGraph.html
+//! [`VertexId`]: ../graph/struct.VertexId.html
+//! [`ranking`]: fn.ranking.html
+
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::Arc;
+use std::thread;
+
+use crate::graph::directed_graph::DirectedGraph;
+use crate::graph::graph::{Direction, Edge, VertexId};
+
+// the number of sources of the betweenness centrality whose dependencies are summed together,
+// so that the sums don't depend on the number of threads
+const SOURCES_PER_CHUNK: usize = 64;
+
+/// Sorts scores from the highest to the lowest, the scores being equal sorted by `VertexId`.
+///
+/// # Examples
+///
+/// ```
+/// use histo_graph_core::graph::centrality::ranking;
+/// use histo_graph_core::graph::graph::VertexId;
+///
+/// let scores = vec![(VertexId(1), 0.2), (VertexId(2), 0.5), (VertexId(3), 0.2)];
+/// assert_eq!(ranking(scores), vec![(VertexId(2), 0.5), (VertexId(1), 0.2), (VertexId(3), 0.2)]);
+/// ```
+pub fn ranking(mut scores: Vec<(VertexId, f64)>) -> Vec<(VertexId, f64)> {
+    scores.sort_by(|(v1, s1), (v2, s2)| s2.partial_cmp(s1).unwrap_or(Ordering::Equal).then(v1.cmp(v2)));
+    scores
+}
+
+impl DirectedGraph {
+
+    /// Returns the PageRank of the vertices, computed with `iterations` steps of the power
+    /// method: a vertex passes the fraction `damping` of its rank to its successors, shared
+    /// according to the multiplicity of its outbound edges, and the rest is spread over all the
+    /// vertices. The rank of a vertex without outbound edges is spread over all the vertices.
+    ///
+    /// The ranks sum up to 1. The usual `damping` is 0.85.
+    /// Returns an error if `damping` isn't between 0 and 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// g.add_edge(Edge(VertexId(1), VertexId(3)));
+    /// g.add_edge(Edge(VertexId(2), VertexId(3)));
+    ///
+    /// let ranks = g.pagerank(0.85, 50).unwrap();
+    /// assert_eq!(ranks[0].1, ranks[1].1);
+    /// assert!(ranks[2].1 > ranks[0].1);
+    ///
+    /// assert!(g.pagerank(2.0, 50).is_err());
+    /// ```
+    pub fn pagerank(&self, damping: f64, iterations: usize) -> Result<Vec<(VertexId, f64)>, String> {
+        if !(0.0..=1.0).contains(&damping) {
+            return Err(format!("The damping factor {} isn't between 0 and 1", damping));
+        }
+        let index = Index::new(self, true);
+        let n = index.vertices.len();
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut ranks = vec![1.0 / n as f64; n];
+        for _ in 0..iterations {
+            let dangling: f64 = (0..n)
+                .filter(|&v| index.successors[v].is_empty())
+                .map(|v| ranks[v])
+                .sum();
+            let mut next = vec![(1.0 - damping + damping * dangling) / n as f64; n];
+            for (v, successors) in index.successors.iter().enumerate() {
+                let share = damping * ranks[v] / successors.len() as f64;
+                for &w in successors {
+                    next[w] += share;
+                }
+            }
+            ranks = next;
+        }
+
+        Ok(index.scores(ranks))
+    }
+
+    /// Returns the degree centrality of the vertices: their degree in the given direction, divided
+    /// by the number of other vertices. Parallel edges and self-loops are counted, so that the
+    /// centrality of a vertex of a multigraph can exceed 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge, Direction};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// g.add_edge(Edge(VertexId(1), VertexId(3)));
+    ///
+    /// assert_eq!(g.degree_centrality(Direction::Outbound),
+    ///            vec![(VertexId(1), 1.0), (VertexId(2), 0.0), (VertexId(3), 0.0)]);
+    /// ```
+    pub fn degree_centrality(&self, direction: Direction) -> Vec<(VertexId, f64)> {
+        let mut vertices: Vec<VertexId> = self.vertices().copied().collect();
+        vertices.sort();
+        let others = vertices.len().saturating_sub(1).max(1) as f64;
+        vertices
+            .into_iter()
+            .map(|vertex_id| {
+                let degree = match direction {
+                    Direction::Outbound => self.degree_out(vertex_id),
+                    Direction::Inbound => self.degree_in(vertex_id),
+                    Direction::Both => self.degree_out(vertex_id) + self.degree_in(vertex_id),
+                };
+                (vertex_id, degree as f64 / others)
+            })
+            .collect()
+    }
+
+    /// Returns the betweenness centrality of the vertices, computed with Brandes' algorithm: the
+    /// sum, over the pairs of other vertices `(s, t)`, of the fraction of the shortest paths from
+    /// `s` to `t` that go through the vertex. The paths follow the direction of the edges, and
+    /// parallel edges are counted once. The scores are not normalized.
+    ///
+    /// The shortest paths from the sources are computed on up to `threads` threads. The result
+    /// doesn't depend on the number of threads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// g.add_edge(Edge(VertexId(2), VertexId(3)));
+    ///
+    /// assert_eq!(g.betweenness_centrality(1),
+    ///            vec![(VertexId(1), 0.0), (VertexId(2), 1.0), (VertexId(3), 0.0)]);
+    /// ```
+    pub fn betweenness_centrality(&self, threads: usize) -> Vec<(VertexId, f64)> {
+        let index = Arc::new(Index::new(self, false));
+        let n = index.vertices.len();
+        let chunks: Arc<Vec<(usize, usize)>> = Arc::new((0..n)
+            .step_by(SOURCES_PER_CHUNK)
+            .map(|start| (start, (start + SOURCES_PER_CHUNK).min(n)))
+            .collect());
+
+        // each thread takes every `threads`-th chunk
+        let threads = threads.max(1).min(chunks.len().max(1));
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| {
+                let (index, chunks) = (Arc::clone(&index), Arc::clone(&chunks));
+                thread::spawn(move || {
+                    chunks
+                        .iter()
+                        .enumerate()
+                        .skip(worker)
+                        .step_by(threads)
+                        .map(|(i, &(start, end))| (i, index.dependencies(start..end)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let mut chunk_sums: Vec<(usize, Vec<f64>)> = workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("a betweenness worker panicked"))
+            .collect();
+
+        chunk_sums.sort_by_key(|&(i, _)| i);
+        let mut scores = vec![0.0; n];
+        for (_, sums) in chunk_sums {
+            for (score, sum) in scores.iter_mut().zip(sums) {
+                *score += sum;
+            }
+        }
+        index.scores(scores)
+    }
+}
+
+/// The vertices of a graph, sorted, with the successors of each vertex given by their index.
+struct Index {
+    vertices: Vec<VertexId>,
+    successors: Vec<Vec<usize>>,
+}
+
+impl Index {
+    // `parallel_edges` keeps a successor once per edge, instead of once
+    fn new(graph: &DirectedGraph, parallel_edges: bool) -> Index {
+        let mut vertices: Vec<VertexId> = graph.vertices().copied().collect();
+        vertices.sort();
+        let positions: HashMap<VertexId, usize> = vertices
+            .iter()
+            .enumerate()
+            .map(|(position, &vertex_id)| (vertex_id, position))
+            .collect();
+        let successors = vertices
+            .iter()
+            .map(|&vertex_id| {
+                let successors = graph.outbound_edges(vertex_id).map(|&Edge(_, to)| positions[&to]);
+                if parallel_edges {
+                    let mut successors: Vec<usize> = successors.collect();
+                    successors.sort();
+                    successors
+                } else {
+                    successors.collect::<BTreeSet<usize>>().into_iter().collect()
+                }
+            })
+            .collect();
+        Index { vertices, successors }
+    }
+
+    fn scores(&self, scores: Vec<f64>) -> Vec<(VertexId, f64)> {
+        self.vertices.iter().copied().zip(scores).collect()
+    }
+
+    // the sum of the dependencies of the sources on each vertex, by Brandes' algorithm
+    fn dependencies(&self, sources: std::ops::Range<usize>) -> Vec<f64> {
+        let n = self.vertices.len();
+        let mut sums = vec![0.0; n];
+        let mut stack: Vec<usize> = Vec::with_capacity(n);
+        let mut queue: VecDeque<usize> = VecDeque::with_capacity(n);
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+        let mut paths: Vec<f64> = vec![0.0; n];
+        let mut distances: Vec<Option<usize>> = vec![None; n];
+        let mut dependencies: Vec<f64> = vec![0.0; n];
+
+        for source in sources {
+            for v in 0..n {
+                predecessors[v].clear();
+                paths[v] = 0.0;
+                distances[v] = None;
+                dependencies[v] = 0.0;
+            }
+            paths[source] = 1.0;
+            distances[source] = Some(0);
+            queue.push_back(source);
+
+            // counts the shortest paths from the source
+            while let Some(v) = queue.pop_front() {
+                stack.push(v);
+                let distance = distances[v].unwrap_or(0) + 1;
+                for &w in &self.successors[v] {
+                    if distances[w].is_none() {
+                        distances[w] = Some(distance);
+                        queue.push_back(w);
+                    }
+                    if distances[w] == Some(distance) {
+                        paths[w] += paths[v];
+                        predecessors[w].push(v);
+                    }
+                }
+            }
+
+            // accumulates the dependencies, from the farthest vertices
+            while let Some(w) = stack.pop() {
+                for &v in &predecessors[w] {
+                    dependencies[v] += paths[v] / paths[w] * (1.0 + dependencies[w]);
+                }
+                if w != source {
+                    sums[w] += dependencies[w];
+                }
+            }
+        }
+        sums
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::graph::centrality::ranking;
+    use crate::graph::directed_graph::DirectedGraph;
+    use crate::graph::graph::{Direction, Edge, VertexId};
+    use rand::{thread_rng, Rng};
+
+    fn scores(scores: Vec<(VertexId, f64)>) -> Vec<(u64, f64)> {
+        scores.into_iter().map(|(VertexId(id), score)| (id, score)).collect()
+    }
+
+    #[test]
+    fn test_empty() {
+        let graph = DirectedGraph::new();
+        assert!(graph.pagerank(0.85, 10).unwrap().is_empty());
+        assert!(graph.degree_centrality(Direction::Both).is_empty());
+        assert!(graph.betweenness_centrality(4).is_empty());
+    }
+
+    #[test]
+    fn test_pagerank() {
+        // a cycle, and a vertex without outbound edges
+        let ranks = DirectedGraph::from_edges(&[(1, 2), (2, 3), (3, 1), (3, 4)]).pagerank(0.85, 100).unwrap();
+
+        let sum: f64 = ranks.iter().map(|&(_, rank)| rank).sum();
+        assert!((sum - 1.0).abs() < 1e-9);
+        let ranked: Vec<u64> = ranking(ranks).into_iter().map(|(VertexId(id), _)| id).collect();
+        // 3 shares its rank between 1 and 4, which rank the same
+        assert_eq!(ranked, vec![3, 2, 1, 4]);
+
+        // without damping, the ranks stay uniform on a cycle
+        let cycle = DirectedGraph::from_edges(&[(1, 2), (2, 1)]).pagerank(0.0, 10).unwrap();
+        assert_eq!(scores(cycle), vec![(1, 0.5), (2, 0.5)]);
+    }
+
+    #[test]
+    fn test_betweenness() {
+        // two shortest paths from 1 to 4, the paths to 5 going through 4
+        let graph = DirectedGraph::from_edges(&[(1, 2), (1, 3), (2, 4), (3, 4), (4, 5), (4, 5), (5, 5)]);
+
+        assert_eq!(scores(graph.betweenness_centrality(1)),
+                   vec![(1, 0.0), (2, 1.0), (3, 1.0), (4, 3.0), (5, 0.0)]);
+    }
+
+    #[test]
+    fn test_betweenness_threads() {
+        let mut rng = thread_rng();
+        let mut graph = DirectedGraph::new();
+        for _ in 0..2000 {
+            graph.add_edge(Edge(VertexId(rng.gen_range(0, 300)), VertexId(rng.gen_range(0, 300))));
+        }
+
+        let single = graph.betweenness_centrality(1);
+        assert_eq!(single.len(), graph.vertex_count());
+        assert_eq!(graph.betweenness_centrality(3), single);
+        assert_eq!(graph.betweenness_centrality(16), single);
+    }
+}
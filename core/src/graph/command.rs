@@ -1,5 +1,7 @@
 use crate::graph::graph::{VertexId, Edge};
-use crate::graph::directed_graph::DirectedGraph;
+use crate::graph::directed_graph::{DirectedGraph, GraphMode};
+
+use std::fmt;
 
 /// A command to manipulate a [`DirectedGraph`]
 ///
@@ -20,9 +22,91 @@ pub enum GraphCommand {
     RemoveEdge(VertexId, VertexId),
 }
 
+/// The reason why a command in explicit form doesn't apply cleanly to a graph, i.e. why it
+/// wouldn't have exactly its own effect.
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub enum Conflict {
+    /// The vertex to add is already in the graph.
+    VertexExists,
+    /// The vertex to remove, or a vertex of the edge to add, is not in the graph.
+    VertexMissing,
+    /// The vertex to remove still has edges.
+    VertexHasEdges,
+    /// The edge to add is already in the simple graph.
+    EdgeExists,
+    /// The edge to remove is not in the graph.
+    EdgeMissing,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Conflict::*;
+        let description = match self {
+            VertexExists => "the vertex already exists",
+            VertexMissing => "a vertex doesn't exist",
+            VertexHasEdges => "the vertex still has edges",
+            EdgeExists => "the edge already exists",
+            EdgeMissing => "the edge doesn't exist",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+impl fmt::Display for GraphCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use GraphCommand::*;
+        match self {
+            AddVertex(VertexId(v)) => write!(f, "AddVertex({})", v),
+            RemoveVertex(VertexId(v)) => write!(f, "RemoveVertex({})", v),
+            AddEdge(VertexId(v1), VertexId(v2)) => write!(f, "AddEdge({}, {})", v1, v2),
+            RemoveEdge(VertexId(v1), VertexId(v2)) => write!(f, "RemoveEdge({}, {})", v1, v2),
+        }
+    }
+}
+
 impl GraphCommand {
 
+    /// Returns true if the command adds or removes the vertex `vertex_id`, or an edge from or to
+    /// it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::command::GraphCommand;
+    /// use histo_graph_core::graph::graph::VertexId;
+    ///
+    /// assert!(GraphCommand::AddEdge(VertexId(1), VertexId(2)).touches_vertex(VertexId(2)));
+    /// assert!(!GraphCommand::RemoveVertex(VertexId(1)).touches_vertex(VertexId(2)));
+    /// ```
+    pub fn touches_vertex(&self, vertex_id: VertexId) -> bool {
+        use GraphCommand::*;
+        match *self {
+            AddVertex(v) | RemoveVertex(v) => v == vertex_id,
+            AddEdge(v1, v2) | RemoveEdge(v1, v2) => v1 == vertex_id || v2 == vertex_id,
+        }
+    }
+
+    /// Returns true if the command adds or removes the edge `edge`.
+    ///
+    /// Removing a vertex also removes its edges, so for commands that are not in explicit form,
+    /// use [`touches_vertex`] to find the commands that may have removed an edge.
+    ///
+    /// [`touches_vertex`]: #method.touches_vertex
+    pub fn touches_edge(&self, edge: Edge) -> bool {
+        use GraphCommand::*;
+        match *self {
+            AddVertex(_) | RemoveVertex(_) => false,
+            AddEdge(v1, v2) | RemoveEdge(v1, v2) => Edge(v1, v2) == edge,
+        }
+    }
+
     /// Returns a command that reverts the provided `command`.
+    ///
+    /// This doesn't take the state of the graph into account: reverting a `RemoveVertex` doesn't
+    /// restore the edges of the vertex, and reverting a command that had no effect has an effect.
+    /// Use [`apply_with_undo`] to get the exact inverse of a command.
+    ///
+    /// [`apply_with_undo`]: #method.apply_with_undo
     pub fn revert(command: GraphCommand) -> GraphCommand {
         use GraphCommand::*;
         match command {
@@ -33,13 +117,160 @@ impl GraphCommand {
         }
     }
 
+    /// Returns the commands that revert the provided `commands`: each command is reverted, in
+    /// reverse order.
+    ///
+    /// The result is exact only for commands in explicit form, i.e. commands that each have
+    /// an effect, and where no vertex is removed along with its edges and no vertex is added
+    /// along with an edge. [`explicit_commands`] provides this form.
+    ///
+    /// [`explicit_commands`]: #method.explicit_commands
+    pub fn revert_commands(commands: &[GraphCommand]) -> Vec<GraphCommand> {
+        commands
+            .iter()
+            .rev()
+            .map(|&command| GraphCommand::revert(command))
+            .collect()
+    }
+
+    /// Applies a vector of commands to a graph, and returns their explicit form: the commands
+    /// that have the same effect on the graph, where every removed edge and every added vertex
+    /// has its own command, and where the commands that have no effect are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    /// use histo_graph_core::graph::command::GraphCommand::{self, *};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    ///
+    /// let explicit = GraphCommand::explicit_commands(&[RemoveVertex(VertexId(1))], &mut g);
+    /// assert_eq!(explicit, vec![RemoveEdge(VertexId(1), VertexId(2)), RemoveVertex(VertexId(1))]);
+    /// ```
+    pub fn explicit_commands(commands: &[GraphCommand], graph: &mut DirectedGraph) -> Vec<GraphCommand> {
+        let undo = GraphCommand::apply_commands_with_undo(commands, graph);
+        GraphCommand::revert_commands(&undo)
+    }
+
+    /// Checks whether the command, in explicit form, applies cleanly to the graph.
+    /// Returns the conflict if it doesn't.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    /// use histo_graph_core::graph::command::{Conflict, GraphCommand::*};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    ///
+    /// assert_eq!(RemoveVertex(VertexId(1)).conflict(&g), Some(Conflict::VertexHasEdges));
+    /// assert_eq!(AddEdge(VertexId(1), VertexId(3)).conflict(&g), Some(Conflict::VertexMissing));
+    /// assert_eq!(RemoveEdge(VertexId(1), VertexId(2)).conflict(&g), None);
+    /// ```
+    pub fn conflict(&self, graph: &DirectedGraph) -> Option<Conflict> {
+        use GraphCommand::*;
+        match *self {
+            AddVertex(v) if graph.contains_vertex(v) => Some(Conflict::VertexExists),
+            RemoveVertex(v) if !graph.contains_vertex(v) => Some(Conflict::VertexMissing),
+            RemoveVertex(v) if graph.degree_in(v) + graph.degree_out(v) > 0 => Some(Conflict::VertexHasEdges),
+            AddEdge(v1, v2) if !graph.contains_vertex(v1) || !graph.contains_vertex(v2) => Some(Conflict::VertexMissing),
+            AddEdge(v1, v2) if graph.mode() == GraphMode::Simple && graph.contains_edge(Edge(v1, v2)) => Some(Conflict::EdgeExists),
+            RemoveEdge(v1, v2) if !graph.contains_edge(Edge(v1, v2)) => Some(Conflict::EdgeMissing),
+            _ => None,
+        }
+    }
+
     /// Applies a vector of commands to a MutableGraph
-    pub fn apply_commands(commands: Vec<GraphCommand>, graph: &mut DirectedGraph) -> () {
+    pub fn apply_commands(commands: Vec<GraphCommand>, graph: &mut DirectedGraph) {
         for command in commands.iter() {
             command.apply_to(graph);
         }
     }
 
+    /// Applies a vector of commands to a graph, and returns the commands that exactly revert
+    /// their effect. See [`apply_with_undo`].
+    ///
+    /// [`apply_with_undo`]: #method.apply_with_undo
+    pub fn apply_commands_with_undo(commands: &[GraphCommand], graph: &mut DirectedGraph) -> Vec<GraphCommand> {
+        let mut undo: Vec<GraphCommand> = Vec::new();
+        for command in commands.iter() {
+            // the last command applied has to be reverted first
+            let mut command_undo = command.apply_with_undo(graph);
+            command_undo.append(&mut undo);
+            undo = command_undo;
+        }
+        undo
+    }
+
+    /// Applies the command to a graph, and returns the commands that exactly revert its effect,
+    /// in the order in which they have to be applied.
+    ///
+    /// The returned commands restore the edges that are removed along with a vertex, and remove
+    /// the vertices that are added along with an edge. A command that has no effect on the graph
+    /// returns no command.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    /// use histo_graph_core::graph::command::GraphCommand;
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    ///
+    /// let undo = GraphCommand::RemoveVertex(VertexId(1)).apply_with_undo(&mut g);
+    /// assert_eq!(undo, vec![GraphCommand::AddVertex(VertexId(1)),
+    ///                       GraphCommand::AddEdge(VertexId(1), VertexId(2))]);
+    ///
+    /// GraphCommand::apply_commands(undo, &mut g);
+    /// assert!(g.contains_edge(Edge(VertexId(1), VertexId(2))));
+    ///
+    /// assert!(GraphCommand::AddVertex(VertexId(1)).apply_with_undo(&mut g).is_empty());
+    /// ```
+    pub fn apply_with_undo(&self, graph: &mut DirectedGraph) -> Vec<GraphCommand> {
+        use GraphCommand::*;
+        match *self {
+            AddVertex(v) => {
+                if graph.add_vertex(v) { vec![] } else { vec![RemoveVertex(v)] }
+            }
+            RemoveVertex(v) => {
+                if !graph.contains_vertex(v) {
+                    return vec![];
+                }
+                let mut undo = vec![AddVertex(v)];
+                // a self-loop is both an outbound and an inbound edge, it must be restored once
+                let edges: Vec<Edge> = graph.outbound_edges(v)
+                    .chain(graph.inbound_edges(v).filter(|e| e.0 != v))
+                    .copied()
+                    .collect();
+                undo.extend(edges.into_iter().map(|Edge(v1, v2)| AddEdge(v1, v2)));
+                graph.remove_vertex(v);
+                undo
+            }
+            AddEdge(v1, v2) => {
+                let new_vertices: Vec<VertexId> = if v1 == v2 { vec![v1] } else { vec![v1, v2] }
+                    .into_iter()
+                    .filter(|&v| !graph.contains_vertex(v))
+                    .collect();
+                if !graph.add_edge(Edge(v1, v2)) {
+                    return vec![];
+                }
+                let mut undo = vec![RemoveEdge(v1, v2)];
+                undo.extend(new_vertices.into_iter().map(RemoveVertex));
+                undo
+            }
+            RemoveEdge(v1, v2) => {
+                if graph.remove_edge(Edge(v1, v2)) { vec![AddEdge(v1, v2)] } else { vec![] }
+            }
+        }
+    }
+
     /// Applies the command to a MutableGraph.
     pub fn apply_to(&self, graph: &mut DirectedGraph) {
         use GraphCommand::*;
@@ -63,4 +294,85 @@ impl GraphCommand {
         }
         res
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::GraphCommand;
+    use super::GraphCommand::*;
+    use crate::graph::directed_graph::{DirectedGraph, GraphMode};
+    use crate::graph::graph::{Edge, VertexId};
+
+    use rand::{thread_rng, Rng};
+
+    /// A random command on the vertices 0 to 4, for the tests that apply random command sequences.
+    pub(crate) fn random_command<R: Rng>(rng: &mut R) -> GraphCommand {
+        let v1 = VertexId(rng.gen_range::<u64>(0, 5));
+        let v2 = VertexId(rng.gen_range::<u64>(0, 5));
+        match rng.gen_range::<u8>(0, 4) {
+            0 => AddVertex(v1),
+            1 => RemoveVertex(v1),
+            2 => AddEdge(v1, v2),
+            _ => RemoveEdge(v1, v2),
+        }
+    }
+
+    #[test]
+    fn test_undo_remove_vertex_restores_edges() {
+        let mut graph = DirectedGraph::new();
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+        graph.add_edge(Edge(VertexId(3), VertexId(1)));
+        graph.add_edge(Edge(VertexId(1), VertexId(1)));
+
+        let undo = RemoveVertex(VertexId(1)).apply_with_undo(&mut graph);
+        assert_eq!(graph.edge_count(), 0);
+
+        GraphCommand::apply_commands(undo, &mut graph);
+        assert_eq!(graph.edge_count(), 4);
+        assert_eq!(graph.edge_multiplicity(Edge(VertexId(1), VertexId(2))), 2);
+        assert!(graph.contains_edge(Edge(VertexId(1), VertexId(1))));
+    }
+
+    #[test]
+    fn test_undo_add_edge_removes_new_vertices() {
+        let mut graph = DirectedGraph::new();
+        graph.add_vertex(VertexId(1));
+
+        let undo = AddEdge(VertexId(1), VertexId(2)).apply_with_undo(&mut graph);
+        assert_eq!(undo, vec![RemoveEdge(VertexId(1), VertexId(2)), RemoveVertex(VertexId(2))]);
+    }
+
+    #[test]
+    fn test_no_op_has_no_undo() {
+        let mut graph = DirectedGraph::with_mode(GraphMode::Simple);
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+
+        assert!(AddVertex(VertexId(1)).apply_with_undo(&mut graph).is_empty());
+        assert!(AddEdge(VertexId(1), VertexId(2)).apply_with_undo(&mut graph).is_empty());
+        assert!(RemoveVertex(VertexId(3)).apply_with_undo(&mut graph).is_empty());
+        assert!(RemoveEdge(VertexId(2), VertexId(1)).apply_with_undo(&mut graph).is_empty());
+    }
+
+    #[test]
+    fn test_undo_random_commands() {
+        let mut rng = thread_rng();
+
+        for &mode in [GraphMode::Simple, GraphMode::Multi].iter() {
+            for _ in 0..100 {
+                let mut graph = DirectedGraph::with_mode(mode);
+                let init: Vec<GraphCommand> = (0..10).map(|_| random_command(&mut rng)).collect();
+                GraphCommand::apply_commands(init.clone(), &mut graph);
+
+                let mut expected = DirectedGraph::with_mode(mode);
+                GraphCommand::apply_commands(init, &mut expected);
+
+                let commands: Vec<GraphCommand> = (0..20).map(|_| random_command(&mut rng)).collect();
+                let undo = GraphCommand::apply_commands_with_undo(&commands, &mut graph);
+                GraphCommand::apply_commands(undo, &mut graph);
+
+                assert_eq!(graph, expected);
+            }
+        }
+    }
+}
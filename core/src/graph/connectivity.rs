@@ -0,0 +1,294 @@
+//! This module implements connectivity analysis on a [`DirectedGraph`].
+//!
+//! All results are deterministic: every component is sorted by [`VertexId`], and the components
+//! are sorted by their smallest [`VertexId`]. This allows to compare the results over history.
+//!
+//! [`DirectedGraph`]: ../directed_graph/struct.DirectedGraph.html
+//! [`VertexId`]: ../graph/struct.VertexId.html
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::graph::directed_graph::DirectedGraph;
+use crate::graph::graph::{Edge, VertexId};
+
+impl DirectedGraph {
+
+    /// Returns the strongly connected components of the graph, computed with Tarjan's algorithm.
+    ///
+    /// Each component is sorted by `VertexId`, and the components are sorted by their first
+    /// vertex.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// g.add_edge(Edge(VertexId(2), VertexId(1)));
+    /// g.add_edge(Edge(VertexId(2), VertexId(3)));
+    ///
+    /// assert_eq!(g.strongly_connected_components(),
+    ///            vec![vec![VertexId(1), VertexId(2)], vec![VertexId(3)]]);
+    /// ```
+    pub fn strongly_connected_components(&self) -> Vec<Vec<VertexId>> {
+        let mut tarjan = Tarjan::new(self);
+        for vertex_id in sorted_vertices(self) {
+            if !tarjan.indices.contains_key(&vertex_id) {
+                tarjan.visit(vertex_id);
+            }
+        }
+
+        sort_components(tarjan.components)
+    }
+
+    /// Returns the weakly connected components of the graph, i.e. the components of the graph
+    /// when ignoring the direction of the edges.
+    ///
+    /// Each component is sorted by `VertexId`, and the components are sorted by their first
+    /// vertex.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// g.add_edge(Edge(VertexId(3), VertexId(2)));
+    /// g.add_vertex(VertexId(4));
+    ///
+    /// assert_eq!(g.weakly_connected_components(),
+    ///            vec![vec![VertexId(1), VertexId(2), VertexId(3)], vec![VertexId(4)]]);
+    /// ```
+    pub fn weakly_connected_components(&self) -> Vec<Vec<VertexId>> {
+        let mut visited: BTreeSet<VertexId> = BTreeSet::new();
+        let mut components: Vec<Vec<VertexId>> = Vec::new();
+
+        for vertex_id in sorted_vertices(self) {
+            if !visited.insert(vertex_id) {
+                continue;
+            }
+            let mut component = vec![vertex_id];
+            let mut stack = vec![vertex_id];
+            while let Some(current) = stack.pop() {
+                let neighbours = self.outbound_edges(current).map(|&Edge(_, to)| to)
+                    .chain(self.inbound_edges(current).map(|&Edge(from, _)| from));
+                for neighbour in neighbours {
+                    if visited.insert(neighbour) {
+                        component.push(neighbour);
+                        stack.push(neighbour);
+                    }
+                }
+            }
+            components.push(component);
+        }
+
+        sort_components(components)
+    }
+
+    /// Builds the condensation of the graph: a new `DirectedGraph` that has one vertex per
+    /// strongly connected component, and an edge between two components if the graph contains
+    /// at least one edge between their vertices.
+    ///
+    /// Each component is represented by its smallest `VertexId`. The condensation contains
+    /// neither self-loops nor parallel edges, and is always acyclic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// g.add_edge(Edge(VertexId(2), VertexId(1)));
+    /// g.add_edge(Edge(VertexId(2), VertexId(3)));
+    /// g.add_edge(Edge(VertexId(1), VertexId(3)));
+    ///
+    /// let c = g.condensation();
+    /// assert_eq!(c.vertex_count(), 2);
+    /// assert_eq!(c.edge_count(), 1);
+    /// assert!(c.contains_edge(Edge(VertexId(1), VertexId(3))));
+    /// ```
+    pub fn condensation(&self) -> DirectedGraph {
+        let components = self.strongly_connected_components();
+
+        let mut representative: HashMap<VertexId, VertexId> = HashMap::new();
+        for component in components.iter() {
+            for &vertex_id in component {
+                representative.insert(vertex_id, component[0]);
+            }
+        }
+
+        let edges: BTreeSet<Edge> = self.edges()
+            .map(|&Edge(from, to)| Edge(representative[&from], representative[&to]))
+            .filter(|Edge(from, to)| from != to)
+            .collect();
+
+        let mut condensation = DirectedGraph::with_mode(self.mode());
+        for component in components.iter() {
+            condensation.add_vertex(component[0]);
+        }
+        for edge in edges {
+            condensation.add_edge(edge);
+        }
+        condensation
+    }
+}
+
+// Helpers
+
+fn sorted_vertices(graph: &DirectedGraph) -> Vec<VertexId> {
+    let mut vertices: Vec<VertexId> = graph.vertices().copied().collect();
+    vertices.sort();
+    vertices
+}
+
+fn sort_components(mut components: Vec<Vec<VertexId>>) -> Vec<Vec<VertexId>> {
+    for component in components.iter_mut() {
+        component.sort();
+    }
+    components.sort();
+    components
+}
+
+/// State of an iterative Tarjan's algorithm. The recursion is replaced by an explicit stack, so
+/// that deep graphs don't overflow the call stack.
+struct Tarjan<'a> {
+    graph: &'a DirectedGraph,
+    next_index: usize,
+    indices: BTreeMap<VertexId, usize>,
+    low_links: BTreeMap<VertexId, usize>,
+    stack: Vec<VertexId>,
+    on_stack: BTreeSet<VertexId>,
+    components: Vec<Vec<VertexId>>,
+}
+
+impl<'a> Tarjan<'a> {
+
+    fn new(graph: &'a DirectedGraph) -> Tarjan<'a> {
+        Tarjan {
+            graph,
+            next_index: 0,
+            indices: BTreeMap::new(),
+            low_links: BTreeMap::new(),
+            stack: Vec::new(),
+            on_stack: BTreeSet::new(),
+            components: Vec::new(),
+        }
+    }
+
+    fn successors(&self, vertex_id: VertexId) -> Vec<VertexId> {
+        self.graph
+            .outbound_edges(vertex_id)
+            .map(|&Edge(_, to)| to)
+            .collect()
+    }
+
+    fn discover(&mut self, vertex_id: VertexId) {
+        self.indices.insert(vertex_id, self.next_index);
+        self.low_links.insert(vertex_id, self.next_index);
+        self.next_index += 1;
+        self.stack.push(vertex_id);
+        self.on_stack.insert(vertex_id);
+    }
+
+    fn visit(&mut self, root: VertexId) {
+        // each frame holds a vertex, its successors, and the position of the next successor to visit
+        let mut frames: Vec<(VertexId, Vec<VertexId>, usize)> = Vec::new();
+        self.discover(root);
+        frames.push((root, self.successors(root), 0));
+
+        while let Some((vertex_id, successors, position)) = frames.last_mut() {
+            let vertex_id = *vertex_id;
+            if let Some(&successor) = successors.get(*position) {
+                *position += 1;
+                if !self.indices.contains_key(&successor) {
+                    self.discover(successor);
+                    let successors = self.successors(successor);
+                    frames.push((successor, successors, 0));
+                } else if self.on_stack.contains(&successor) {
+                    let low_link = self.low_links[&vertex_id].min(self.indices[&successor]);
+                    self.low_links.insert(vertex_id, low_link);
+                }
+            } else {
+                frames.pop();
+                if let Some((parent, _, _)) = frames.last() {
+                    let low_link = self.low_links[parent].min(self.low_links[&vertex_id]);
+                    self.low_links.insert(*parent, low_link);
+                }
+                if self.low_links[&vertex_id] == self.indices[&vertex_id] {
+                    self.pop_component(vertex_id);
+                }
+            }
+        }
+    }
+
+    fn pop_component(&mut self, root: VertexId) {
+        let mut component = Vec::new();
+        while let Some(vertex_id) = self.stack.pop() {
+            self.on_stack.remove(&vertex_id);
+            component.push(vertex_id);
+            if vertex_id == root {
+                break;
+            }
+        }
+        self.components.push(component);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::graph::directed_graph::DirectedGraph;
+    use crate::graph::graph::{Edge, VertexId};
+
+    fn ids(components: &[&[u64]]) -> Vec<Vec<VertexId>> {
+        components
+            .iter()
+            .map(|c| c.iter().map(|&id| VertexId(id)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_scc_empty() {
+        let graph = DirectedGraph::new();
+        assert!(graph.strongly_connected_components().is_empty());
+        assert!(graph.weakly_connected_components().is_empty());
+        assert!(graph.condensation().is_empty());
+    }
+
+    #[test]
+    fn test_scc_two_cycles() {
+        let graph = DirectedGraph::from_edges(&[(5, 6), (6, 7), (7, 5), (7, 1), (1, 2), (2, 1), (2, 2), (3, 4)]);
+
+        assert_eq!(graph.strongly_connected_components(), ids(&[&[1, 2], &[3], &[4], &[5, 6, 7]]));
+        assert_eq!(graph.weakly_connected_components(), ids(&[&[1, 2, 5, 6, 7], &[3, 4]]));
+    }
+
+    #[test]
+    fn test_scc_long_chain() {
+        // a long cycle must not overflow the stack
+        let edges: Vec<(u64, u64)> = (0..100_000).map(|i| (i, (i + 1) % 100_000)).collect();
+        let graph = DirectedGraph::from_edges(&edges);
+
+        let components = graph.strongly_connected_components();
+        assert_eq!(components.len(), 1);
+        assert_eq!(components[0].len(), 100_000);
+    }
+
+    #[test]
+    fn test_condensation() {
+        let graph = DirectedGraph::from_edges(&[(5, 6), (6, 5), (6, 1), (5, 1), (1, 2), (2, 1), (3, 3)]);
+
+        let condensation = graph.condensation();
+
+        let mut expected = DirectedGraph::new();
+        expected.add_edge(Edge(VertexId(5), VertexId(1)));
+        expected.add_vertex(VertexId(3));
+
+        assert_eq!(condensation, expected);
+    }
+}
@@ -1,8 +1,15 @@
+use std::collections::BTreeMap;
+
 use crate::graph::graph::*;
 use crate::graph::directed_graph::DirectedGraph;
 use crate::graph::command::GraphCommand;
 
 /// Diff between 2 graphs
+///
+/// Edges are compared with their multiplicity: an edge that is contained twice in graph 1 and
+/// once in graph 2 appears once in the extra edges. For graphs with `GraphMode::Simple`, this
+/// amounts to comparing the sets of edges.
+#[derive(PartialEq, Eq, Clone, Debug)]
 pub struct StructureDiff {
     extra_vertices: Vec<VertexId>,   // in graph 1, not in graph 2
     missing_vertices: Vec<VertexId>, // missing in graph 2
@@ -10,12 +17,102 @@ pub struct StructureDiff {
     missing_edges: Vec<Edge>,        // missing in graph 2
 }
 
-// Compute the diff between 2 graphs, from the point of view of the first one
-pub fn diff(_g1: DirectedGraph, _g2: DirectedGraph) -> StructureDiff {
-    unimplemented!()
+/// Compute the diff between 2 graphs, from the point of view of the first one.
+/// The vertices and edges of the diff are sorted.
+///
+/// # Examples
+///
+/// ```
+/// use histo_graph_core::graph::directed_graph::DirectedGraph;
+/// use histo_graph_core::graph::graph::{VertexId, Edge};
+/// use histo_graph_core::graph::diff::diff;
+///
+/// let mut g1 = DirectedGraph::new();
+/// g1.add_edge(Edge(VertexId(1), VertexId(2)));
+/// let mut g2 = DirectedGraph::new();
+/// g2.add_edge(Edge(VertexId(1), VertexId(3)));
+///
+/// let d = diff(&g1, &g2);
+/// assert_eq!(d.extra_vertices(), &[VertexId(2)]);
+/// assert_eq!(d.missing_edges(), &[Edge(VertexId(1), VertexId(3))]);
+/// ```
+pub fn diff(g1: &DirectedGraph, g2: &DirectedGraph) -> StructureDiff {
+    let mut extra_vertices: Vec<VertexId> = g1.vertices()
+        .filter(|&&v| !g2.contains_vertex(v))
+        .copied()
+        .collect();
+    extra_vertices.sort();
+
+    let mut missing_vertices: Vec<VertexId> = g2.vertices()
+        .filter(|&&v| !g1.contains_vertex(v))
+        .copied()
+        .collect();
+    missing_vertices.sort();
+
+    StructureDiff {
+        extra_vertices,
+        missing_vertices,
+        extra_edges: edges_not_in(g1, g2),
+        missing_edges: edges_not_in(g2, g1),
+    }
+}
+
+/// Returns the edges of `g1` that are not in `g2`, taking multiplicities into account.
+fn edges_not_in(g1: &DirectedGraph, g2: &DirectedGraph) -> Vec<Edge> {
+    let mut counts: BTreeMap<Edge, usize> = BTreeMap::new();
+    for &edge in g1.edges() {
+        *counts.entry(edge).or_insert(0) += 1;
+    }
+
+    let mut res = Vec::new();
+    for (edge, count) in counts {
+        for _ in g2.edge_multiplicity(edge)..count {
+            res.push(edge);
+        }
+    }
+    res
 }
 
 impl StructureDiff {
+
+    /// Creates a diff from its parts, e.g. after it has been deserialized.
+    pub fn new(
+        extra_vertices: Vec<VertexId>,
+        missing_vertices: Vec<VertexId>,
+        extra_edges: Vec<Edge>,
+        missing_edges: Vec<Edge>,
+    ) -> StructureDiff {
+        StructureDiff { extra_vertices, missing_vertices, extra_edges, missing_edges }
+    }
+
+    /// The vertices that are in graph 1, but not in graph 2.
+    pub fn extra_vertices(&self) -> &[VertexId] {
+        &self.extra_vertices
+    }
+
+    /// The vertices that are in graph 2, but not in graph 1.
+    pub fn missing_vertices(&self) -> &[VertexId] {
+        &self.missing_vertices
+    }
+
+    /// The edges that are in graph 1, but not in graph 2.
+    pub fn extra_edges(&self) -> &[Edge] {
+        &self.extra_edges
+    }
+
+    /// The edges that are in graph 2, but not in graph 1.
+    pub fn missing_edges(&self) -> &[Edge] {
+        &self.missing_edges
+    }
+
+    /// Returns true if both graphs have the same structure.
+    pub fn is_empty(&self) -> bool {
+        self.extra_vertices.is_empty()
+            && self.missing_vertices.is_empty()
+            && self.extra_edges.is_empty()
+            && self.missing_edges.is_empty()
+    }
+
     /// Reverse the diff : provides the point of view of the second graph
     pub fn reverse(&self) -> StructureDiff {
         // Note : inefficient implementation which is using cloning.
@@ -32,18 +129,57 @@ impl StructureDiff {
     pub fn as_commands(&self) -> Vec<GraphCommand> {
         use GraphCommand::*;
         let mut res: Vec<GraphCommand> = Vec::new();
+        // edges are removed before the vertices, as removing a vertex removes its edges
+        for Edge(v1, v2) in self.extra_edges.iter() {
+            res.push(RemoveEdge(*v1, *v2))
+        }
         for vertex_id in self.extra_vertices.iter() {
             res.push(RemoveVertex(*vertex_id))
         }
         for vertex_id in self.missing_vertices.iter() {
             res.push(AddVertex(*vertex_id))
         }
-        for Edge(v1, v2) in self.extra_edges.iter() {
-            res.push(RemoveEdge(*v1, *v2))
-        }
         for Edge(v1, v2) in self.missing_edges.iter() {
             res.push(AddEdge(*v1, *v2))
         }
         res
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::diff;
+    use crate::graph::command::GraphCommand;
+    use crate::graph::directed_graph::{DirectedGraph, GraphMode};
+    use crate::graph::graph::{Edge, VertexId};
+
+    #[test]
+    fn test_diff_multiplicities() {
+        let g1 = DirectedGraph::from_edges(&[(1, 2), (1, 2), (1, 2), (2, 3)]);
+        let g2 = DirectedGraph::from_edges(&[(1, 2), (3, 4)]);
+
+        let d = diff(&g1, &g2);
+
+        assert_eq!(d.extra_vertices(), &[]);
+        assert_eq!(d.missing_vertices(), &[VertexId(4)]);
+        assert_eq!(d.extra_edges(), &[Edge(VertexId(1), VertexId(2)), Edge(VertexId(1), VertexId(2)), Edge(VertexId(2), VertexId(3))]);
+        assert_eq!(d.missing_edges(), &[Edge(VertexId(3), VertexId(4))]);
+        assert_eq!(d.reverse().reverse(), d);
+    }
+
+    #[test]
+    fn test_diff_as_commands() {
+        for &mode in [GraphMode::Simple, GraphMode::Multi].iter() {
+            let mut g1 = DirectedGraph::with_mode(mode);
+            g1.extend([(1, 2), (1, 2), (2, 3), (5, 5)].iter().copied().map(Edge::from));
+            let mut g2 = DirectedGraph::with_mode(mode);
+            g2.extend([(1, 2), (3, 4), (4, 4)].iter().copied().map(Edge::from));
+
+            let d = diff(&g1, &g2);
+            GraphCommand::apply_commands(d.as_commands(), &mut g1);
+
+            assert_eq!(g1, g2);
+            assert!(diff(&g1, &g2).is_empty());
+        }
+    }
+}
@@ -1,21 +1,37 @@
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
 
 use crate::graph::graph::*;
 use crate::util::b_tree_bag::BTreeBag;
 use std::collections::btree_map::BTreeMap;
 
+/// Defines whether a [`DirectedGraph`] may contain the same edge more than once.
+///
+/// [`DirectedGraph`]: struct.DirectedGraph.html
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "lowercase"))]
+pub enum GraphMode {
+
+    /// Each edge is contained at most once. Adding an edge that is already contained is rejected.
+    Simple,
+
+    /// An edge can be contained several times (parallel edges).
+    Multi,
+}
+
 /// A directed graph structure that doesn't contain any information concerning the vertex or the
 /// edge attributes
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct DirectedGraph {
     // Each edge is indexed for by of both its vertices => 1 edge appears twice in the map
     edge_map: HashMap<VertexId, BTreeBag<Edge>>,
+    mode: GraphMode,
 }
 
 impl DirectedGraph {
 
-    /// Creates an empty `DirectedGraph`.
+    /// Creates an empty `DirectedGraph` that allows parallel edges.
     ///
     /// # Examples
     ///
@@ -25,11 +41,60 @@ impl DirectedGraph {
     /// let mut graph = DirectedGraph::new();
     /// ```
     pub fn new() -> DirectedGraph {
+        DirectedGraph::with_mode(GraphMode::Multi)
+    }
+
+    /// Creates an empty `DirectedGraph` with the given `mode`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::{DirectedGraph, GraphMode};
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g = DirectedGraph::with_mode(GraphMode::Simple);
+    /// assert!(g.add_edge(Edge(VertexId(1), VertexId(2))));
+    /// assert!(!g.add_edge(Edge(VertexId(1), VertexId(2))));
+    /// assert_eq!(g.edge_count(), 1);
+    /// ```
+    pub fn with_mode(mode: GraphMode) -> DirectedGraph {
         DirectedGraph {
             edge_map: HashMap::new(),
+            mode,
         }
     }
 
+    /// Creates a `DirectedGraph` that allows parallel edges from the pairs of the ids of the
+    /// vertices of its edges.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let g = DirectedGraph::from_edges(&[(1, 2), (1, 2), (2, 3)]);
+    /// assert_eq!(g.edge_multiplicity(Edge(VertexId(1), VertexId(2))), 2);
+    /// assert_eq!(g.vertex_count(), 3);
+    /// ```
+    pub fn from_edges(edges: &[(u64, u64)]) -> DirectedGraph {
+        edges.iter().copied().map(Edge::from).collect()
+    }
+
+    /// Returns the `GraphMode` of the graph.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::{DirectedGraph, GraphMode};
+    ///
+    /// assert_eq!(DirectedGraph::new().mode(), GraphMode::Multi);
+    /// assert_eq!(DirectedGraph::with_mode(GraphMode::Simple).mode(), GraphMode::Simple);
+    /// ```
+    pub fn mode(&self) -> GraphMode {
+        self.mode
+    }
+
     /// Returns true if the graph contains no vertices nor edges.
     ///
     /// # Examples
@@ -80,7 +145,7 @@ impl DirectedGraph {
     /// ```
     pub fn edge_count(&self) -> usize {
         let mut total_count: usize = 0;
-        for (_, edges) in &self.edge_map {
+        for edges in self.edge_map.values() {
             let count: usize = edges
                 .iter()
                 .map(|&Edge(v1, v2)| if v1 == v2 { 2 } else { 1 })
@@ -142,18 +207,30 @@ impl DirectedGraph {
     /// assert!(g.contains_edge(Edge(VertexId(1), VertexId(2))));
     /// ```
     pub fn contains_edge(&self, edge: Edge) -> bool {
-        let Edge(v1, v2) = edge;
-        if self.contains_vertex(v1) && self.contains_vertex(v2) {
-            // We need to look-up only for one of the vertices
-            self.edge_map
-                .get(&v1)
-                .unwrap()
-                .iter()
-                .position(|x| *x == edge)
-                .is_some()
-        } else {
-            false
-        }
+        // We need to look-up only for one of the vertices
+        self.edge_multiplicity(edge) > 0
+    }
+
+    /// Returns how many times the graph contains the `edge`.
+    /// In a graph with `GraphMode::Simple`, this is at most 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// assert_eq!(g.edge_multiplicity(Edge(VertexId(1), VertexId(2))), 0);
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// assert_eq!(g.edge_multiplicity(Edge(VertexId(1), VertexId(2))), 2);
+    /// ```
+    pub fn edge_multiplicity(&self, edge: Edge) -> usize {
+        self.edge_map
+            .get(&edge.0)
+            .map(|edges| edges.count(&edge))
+            .unwrap_or(0)
     }
 
     /// An iterator visiting all the edges of the graph in arbitrary order.
@@ -176,11 +253,10 @@ impl DirectedGraph {
     /// ```
     pub fn edges(&self) -> impl Iterator<Item = &Edge> {
         self.edge_map.iter()
-            .map(|(vertex_id, bag)| {
+            .flat_map(|(vertex_id, bag)| {
                 bag.iter()
-                    .filter(move |Edge(from, to)| *vertex_id == *from)
+                    .filter(move |Edge(from, _)| *vertex_id == *from)
             })
-            .flatten()
     }
 
     /// An iterator visiting all the outbound edges of `vertex_id`.
@@ -327,7 +403,9 @@ impl DirectedGraph {
         }
     }
 
-    /// Adds an edge to the graph.
+    /// Adds an edge to the graph, and the vertices it connects if they are not yet contained.
+    /// Returns true if the edge has been added. In a graph with `GraphMode::Simple`, adding an
+    /// edge that is already contained is rejected, and false is returned.
     ///
     /// # Examples
     /// ```
@@ -335,9 +413,14 @@ impl DirectedGraph {
     /// use histo_graph_core::graph::graph::{VertexId, Edge};
     ///
     /// let mut g = DirectedGraph::new();
-    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// assert!(g.add_edge(Edge(VertexId(1), VertexId(2))));
+    /// assert!(g.add_edge(Edge(VertexId(1), VertexId(2))));
+    /// assert_eq!(g.edge_count(), 2);
     /// ```
-    pub fn add_edge(&mut self, edge: Edge) {
+    pub fn add_edge(&mut self, edge: Edge) -> bool {
+        if self.mode == GraphMode::Simple && self.contains_edge(edge) {
+            return false;
+        }
         let Edge(v1, v2) = edge;
         self.add_vertex(v1);
         self.add_vertex(v2);
@@ -345,6 +428,7 @@ impl DirectedGraph {
         if edge.0 != edge.1 {
             self.edge_map.get_mut(&v2).unwrap().insert(edge);
         }
+        true
     }
 
     /// Removes and edge from the graph.
@@ -367,13 +451,42 @@ impl DirectedGraph {
         if let Some(found_v1) = self.edge_map.get_mut(&v1) {
             found |= found_v1.remove(&edge);
         }
-        if let Some(found_v2) = self.edge_map.get_mut(&v2) {
-            found |= found_v2.remove(&edge);
+        // a self-loop is stored only once
+        if v1 != v2 {
+            if let Some(found_v2) = self.edge_map.get_mut(&v2) {
+                found |= found_v2.remove(&edge);
+            }
         }
         found
     }
 }
 
+impl Default for DirectedGraph {
+    fn default() -> DirectedGraph {
+        DirectedGraph::new()
+    }
+}
+
+impl Extend<Edge> for DirectedGraph {
+
+    /// Adds the edges, as `add_edge` does in the mode of the graph.
+    fn extend<I: IntoIterator<Item = Edge>>(&mut self, edges: I) {
+        for edge in edges {
+            self.add_edge(edge);
+        }
+    }
+}
+
+impl FromIterator<Edge> for DirectedGraph {
+
+    /// Creates a `DirectedGraph` that allows parallel edges from its edges.
+    fn from_iter<I: IntoIterator<Item = Edge>>(edges: I) -> DirectedGraph {
+        let mut graph = DirectedGraph::new();
+        graph.extend(edges);
+        graph
+    }
+}
+
 impl Hash for DirectedGraph {
 
     /// Hashes the `DirectedGraph`.
@@ -443,4 +556,14 @@ mod test {
 
         assert_ne!(graph_1, graph_2);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_remove_one_of_two_self_loops() {
+        let mut graph = DirectedGraph::new();
+        graph.add_edge(Edge(VertexId(0), VertexId(0)));
+        graph.add_edge(Edge(VertexId(0), VertexId(0)));
+
+        assert!(graph.remove_edge(Edge(VertexId(0), VertexId(0))));
+        assert_eq!(graph.edge_multiplicity(Edge(VertexId(0), VertexId(0))), 1);
+    }
+}
@@ -0,0 +1,230 @@
+//! This module implements `Serialize` and `Deserialize` for [`DirectedGraph`], behind the `serde`
+//! feature.
+//!
+//! The graph is written as `{"mode":"multi","vertices":[1,2],"edges":[[1,2]]}`, the format of
+//! `histo_graph_serde::directed_graph_serde::DirectedGraphSer`. The vertices and the edges are
+//! streamed from and into the graph, without intermediate vectors. A missing mode is read as
+//! `multi`.
+//!
+//! [`DirectedGraph`]: ../directed_graph/struct.DirectedGraph.html
+
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeSeq, SerializeStruct};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::graph::directed_graph::{DirectedGraph, GraphMode};
+use crate::graph::graph::{Edge, VertexId};
+
+const FIELDS: &[&str] = &["mode", "vertices", "edges"];
+
+struct Vertices<'a>(&'a DirectedGraph);
+
+impl<'a> Serialize for Vertices<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.vertex_count()))?;
+        for vertex_id in self.0.vertices() {
+            seq.serialize_element(vertex_id)?;
+        }
+        seq.end()
+    }
+}
+
+struct Edges<'a>(&'a DirectedGraph);
+
+impl<'a> Serialize for Edges<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.edge_count()))?;
+        for edge in self.0.edges() {
+            seq.serialize_element(edge)?;
+        }
+        seq.end()
+    }
+}
+
+impl Serialize for DirectedGraph {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("DirectedGraph", FIELDS.len())?;
+        state.serialize_field("mode", &self.mode())?;
+        state.serialize_field("vertices", &Vertices(self))?;
+        state.serialize_field("edges", &Edges(self))?;
+        state.end()
+    }
+}
+
+/// Adds the vertices of a sequence to the graph, as they are read.
+struct AddVertices<'a>(&'a mut DirectedGraph);
+
+impl<'de, 'a> DeserializeSeed<'de> for AddVertices<'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for AddVertices<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of vertices")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(vertex_id) = seq.next_element::<VertexId>()? {
+            self.0.add_vertex(vertex_id);
+        }
+        Ok(())
+    }
+}
+
+/// Adds the edges of a sequence to the graph, as they are read.
+struct AddEdges<'a>(&'a mut DirectedGraph);
+
+impl<'de, 'a> DeserializeSeed<'de> for AddEdges<'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for AddEdges<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of edges")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(edge) = seq.next_element::<Edge>()? {
+            self.0.add_edge(edge);
+        }
+        Ok(())
+    }
+}
+
+/// Returns a graph with the given mode and the vertices and edges of `graph`.
+/// Used when the mode comes after the vertices or the edges.
+fn with_mode(graph: DirectedGraph, mode: GraphMode) -> DirectedGraph {
+    if graph.mode() == mode {
+        return graph;
+    }
+    let mut res = DirectedGraph::with_mode(mode);
+    for &vertex_id in graph.vertices() {
+        res.add_vertex(vertex_id);
+    }
+    for &edge in graph.edges() {
+        res.add_edge(edge);
+    }
+    res
+}
+
+struct DirectedGraphVisitor;
+
+impl<'de> Visitor<'de> for DirectedGraphVisitor {
+    type Value = DirectedGraph;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a graph with vertices and edges")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<DirectedGraph, A::Error> {
+        let mode: GraphMode = seq.next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let mut graph = DirectedGraph::with_mode(mode);
+        seq.next_element_seed(AddVertices(&mut graph))?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        seq.next_element_seed(AddEdges(&mut graph))?
+            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+        Ok(graph)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<DirectedGraph, A::Error> {
+        let mut graph = DirectedGraph::new();
+        let (mut mode, mut vertices, mut edges) = (false, false, false);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "mode" if mode => return Err(de::Error::duplicate_field("mode")),
+                "mode" => {
+                    graph = with_mode(graph, map.next_value()?);
+                    mode = true;
+                }
+                "vertices" if vertices => return Err(de::Error::duplicate_field("vertices")),
+                "vertices" => {
+                    map.next_value_seed(AddVertices(&mut graph))?;
+                    vertices = true;
+                }
+                "edges" if edges => return Err(de::Error::duplicate_field("edges")),
+                "edges" => {
+                    map.next_value_seed(AddEdges(&mut graph))?;
+                    edges = true;
+                }
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+        if !vertices {
+            return Err(de::Error::missing_field("vertices"));
+        }
+        if !edges {
+            return Err(de::Error::missing_field("edges"));
+        }
+        Ok(graph)
+    }
+}
+
+impl<'de> Deserialize<'de> for DirectedGraph {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<DirectedGraph, D::Error> {
+        deserializer.deserialize_struct("DirectedGraph", FIELDS, DirectedGraphVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::graph::directed_graph::{DirectedGraph, GraphMode};
+    use crate::graph::graph::{Edge, VertexId};
+
+    #[test]
+    fn test_round_trip() -> Result<(), serde_json::error::Error> {
+        for &mode in [GraphMode::Simple, GraphMode::Multi].iter() {
+            let mut graph = DirectedGraph::with_mode(mode);
+            graph.add_edge(Edge(VertexId(0), VertexId(1)));
+            graph.add_edge(Edge(VertexId(0), VertexId(1)));
+            graph.add_edge(Edge(VertexId(2), VertexId(2)));
+            graph.add_vertex(VertexId(3));
+
+            let str = serde_json::to_string(&graph)?;
+            let ser_de_graph: DirectedGraph = serde_json::from_str(&str)?;
+            assert_eq!(graph, ser_de_graph);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_format() -> Result<(), serde_json::error::Error> {
+        let mut graph = DirectedGraph::with_mode(GraphMode::Simple);
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+        graph.remove_vertex(VertexId(2));
+
+        assert_eq!(serde_json::to_string(&graph)?, r#"{"mode":"simple","vertices":[1],"edges":[]}"#);
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_or_late_mode() -> Result<(), serde_json::error::Error> {
+        let graph: DirectedGraph = serde_json::from_str(r#"{"vertices":[0,1],"edges":[[0,1],[0,1]]}"#)?;
+        assert_eq!(graph.mode(), GraphMode::Multi);
+        assert_eq!(graph.edge_count(), 2);
+
+        let graph: DirectedGraph = serde_json::from_str(r#"{"edges":[[0,1],[0,1]],"vertices":[2],"mode":"simple"}"#)?;
+        assert_eq!(graph.mode(), GraphMode::Simple);
+        assert_eq!(graph.edge_count(), 1);
+        assert_eq!(graph.vertex_count(), 3);
+
+        assert!(serde_json::from_str::<DirectedGraph>(r#"{"mode":"multi","vertices":[]}"#).is_err());
+        Ok(())
+    }
+}
@@ -1,17 +1,23 @@
 //! This module defines the basic building blocks of a graph.
 //!
 //! [`VertexId`] to identify a vertex, and [`Edge`], a connection between two vertices.
+//! [`Direction`] selects which edges of a vertex are followed when walking a graph.
 //!
 //! [`VertexId`]: struct.VertexId.html
 //! [`Edge`]: struct.Edge.html
+//! [`Direction`]: enum.Direction.html
 
 /// Identifies and represents a vertex in a graph.
+/// With the `serde` feature, it is serialized as its id.
 #[derive(PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VertexId(pub u64);
 
 /// Represents a directed edge in a graph.
 /// The first `VertexId` is the start of the edge, the second `VertexId` is its end.
+/// With the `serde` feature, it is serialized as the pair of the ids of its vertices.
 #[derive(PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Edge(pub VertexId, pub VertexId);
 
 impl Edge {
@@ -27,3 +33,23 @@ impl From<(VertexId, VertexId)> for Edge {
         Edge(pair.0, pair.1)
     }
 }
+
+impl From<(u64, u64)> for Edge {
+    fn from(pair: (u64, u64)) -> Edge {
+        Edge(VertexId(pair.0), VertexId(pair.1))
+    }
+}
+
+/// The direction in which edges are followed when walking a graph from a vertex.
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub enum Direction {
+
+    /// Follows the outbound edges of a vertex.
+    Outbound,
+
+    /// Follows the inbound edges of a vertex.
+    Inbound,
+
+    /// Follows both the outbound and the inbound edges of a vertex.
+    Both,
+}
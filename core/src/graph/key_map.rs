@@ -0,0 +1,128 @@
+//! This module defines [`KeyMap`], a bidirectional map between external string keys and vertex ids.
+//!
+//! A binding is recorded in the history as a trailer of a commit, whose key is [`KEY_TRAILER`] and
+//! whose value is the vertex id followed by a space and the key.
+//!
+//! [`KeyMap`]: struct.KeyMap.html
+//! [`KEY_TRAILER`]: constant.KEY_TRAILER.html
+
+use std::collections::HashMap;
+
+use crate::graph::graph::VertexId;
+
+/// The key of the commit trailers that record the bindings of keys to vertex ids.
+pub const KEY_TRAILER: &str = "Key";
+
+/// A bidirectional map between external keys, e.g. package names or host names, and the ids of
+/// the vertices they designate. A key has a single vertex id, and a vertex id a single key.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct KeyMap {
+    ids: HashMap<String, VertexId>,
+    keys: HashMap<VertexId, String>,
+}
+
+impl KeyMap {
+
+    /// Creates an empty `KeyMap`.
+    pub fn new() -> KeyMap {
+        KeyMap::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Returns the vertex id of `key`, if it has one.
+    pub fn vertex_id(&self, key: &str) -> Option<VertexId> {
+        self.ids.get(key).copied()
+    }
+
+    /// Returns the key of `vertex_id`, if it has one.
+    pub fn key(&self, vertex_id: VertexId) -> Option<&str> {
+        self.keys.get(&vertex_id).map(String::as_str)
+    }
+
+    /// Binds `key` to `vertex_id`.
+    /// Returns false, and leaves the map unchanged, if the key or the vertex id is already bound to
+    /// something else.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::key_map::KeyMap;
+    /// use histo_graph_core::graph::graph::VertexId;
+    ///
+    /// let mut keys = KeyMap::new();
+    /// assert!(keys.insert("serde", VertexId(1)));
+    /// assert!(keys.insert("serde", VertexId(1)));
+    /// assert!(!keys.insert("serde", VertexId(2)));
+    /// assert!(!keys.insert("tokio", VertexId(1)));
+    ///
+    /// assert_eq!(keys.vertex_id("serde"), Some(VertexId(1)));
+    /// assert_eq!(keys.key(VertexId(1)), Some("serde"));
+    /// ```
+    pub fn insert(&mut self, key: &str, vertex_id: VertexId) -> bool {
+        match (self.ids.get(key), self.keys.get(&vertex_id)) {
+            (None, None) => {
+                self.ids.insert(key.to_string(), vertex_id);
+                self.keys.insert(vertex_id, key.to_string());
+                true
+            }
+            (Some(&id), Some(_)) => id == vertex_id,
+            _ => false,
+        }
+    }
+
+    /// An iterator visiting all the keys with their vertex id, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item=(&str, VertexId)> {
+        self.ids.iter().map(|(key, &vertex_id)| (key.as_str(), vertex_id))
+    }
+
+    /// Returns the bindings as commit trailers, sorted by vertex id.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::key_map::KeyMap;
+    /// use histo_graph_core::graph::graph::VertexId;
+    ///
+    /// let mut keys = KeyMap::new();
+    /// keys.insert("serde derive", VertexId(2));
+    /// keys.insert("serde", VertexId(1));
+    ///
+    /// let trailers = keys.trailers();
+    /// assert_eq!(trailers, vec![
+    ///     ("Key".to_string(), "1 serde".to_string()),
+    ///     ("Key".to_string(), "2 serde derive".to_string()),
+    /// ]);
+    ///
+    /// let mut read = KeyMap::new();
+    /// read.insert_trailers(&trailers);
+    /// assert_eq!(read, keys);
+    /// ```
+    pub fn trailers(&self) -> Vec<(String, String)> {
+        let mut bindings: Vec<(VertexId, &str)> = self.iter().map(|(key, vertex_id)| (vertex_id, key)).collect();
+        bindings.sort();
+        bindings
+            .into_iter()
+            .map(|(VertexId(id), key)| (KEY_TRAILER.to_string(), format!("{} {}", id, key)))
+            .collect()
+    }
+
+    /// Binds the keys recorded by the `Key` trailers, and ignores the other trailers.
+    /// A binding that is malformed, or that conflicts with a previous one, is ignored.
+    pub fn insert_trailers(&mut self, trailers: &[(String, String)]) {
+        for (_, value) in trailers.iter().filter(|(name, _)| name == KEY_TRAILER) {
+            let binding = value
+                .split_once(' ')
+                .and_then(|(id, key)| id.parse().ok().map(|id| (key, VertexId(id))));
+            if let Some((key, vertex_id)) = binding {
+                self.insert(key, vertex_id);
+            }
+        }
+    }
+}
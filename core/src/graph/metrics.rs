@@ -0,0 +1,325 @@
+//! This module implements statistics on the structure of a [`DirectedGraph`]: degree
+//! distributions, density, self-loops and parallel edges, sources and sinks, diameter and
+//! clustering coefficient.
+//!
+//! All results are deterministic, so that the metrics of the states of a graph can be compared
+//! over history. [`GraphMetrics`] gathers them in a single value.
+//!
+//! [`DirectedGraph`]: ../directed_graph/struct.DirectedGraph.html
+//! [`GraphMetrics`]: struct.GraphMetrics.html
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+use crate::graph::directed_graph::DirectedGraph;
+use crate::graph::graph::{Direction, Edge, VertexId};
+
+/// The number of vertices from which the diameter is measured by [`metrics`].
+///
+/// [`metrics`]: ../directed_graph/struct.DirectedGraph.html#method.metrics
+pub const DIAMETER_SAMPLES: usize = 16;
+
+/// The metrics of a graph, as returned by [`metrics`].
+///
+/// [`metrics`]: ../directed_graph/struct.DirectedGraph.html#method.metrics
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GraphMetrics {
+    pub vertices: usize,
+    pub edges: usize,
+    pub density: f64,
+    pub self_loops: usize,
+    pub parallel_edges: usize,
+    pub sources: usize,
+    pub sinks: usize,
+    /// The number of vertices of each in-degree.
+    pub degree_in: BTreeMap<usize, usize>,
+    /// The number of vertices of each out-degree.
+    pub degree_out: BTreeMap<usize, usize>,
+    /// A lower bound of the diameter, measured from [`DIAMETER_SAMPLES`] vertices.
+    ///
+    /// [`DIAMETER_SAMPLES`]: constant.DIAMETER_SAMPLES.html
+    pub diameter: usize,
+    pub clustering_coefficient: f64,
+}
+
+impl DirectedGraph {
+
+    /// Returns all the metrics of the graph.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// g.add_edge(Edge(VertexId(2), VertexId(3)));
+    ///
+    /// let metrics = g.metrics();
+    /// assert_eq!((metrics.vertices, metrics.edges, metrics.diameter), (3, 2, 2));
+    /// assert_eq!((metrics.sources, metrics.sinks), (1, 1));
+    /// ```
+    pub fn metrics(&self) -> GraphMetrics {
+        GraphMetrics {
+            vertices: self.vertex_count(),
+            edges: self.edge_count(),
+            density: self.density(),
+            self_loops: self.self_loop_count(),
+            parallel_edges: self.parallel_edge_count(),
+            sources: self.sources().len(),
+            sinks: self.sinks().len(),
+            degree_in: self.degree_distribution(Direction::Inbound),
+            degree_out: self.degree_distribution(Direction::Outbound),
+            diameter: self.estimate_diameter(DIAMETER_SAMPLES),
+            clustering_coefficient: self.clustering_coefficient(),
+        }
+    }
+
+    /// Returns the number of vertices of each degree. The degree of a vertex is counted in the
+    /// given direction, `Both` adding its in-degree and its out-degree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge, Direction};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// g.add_edge(Edge(VertexId(1), VertexId(3)));
+    ///
+    /// let distribution = g.degree_distribution(Direction::Outbound);
+    /// assert_eq!(distribution.into_iter().collect::<Vec<_>>(), vec![(0, 2), (2, 1)]);
+    /// ```
+    pub fn degree_distribution(&self, direction: Direction) -> BTreeMap<usize, usize> {
+        let mut distribution = BTreeMap::new();
+        for &vertex_id in self.vertices() {
+            let degree = match direction {
+                Direction::Outbound => self.degree_out(vertex_id),
+                Direction::Inbound => self.degree_in(vertex_id),
+                Direction::Both => self.degree_out(vertex_id) + self.degree_in(vertex_id),
+            };
+            *distribution.entry(degree).or_insert(0) += 1;
+        }
+        distribution
+    }
+
+    /// Returns the ratio of the pairs of distinct vertices that are connected by an edge, between
+    /// 0 and 1. Self-loops are ignored, and parallel edges are counted once.
+    /// Returns 0 if the graph has fewer than two vertices.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    ///
+    /// assert_eq!(g.density(), 0.5);
+    /// ```
+    pub fn density(&self) -> f64 {
+        let vertices = self.vertex_count();
+        if vertices < 2 {
+            return 0.0;
+        }
+        let edges = self.edges()
+            .filter(|&&Edge(from, to)| from != to)
+            .collect::<HashSet<&Edge>>()
+            .len();
+        edges as f64 / (vertices * (vertices - 1)) as f64
+    }
+
+    /// Returns the number of edges from a vertex to itself, each parallel edge being counted.
+    pub fn self_loop_count(&self) -> usize {
+        self.edges().filter(|&&Edge(from, to)| from == to).count()
+    }
+
+    /// Returns the number of edges that are parallel to another edge, i.e. the number of edges
+    /// that would have to be removed to make the graph simple.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// for _ in 0..3 {
+    ///     g.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// }
+    /// g.add_edge(Edge(VertexId(2), VertexId(1)));
+    ///
+    /// assert_eq!(g.parallel_edge_count(), 2);
+    /// ```
+    pub fn parallel_edge_count(&self) -> usize {
+        self.edge_count() - self.edges().collect::<HashSet<&Edge>>().len()
+    }
+
+    /// Returns the vertices without inbound edges, sorted. An isolated vertex is both a source and
+    /// a sink.
+    pub fn sources(&self) -> Vec<VertexId> {
+        sorted_vertices(self, |vertex_id| self.degree_in(vertex_id) == 0)
+    }
+
+    /// Returns the vertices without outbound edges, sorted. An isolated vertex is both a source and
+    /// a sink.
+    pub fn sinks(&self) -> Vec<VertexId> {
+        sorted_vertices(self, |vertex_id| self.degree_out(vertex_id) == 0)
+    }
+
+    /// Estimates the diameter of the graph: the greatest length of a shortest path, following the
+    /// direction of the edges, from one of `samples` vertices evenly spread over the sorted
+    /// vertices to any vertex it reaches.
+    ///
+    /// The estimate is a lower bound of the diameter, and is exact if `samples` is at least the
+    /// number of vertices. The cost is one breadth-first search per sample.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// for i in 0..9 {
+    ///     g.add_edge(Edge(VertexId(i), VertexId(i + 1)));
+    /// }
+    ///
+    /// assert_eq!(g.estimate_diameter(10), 9);
+    /// assert_eq!(g.estimate_diameter(1), 9);
+    /// ```
+    pub fn estimate_diameter(&self, samples: usize) -> usize {
+        let vertices = sorted_vertices(self, |_| true);
+        let samples = samples.min(vertices.len());
+        (0..samples)
+            .map(|i| vertices[i * vertices.len() / samples])
+            .map(|vertex_id| self.eccentricity(vertex_id))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the average clustering coefficient of the vertices, ignoring the direction of the
+    /// edges, self-loops and parallel edges.
+    ///
+    /// The clustering coefficient of a vertex is the ratio of the pairs of its neighbours that are
+    /// connected, and is 0 for a vertex with fewer than two neighbours.
+    /// Returns 0 for an empty graph.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// g.add_edge(Edge(VertexId(2), VertexId(3)));
+    /// g.add_edge(Edge(VertexId(3), VertexId(1)));
+    ///
+    /// assert_eq!(g.clustering_coefficient(), 1.0);
+    /// ```
+    pub fn clustering_coefficient(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+
+        // sorted, so that the coefficients are summed in the same order
+        let mut neighbours: BTreeMap<VertexId, BTreeSet<VertexId>> = BTreeMap::new();
+        for &Edge(from, to) in self.edges().filter(|&&Edge(from, to)| from != to) {
+            neighbours.entry(from).or_default().insert(to);
+            neighbours.entry(to).or_default().insert(from);
+        }
+
+        let total: f64 = neighbours
+            .values()
+            .filter(|adjacent| adjacent.len() >= 2)
+            .map(|adjacent| {
+                let adjacent: Vec<VertexId> = adjacent.iter().copied().collect();
+                let mut links = 0;
+                for (i, a) in adjacent.iter().enumerate() {
+                    links += adjacent[i + 1..].iter().filter(|b| neighbours[a].contains(b)).count();
+                }
+                let pairs = adjacent.len() * (adjacent.len() - 1) / 2;
+                links as f64 / pairs as f64
+            })
+            .sum();
+        total / self.vertex_count() as f64
+    }
+
+    // Helpers
+
+    // the greatest distance from `vertex_id` to a vertex it reaches
+    fn eccentricity(&self, vertex_id: VertexId) -> usize {
+        let mut distances: HashMap<VertexId, usize> = HashMap::new();
+        distances.insert(vertex_id, 0);
+        let mut queue = VecDeque::new();
+        queue.push_back(vertex_id);
+        let mut eccentricity = 0;
+        while let Some(current) = queue.pop_front() {
+            let distance = distances[&current];
+            eccentricity = distance;
+            for &Edge(_, to) in self.outbound_edges(current) {
+                if let Entry::Vacant(entry) = distances.entry(to) {
+                    entry.insert(distance + 1);
+                    queue.push_back(to);
+                }
+            }
+        }
+        eccentricity
+    }
+}
+
+fn sorted_vertices<P: Fn(VertexId) -> bool>(graph: &DirectedGraph, predicate: P) -> Vec<VertexId> {
+    let mut vertices: Vec<VertexId> = graph.vertices().copied().filter(|&vertex_id| predicate(vertex_id)).collect();
+    vertices.sort();
+    vertices
+}
+
+#[cfg(test)]
+mod test {
+    use crate::graph::directed_graph::DirectedGraph;
+    use crate::graph::graph::{Direction, VertexId};
+
+    #[test]
+    fn test_metrics_empty() {
+        let metrics = DirectedGraph::new().metrics();
+        assert_eq!((metrics.vertices, metrics.edges, metrics.diameter), (0, 0, 0));
+        assert_eq!((metrics.density, metrics.clustering_coefficient), (0.0, 0.0));
+        assert!(metrics.degree_in.is_empty());
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut graph = DirectedGraph::from_edges(&[(1, 2), (1, 2), (2, 3), (3, 1), (3, 3), (3, 4)]);
+        graph.add_vertex(VertexId(5));
+
+        let metrics = graph.metrics();
+        assert_eq!((metrics.vertices, metrics.edges), (5, 6));
+        assert_eq!((metrics.self_loops, metrics.parallel_edges), (1, 1));
+        assert_eq!(graph.sources(), vec![VertexId(5)]);
+        assert_eq!(graph.sinks(), vec![VertexId(4), VertexId(5)]);
+        assert_eq!(metrics.density, 4.0 / 20.0);
+        assert_eq!(metrics.diameter, 3);
+        // the triangle 1, 2, 3: 3 has a third neighbour, 4
+        assert_eq!(metrics.clustering_coefficient, (1.0 + 1.0 + 1.0 / 3.0) / 5.0);
+
+        let both = graph.degree_distribution(Direction::Both);
+        assert_eq!(both.into_iter().collect::<Vec<_>>(), vec![(0, 1), (1, 1), (3, 2), (5, 1)]);
+        assert_eq!(metrics.degree_in.values().sum::<usize>(), 5);
+    }
+
+    #[test]
+    fn test_diameter_samples() {
+        // the longest path starts at the vertex with the greatest id
+        let graph = DirectedGraph::from_edges(&[(9, 1), (1, 2), (2, 3), (3, 4), (5, 6)]);
+
+        assert_eq!(graph.estimate_diameter(graph.vertex_count()), 4);
+        assert_eq!(graph.estimate_diameter(1), 3);
+        assert_eq!(graph.estimate_diameter(0), 0);
+    }
+}
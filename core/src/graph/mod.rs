@@ -1,4 +1,13 @@
+pub mod centrality;
 pub mod command;
+pub mod connectivity;
 pub mod diff;
 pub mod directed_graph;
+#[cfg(feature = "serde")]
+pub mod directed_graph_serde;
+#[allow(clippy::module_inception)]
 pub mod graph;
+pub mod key_map;
+pub mod metrics;
+pub mod optimizer;
+pub mod transform;
@@ -0,0 +1,257 @@
+//! This module implements the optimization of command streams: a list of [`GraphCommand`] is
+//! rewritten into a canonical list, which has the same effect on any graph of a given
+//! [`GraphMode`].
+//!
+//! The canonical list only depends on the effect of the commands, so that equivalent lists are
+//! optimized into the same list. It contains, in this order:
+//! - the `RemoveEdge` commands that apply to the edges initially in the graph,
+//! - the `RemoveVertex` commands, which also remove the edges of the vertices,
+//! - the `AddVertex` commands of the vertices that are not added by an `AddEdge`,
+//! - the `AddEdge` commands.
+//!
+//! Each group is sorted, and a command is repeated for the multiplicity of its edge. A vertex or
+//! an edge only gets the commands that its effect requires: self-cancelling commands disappear.
+//!
+//! [`GraphCommand`]: ../command/enum.GraphCommand.html
+//! [`GraphMode`]: ../directed_graph/enum.GraphMode.html
+
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::graph::command::GraphCommand;
+use crate::graph::directed_graph::GraphMode;
+use crate::graph::graph::{Edge, VertexId};
+
+/// The effect of a list of commands on a vertex.
+#[derive(Default)]
+struct VertexEffect {
+    // the vertex is removed, along with its edges
+    removed: bool,
+    // the vertex is in the graph afterwards
+    added: bool,
+}
+
+/// The effect of a list of commands on an edge: its multiplicity `n` becomes
+/// `max(n - removed, 0) + added`, or `added` if the edge is cleared by the removal of a vertex.
+#[derive(Default)]
+struct EdgeEffect {
+    cleared: bool,
+    removed: usize,
+    added: usize,
+}
+
+/// Returns the canonical list of commands that has the same effect as `commands` on any graph
+/// with the given mode. See the [module documentation](index.html) for the canonical form.
+///
+/// # Examples
+///
+/// ```
+/// use histo_graph_core::graph::directed_graph::GraphMode;
+/// use histo_graph_core::graph::graph::VertexId;
+/// use histo_graph_core::graph::command::GraphCommand::*;
+/// use histo_graph_core::graph::optimizer::optimize;
+///
+/// let commands = vec![
+///     AddEdge(VertexId(1), VertexId(2)),
+///     AddVertex(VertexId(3)),
+///     RemoveEdge(VertexId(1), VertexId(2)),
+///     AddEdge(VertexId(2), VertexId(1)),
+/// ];
+///
+/// // in a multigraph, adding and removing an edge only adds its vertices
+/// assert_eq!(optimize(&commands, GraphMode::Multi), vec![
+///     AddVertex(VertexId(3)),
+///     AddEdge(VertexId(2), VertexId(1)),
+/// ]);
+/// // in a simple graph, the edge is absent afterwards, even if it was initially there
+/// assert_eq!(optimize(&commands, GraphMode::Simple), vec![
+///     RemoveEdge(VertexId(1), VertexId(2)),
+///     AddVertex(VertexId(3)),
+///     AddEdge(VertexId(2), VertexId(1)),
+/// ]);
+/// ```
+pub fn optimize(commands: &[GraphCommand], mode: GraphMode) -> Vec<GraphCommand> {
+    use GraphCommand::*;
+    let mut vertices: BTreeMap<VertexId, VertexEffect> = BTreeMap::new();
+    let mut edges: BTreeMap<Edge, EdgeEffect> = BTreeMap::new();
+    // the edges of `edges`, indexed by their vertices
+    let mut edges_of: HashMap<VertexId, Vec<Edge>> = HashMap::new();
+
+    for &command in commands {
+        match command {
+            AddVertex(v) => vertices.entry(v).or_default().added = true,
+            RemoveVertex(v) => {
+                let effect = vertices.entry(v).or_default();
+                effect.removed = true;
+                effect.added = false;
+                for edge in edges_of.get(&v).into_iter().flatten() {
+                    edges.insert(*edge, EdgeEffect { cleared: true, removed: 0, added: 0 });
+                }
+            }
+            AddEdge(v1, v2) => {
+                vertices.entry(v1).or_default().added = true;
+                vertices.entry(v2).or_default().added = true;
+                let effect = edge_effect(&mut edges, &mut edges_of, &vertices, Edge(v1, v2));
+                match mode {
+                    GraphMode::Multi => effect.added += 1,
+                    GraphMode::Simple => {
+                        effect.removed = 0;
+                        effect.added = 1;
+                    }
+                }
+            }
+            RemoveEdge(v1, v2) => {
+                let effect = edge_effect(&mut edges, &mut edges_of, &vertices, Edge(v1, v2));
+                match mode {
+                    GraphMode::Multi if effect.added > 0 => effect.added -= 1,
+                    GraphMode::Multi if !effect.cleared => effect.removed += 1,
+                    GraphMode::Multi => (),
+                    GraphMode::Simple => {
+                        effect.removed = if effect.cleared { 0 } else { 1 };
+                        effect.added = 0;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut res: Vec<GraphCommand> = Vec::new();
+    for (&Edge(v1, v2), effect) in edges.iter() {
+        res.extend((0..effect.removed).map(|_| RemoveEdge(v1, v2)));
+    }
+    for (&v, _) in vertices.iter().filter(|(_, effect)| effect.removed) {
+        res.push(RemoveVertex(v));
+    }
+    let added_by_edges: BTreeSet<VertexId> = edges
+        .iter()
+        .filter(|(_, effect)| effect.added > 0)
+        .flat_map(|(&Edge(v1, v2), _)| [v1, v2])
+        .collect();
+    for (&v, _) in vertices.iter().filter(|(_, effect)| effect.added) {
+        if !added_by_edges.contains(&v) {
+            res.push(AddVertex(v));
+        }
+    }
+    for (&Edge(v1, v2), effect) in edges.iter() {
+        res.extend((0..effect.added).map(|_| AddEdge(v1, v2)));
+    }
+    res
+}
+
+/// Returns the effect on the edge. A new edge is cleared if one of its vertices has been removed,
+/// and is indexed by its vertices.
+fn edge_effect<'a>(
+    edges: &'a mut BTreeMap<Edge, EdgeEffect>,
+    edges_of: &mut HashMap<VertexId, Vec<Edge>>,
+    vertices: &BTreeMap<VertexId, VertexEffect>,
+    edge: Edge,
+) -> &'a mut EdgeEffect {
+    match edges.entry(edge) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            let Edge(v1, v2) = edge;
+            edges_of.entry(v1).or_default().push(edge);
+            if v1 != v2 {
+                edges_of.entry(v2).or_default().push(edge);
+            }
+            let removed = |v: VertexId| vertices.get(&v).map_or(false, |effect| effect.removed);
+            let cleared = removed(v1) || removed(v2);
+            entry.insert(EdgeEffect { cleared, removed: 0, added: 0 })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::optimize;
+    use crate::graph::command::GraphCommand::{self, *};
+    use crate::graph::command::test::random_command;
+    use crate::graph::directed_graph::{DirectedGraph, GraphMode};
+    use crate::graph::graph::VertexId;
+
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn test_optimize_cancels_commands() {
+        let v = VertexId;
+        let commands = vec![
+            AddVertex(v(1)),
+            AddEdge(v(1), v(2)),
+            AddEdge(v(1), v(2)),
+            RemoveVertex(v(1)),
+            RemoveEdge(v(1), v(2)),
+            AddVertex(v(1)),
+            RemoveEdge(v(3), v(4)),
+            AddEdge(v(3), v(4)),
+            AddEdge(v(3), v(4)),
+        ];
+
+        assert_eq!(optimize(&commands, GraphMode::Multi), vec![
+            RemoveEdge(v(3), v(4)),
+            RemoveVertex(v(1)),
+            AddVertex(v(1)),
+            AddVertex(v(2)),
+            AddEdge(v(3), v(4)),
+            AddEdge(v(3), v(4)),
+        ]);
+        assert_eq!(optimize(&commands, GraphMode::Simple), vec![
+            RemoveVertex(v(1)),
+            AddVertex(v(1)),
+            AddVertex(v(2)),
+            AddEdge(v(3), v(4)),
+        ]);
+        assert_eq!(optimize(&[RemoveVertex(v(1)), AddVertex(v(1)), RemoveVertex(v(1))], GraphMode::Multi),
+                   vec![RemoveVertex(v(1))]);
+    }
+
+    #[test]
+    fn test_optimize_random_commands() {
+        let mut rng = thread_rng();
+
+        for &mode in [GraphMode::Simple, GraphMode::Multi].iter() {
+            for _ in 0..200 {
+                let init: Vec<GraphCommand> = (0..15).map(|_| random_command(&mut rng)).collect();
+                let commands: Vec<GraphCommand> = (0..30).map(|_| random_command(&mut rng)).collect();
+                let optimized = optimize(&commands, mode);
+
+                let mut expected = DirectedGraph::with_mode(mode);
+                GraphCommand::apply_commands(init.clone(), &mut expected);
+                let mut graph = expected.clone();
+                GraphCommand::apply_commands(commands.clone(), &mut expected);
+                GraphCommand::apply_commands(optimized.clone(), &mut graph);
+
+                assert_eq!(graph, expected, "{:?} optimized into {:?}", commands, optimized);
+                assert_eq!(optimize(&optimized, mode), optimized);
+            }
+        }
+    }
+
+    #[test]
+    fn test_optimize_equivalent_commands() {
+        let mut rng = thread_rng();
+
+        // equivalent lists of commands are optimized into the same list
+        for &mode in [GraphMode::Simple, GraphMode::Multi].iter() {
+            for _ in 0..200 {
+                let commands: Vec<GraphCommand> = (0..20).map(|_| random_command(&mut rng)).collect();
+                let position = rng.gen_range::<usize>(0, commands.len() + 1);
+                let v1 = VertexId(rng.gen_range::<u64>(0, 5));
+                let v2 = VertexId(rng.gen_range::<u64>(0, 5));
+
+                let (mut with, mut without) = (commands.clone(), commands.clone());
+                // adding a vertex has no effect if it is removed afterwards
+                with.splice(position..position, vec![AddVertex(v1), RemoveVertex(v1)]);
+                without.splice(position..position, vec![RemoveVertex(v1)]);
+                assert_eq!(optimize(&with, mode), optimize(&without, mode));
+
+                // in a multigraph, adding and removing an edge only adds its vertices
+                if mode == GraphMode::Multi {
+                    let (mut with, mut without) = (commands.clone(), commands);
+                    with.splice(position..position, vec![AddEdge(v1, v2), RemoveEdge(v1, v2)]);
+                    without.splice(position..position, vec![AddVertex(v1), AddVertex(v2)]);
+                    assert_eq!(optimize(&with, mode), optimize(&without, mode));
+                }
+            }
+        }
+    }
+}
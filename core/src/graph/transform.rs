@@ -0,0 +1,320 @@
+//! This module implements operations that derive new graphs from existing ones: induced
+//! subgraphs, neighbourhoods, reversal, and set operations between two graphs.
+//!
+//! The operations respect the multigraph semantics of [`DirectedGraph`]: an edge that is
+//! contained `n` times in a graph is treated as an element of multiplicity `n`.
+//! The derived graphs have the same [`GraphMode`] as the graph they are derived from.
+//!
+//! [`DirectedGraph`]: ../directed_graph/struct.DirectedGraph.html
+//! [`GraphMode`]: ../directed_graph/enum.GraphMode.html
+
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::graph::directed_graph::DirectedGraph;
+use crate::graph::graph::{Direction, Edge, VertexId};
+
+impl DirectedGraph {
+
+    /// Returns the subgraph induced by `vertices`: the vertices that are contained in the graph,
+    /// and all the edges of the graph that connect two of them.
+    /// Vertices that are not contained in the graph are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// g.add_edge(Edge(VertexId(2), VertexId(3)));
+    ///
+    /// let s = g.subgraph(vec![VertexId(1), VertexId(2), VertexId(4)]);
+    /// assert_eq!(s.vertex_count(), 2);
+    /// assert!(s.contains_edge(Edge(VertexId(1), VertexId(2))));
+    /// assert!(!s.contains_edge(Edge(VertexId(2), VertexId(3))));
+    /// ```
+    pub fn subgraph<I>(&self, vertices: I) -> DirectedGraph
+        where I: IntoIterator,
+              <I as IntoIterator>::Item: Borrow<VertexId>
+    {
+        let vertices: HashSet<VertexId> = vertices
+            .into_iter()
+            .map(|v| *v.borrow())
+            .filter(|&v| self.contains_vertex(v))
+            .collect();
+
+        let mut graph = DirectedGraph::with_mode(self.mode());
+        for &vertex_id in vertices.iter() {
+            graph.add_vertex(vertex_id);
+        }
+        for &vertex_id in vertices.iter() {
+            for &edge in self.outbound_edges(vertex_id) {
+                if vertices.contains(&edge.1) {
+                    graph.add_edge(edge);
+                }
+            }
+        }
+        graph
+    }
+
+    /// Returns the subgraph induced by the vertices that can be reached from `vertex_id` in at
+    /// most `depth` steps, following the edges in the given `direction`.
+    /// Returns an empty graph if the graph doesn't contain `vertex_id`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge, Direction};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// g.add_edge(Edge(VertexId(2), VertexId(3)));
+    /// g.add_edge(Edge(VertexId(0), VertexId(1)));
+    ///
+    /// let e = g.ego_graph(VertexId(1), 1, Direction::Outbound);
+    /// assert_eq!(e.vertex_count(), 2);
+    ///
+    /// let e = g.ego_graph(VertexId(1), 1, Direction::Both);
+    /// assert_eq!(e.vertex_count(), 3);
+    /// ```
+    pub fn ego_graph(&self, vertex_id: VertexId, depth: usize, direction: Direction) -> DirectedGraph {
+        if !self.contains_vertex(vertex_id) {
+            return DirectedGraph::with_mode(self.mode());
+        }
+
+        let mut reached: HashSet<VertexId> = HashSet::new();
+        reached.insert(vertex_id);
+        let mut frontier = vec![vertex_id];
+
+        for _ in 0..depth {
+            let mut next_frontier = Vec::new();
+            for &current in frontier.iter() {
+                for neighbour in self.neighbours(current, direction) {
+                    if reached.insert(neighbour) {
+                        next_frontier.push(neighbour);
+                    }
+                }
+            }
+            if next_frontier.is_empty() {
+                break;
+            }
+            frontier = next_frontier;
+        }
+
+        self.subgraph(reached)
+    }
+
+    /// Returns a graph with the same vertices, and all edges reversed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g = DirectedGraph::new();
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    ///
+    /// let r = g.reverse();
+    /// assert!(r.contains_edge(Edge(VertexId(2), VertexId(1))));
+    /// assert!(!r.contains_edge(Edge(VertexId(1), VertexId(2))));
+    /// ```
+    pub fn reverse(&self) -> DirectedGraph {
+        let mut graph = DirectedGraph::with_mode(self.mode());
+        for &vertex_id in self.vertices() {
+            graph.add_vertex(vertex_id);
+        }
+        for edge in self.edges() {
+            graph.add_edge(edge.reverse());
+        }
+        graph
+    }
+
+    /// Returns the union of two graphs. It contains the vertices of both graphs, and each edge
+    /// with the greater of its multiplicities in the two graphs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g1 = DirectedGraph::new();
+    /// g1.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// let mut g2 = DirectedGraph::new();
+    /// g2.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// g2.add_edge(Edge(VertexId(2), VertexId(3)));
+    ///
+    /// let u = g1.union(&g2);
+    /// assert_eq!(u.vertex_count(), 3);
+    /// assert_eq!(u.edge_count(), 2);
+    /// ```
+    pub fn union(&self, other: &DirectedGraph) -> DirectedGraph {
+        let mut graph = DirectedGraph::with_mode(self.mode());
+        for &vertex_id in self.vertices().chain(other.vertices()) {
+            graph.add_vertex(vertex_id);
+        }
+
+        let mut counts = edge_counts(self);
+        for (edge, count) in edge_counts(other) {
+            let entry = counts.entry(edge).or_insert(0);
+            *entry = (*entry).max(count);
+        }
+        add_edge_counts(&mut graph, counts);
+        graph
+    }
+
+    /// Returns the intersection of two graphs. It contains the vertices that are in both
+    /// graphs, and each edge with the lesser of its multiplicities in the two graphs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g1 = DirectedGraph::new();
+    /// g1.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// let mut g2 = DirectedGraph::new();
+    /// g2.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// g2.add_edge(Edge(VertexId(2), VertexId(3)));
+    ///
+    /// let i = g1.intersection(&g2);
+    /// assert_eq!(i.vertex_count(), 2);
+    /// assert_eq!(i.edge_count(), 1);
+    /// ```
+    pub fn intersection(&self, other: &DirectedGraph) -> DirectedGraph {
+        let mut graph = DirectedGraph::with_mode(self.mode());
+        for &vertex_id in self.vertices().filter(|&&v| other.contains_vertex(v)) {
+            graph.add_vertex(vertex_id);
+        }
+
+        let other_counts = edge_counts(other);
+        let counts = edge_counts(self)
+            .into_iter()
+            .filter_map(|(edge, count)| other_counts
+                .get(&edge)
+                .map(|&other_count| (edge, count.min(other_count))))
+            .collect();
+        add_edge_counts(&mut graph, counts);
+        graph
+    }
+
+    /// Returns the difference of two graphs. It contains all the vertices of `self`, and each
+    /// edge of `self` with its multiplicity reduced by its multiplicity in `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::directed_graph::DirectedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g1 = DirectedGraph::new();
+    /// g1.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// g1.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// g1.add_edge(Edge(VertexId(2), VertexId(3)));
+    /// let mut g2 = DirectedGraph::new();
+    /// g2.add_edge(Edge(VertexId(1), VertexId(2)));
+    ///
+    /// let d = g1.difference(&g2);
+    /// assert_eq!(d.vertex_count(), 3);
+    /// assert_eq!(d.edge_count(), 2);
+    /// ```
+    pub fn difference(&self, other: &DirectedGraph) -> DirectedGraph {
+        let mut graph = DirectedGraph::with_mode(self.mode());
+        for &vertex_id in self.vertices() {
+            graph.add_vertex(vertex_id);
+        }
+
+        let other_counts = edge_counts(other);
+        let counts = edge_counts(self)
+            .into_iter()
+            .map(|(edge, count)| {
+                let other_count = other_counts.get(&edge).cloned().unwrap_or(0);
+                (edge, count.saturating_sub(other_count))
+            })
+            .collect();
+        add_edge_counts(&mut graph, counts);
+        graph
+    }
+
+    // Helpers
+
+    fn neighbours(&self, vertex_id: VertexId, direction: Direction) -> Vec<VertexId> {
+        let outbound = self.outbound_edges(vertex_id).map(|&Edge(_, to)| to);
+        let inbound = self.inbound_edges(vertex_id).map(|&Edge(from, _)| from);
+        match direction {
+            Direction::Outbound => outbound.collect(),
+            Direction::Inbound => inbound.collect(),
+            Direction::Both => outbound.chain(inbound).collect(),
+        }
+    }
+}
+
+/// Counts how many times each edge is contained in the graph.
+fn edge_counts(graph: &DirectedGraph) -> BTreeMap<Edge, usize> {
+    let mut counts = BTreeMap::new();
+    for &edge in graph.edges() {
+        *counts.entry(edge).or_insert(0) += 1;
+    }
+    counts
+}
+
+fn add_edge_counts(graph: &mut DirectedGraph, counts: BTreeMap<Edge, usize>) {
+    for (edge, count) in counts {
+        for _ in 0..count {
+            graph.add_edge(edge);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::graph::directed_graph::DirectedGraph;
+    use crate::graph::graph::{Direction, VertexId};
+
+    #[test]
+    fn test_subgraph_keeps_parallel_edges_and_loops() {
+        let g = DirectedGraph::from_edges(&[(1, 2), (1, 2), (2, 2), (2, 3)]);
+
+        assert_eq!(g.subgraph(vec![VertexId(1), VertexId(2)]), DirectedGraph::from_edges(&[(1, 2), (1, 2), (2, 2)]));
+    }
+
+    #[test]
+    fn test_ego_graph_depth() {
+        let g = DirectedGraph::from_edges(&[(1, 2), (2, 3), (3, 4), (0, 1)]);
+
+        assert_eq!(g.ego_graph(VertexId(1), 0, Direction::Both), {
+            let mut e = DirectedGraph::new();
+            e.add_vertex(VertexId(1));
+            e
+        });
+        assert_eq!(g.ego_graph(VertexId(1), 2, Direction::Outbound), DirectedGraph::from_edges(&[(1, 2), (2, 3)]));
+        assert_eq!(g.ego_graph(VertexId(3), 5, Direction::Inbound), DirectedGraph::from_edges(&[(0, 1), (1, 2), (2, 3)]));
+        assert!(g.ego_graph(VertexId(9), 1, Direction::Both).is_empty());
+    }
+
+    #[test]
+    fn test_reverse_twice() {
+        let g = DirectedGraph::from_edges(&[(1, 2), (1, 2), (2, 2), (3, 1)]);
+
+        assert_eq!(g.reverse().reverse(), g);
+    }
+
+    #[test]
+    fn test_set_operations_multiplicities() {
+        let g1 = DirectedGraph::from_edges(&[(1, 2), (1, 2), (2, 3)]);
+        let g2 = DirectedGraph::from_edges(&[(1, 2), (3, 4), (3, 4)]);
+
+        assert_eq!(g1.union(&g2), DirectedGraph::from_edges(&[(1, 2), (1, 2), (2, 3), (3, 4), (3, 4)]));
+        assert_eq!(g1.intersection(&g2), {
+            let mut i = DirectedGraph::from_edges(&[(1, 2)]);
+            i.add_vertex(VertexId(3));
+            i
+        });
+        assert_eq!(g1.difference(&g2), DirectedGraph::from_edges(&[(1, 2), (2, 3)]));
+    }
+}
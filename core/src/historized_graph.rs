@@ -1,98 +1,1585 @@
 use crate::graph::graph::*;
 use crate::graph::directed_graph::*;
 use crate::graph::command::*;
+use crate::graph::diff::diff;
+use crate::graph::key_map::{KeyMap, KEY_TRAILER};
+use crate::graph::metrics::GraphMetrics;
 use crate::history::hashlist::*;
 use crate::history::history::*;
 
-type Commands = Vec<GraphCommand>;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
 
+pub type Commands = Vec<GraphCommand>;
+
+/// Decides when a state of the graph is kept as a checkpoint. Past states of the graph are rebuilt
+/// from the nearest checkpoint, so that only the commits after it are replayed.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CheckpointPolicy {
+    /// Checkpoints are only taken on demand, with `HistorizedGraph::checkpoint`.
+    OnDemand,
+    /// A checkpoint is taken at every `n`-th commit replayed after the nearest checkpoint.
+    Every(usize),
+}
+
+impl Default for CheckpointPolicy {
+    fn default() -> CheckpointPolicy {
+        CheckpointPolicy::Every(64)
+    }
+}
+
+/// Counts the commits that have been replayed to rebuild states of the graph.
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub struct ReplayMetrics {
+    materializations: usize,
+    replayed_commits: usize,
+    last_replay_length: usize,
+    max_replay_length: usize,
+}
+
+impl ReplayMetrics {
+    /// Number of states of the graph that have been rebuilt.
+    pub fn materializations(&self) -> usize {
+        self.materializations
+    }
+
+    /// Total number of commits that have been replayed.
+    pub fn replayed_commits(&self) -> usize {
+        self.replayed_commits
+    }
+
+    /// Number of commits replayed to rebuild the last state.
+    pub fn last_replay_length(&self) -> usize {
+        self.last_replay_length
+    }
+
+    /// Largest number of commits replayed to rebuild a state.
+    pub fn max_replay_length(&self) -> usize {
+        self.max_replay_length
+    }
+
+    fn record(&mut self, replay_length: usize) {
+        self.materializations += 1;
+        self.replayed_commits += replay_length;
+        self.last_replay_length = replay_length;
+        self.max_replay_length = self.max_replay_length.max(replay_length);
+    }
+}
+
+/// A command of a picked commit that doesn't apply cleanly to the current graph.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct CommandConflict {
+    /// The command, in explicit form.
+    pub command: GraphCommand,
+    pub conflict: Conflict,
+}
+
+/// The error returned when cherry-picking or reverting a commit.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum PickError {
+    /// The commit can't be found, or the current reference can't be modified.
+    History(String),
+    /// The commands of the commit don't apply cleanly to the current graph, which is left unchanged.
+    Conflicts(Vec<CommandConflict>),
+}
+
+impl fmt::Display for PickError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PickError::History(message) => write!(f, "{}", message),
+            PickError::Conflicts(conflicts) => {
+                write!(f, "{} conflicting command(s):", conflicts.len())?;
+                for CommandConflict { command, conflict } in conflicts {
+                    write!(f, " {}: {};", command, conflict)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl From<String> for PickError {
+    fn from(message: String) -> PickError {
+        PickError::History(message)
+    }
+}
+
+/// Hashes the commands and the metadata of a commit, chained with the hashes of its parents.
+///
+/// It uses 64-bit FNV-1a on a fixed byte encoding of the commit, so that the hashes are stable
+/// across platforms and compiler versions.
 pub struct CommandHasher { }
 
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+fn fnv_write(state: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(state, |h, &b| (h ^ u64::from(b)).wrapping_mul(FNV_PRIME))
+}
+
+// writes the length of a string before its bytes, so that consecutive strings can't be confused
+fn fnv_write_str(state: u64, text: &str) -> u64 {
+    let state = fnv_write(state, &(text.len() as u64).to_le_bytes());
+    fnv_write(state, text.as_bytes())
+}
+
 impl Hasher<Commands> for CommandHasher {
-    fn hash(&self, item: &Commands, previous: Option<NodeHash>) -> NodeHash {
-        unimplemented!()
+    fn hash(&self, item: &Commands, info: &CommitInfo, timestamp: Timestamp, parents: &[NodeHash]) -> NodeHash {
+        use GraphCommand::*;
+        let mut state = FNV_OFFSET_BASIS;
+        state = fnv_write(state, &(parents.len() as u64).to_le_bytes());
+        for &NodeHash(parent) in parents {
+            state = fnv_write(state, &parent.to_le_bytes());
+        }
+        state = fnv_write_str(state, &info.author.0);
+        match info.author_email.as_ref() {
+            None => state = fnv_write(state, &[0]),
+            Some(email) => state = fnv_write_str(fnv_write(state, &[1]), email),
+        }
+        state = fnv_write(state, &info.author_timestamp.unwrap_or(timestamp).0.to_le_bytes());
+        state = fnv_write(state, &timestamp.0.to_le_bytes());
+        state = fnv_write_str(state, &info.comment.0);
+        state = fnv_write(state, &(info.trailers.len() as u64).to_le_bytes());
+        for (key, value) in info.trailers.iter() {
+            state = fnv_write_str(fnv_write_str(state, key), value);
+        }
+        state = fnv_write(state, &(item.len() as u64).to_le_bytes());
+        for command in item {
+            let (tag, v1, v2) = match *command {
+                AddVertex(VertexId(v)) => (0u8, v, 0),
+                RemoveVertex(VertexId(v)) => (1u8, v, 0),
+                AddEdge(VertexId(v1), VertexId(v2)) => (2u8, v1, v2),
+                RemoveEdge(VertexId(v1), VertexId(v2)) => (3u8, v1, v2),
+            };
+            state = fnv_write(state, &[tag]);
+            state = fnv_write(state, &v1.to_le_bytes());
+            state = fnv_write(state, &v2.to_le_bytes());
+        }
+        NodeHash(state)
     }
 }
 
+/// A `DirectedGraph` that records every mutation as a commit of a `Repository`.
+///
+/// The commands are committed in their explicit form (see `GraphCommand::explicit_commands`), so
+/// that a commit can be undone exactly by reverting its commands.
 pub struct HistorizedGraph {
     repository: Repository<Commands, CommandHasher>,
     graph: DirectedGraph,
+    // hashes of the undone commits, the most recently undone last
+    redo_buffer: Vec<NodeHash>,
+    // states of the graph after some commits, used to materialize past states.
+    // As a hash is chained with the hash of the previous commit, it identifies a whole history.
+    checkpoints: RefCell<HashMap<NodeHash, DirectedGraph>>,
+    checkpoint_policy: CheckpointPolicy,
+    replay_metrics: Cell<ReplayMetrics>,
+    // the metadata of the next commits
+    commit_info: CommitInfo,
+    // the keys of the vertices bound by all the commits and the pending ones, which are never
+    // unbound, so that a key has the same vertex id in all the branches
+    keys: KeyMap,
+    // the bindings that the next commit records
+    pending_keys: KeyMap,
+    // greater than all the vertex ids of the commits and of the keys
+    next_vertex_id: u64,
+}
+
+impl Default for HistorizedGraph {
+    fn default() -> HistorizedGraph {
+        HistorizedGraph::new()
+    }
 }
 
 impl HistorizedGraph {
 
-    fn is_empty(&self) -> bool {
+    /// Creates an empty `HistorizedGraph` that allows parallel edges.
+    pub fn new() -> HistorizedGraph {
+        HistorizedGraph::with_mode(GraphMode::Multi)
+    }
+
+    /// Creates an empty `HistorizedGraph` with the given `mode`.
+    pub fn with_mode(mode: GraphMode) -> HistorizedGraph {
+        HistorizedGraph {
+            repository: Repository::new(CommandHasher {}),
+            graph: DirectedGraph::with_mode(mode),
+            redo_buffer: Vec::new(),
+            checkpoints: RefCell::new(HashMap::new()),
+            checkpoint_policy: CheckpointPolicy::default(),
+            replay_metrics: Cell::new(ReplayMetrics::default()),
+            commit_info: default_commit_info(),
+            keys: KeyMap::new(),
+            pending_keys: KeyMap::new(),
+            next_vertex_id: 0,
+        }
+    }
+
+    /// Restores a `HistorizedGraph` from a repository, a redo buffer, checkpoints and the key
+    /// bindings that no commit records yet, e.g. after they have been read from a storage. The
+    /// graph is rebuilt by replaying the commits of the current reference from the nearest
+    /// checkpoint.
+    pub fn restore(
+        repository: Repository<Commands, CommandHasher>,
+        mode: GraphMode,
+        redo_buffer: Vec<NodeHash>,
+        checkpoint_policy: CheckpointPolicy,
+        checkpoints: Vec<(NodeHash, DirectedGraph)>,
+        pending_keys: KeyMap,
+    ) -> Result<HistorizedGraph, String> {
+        let mut keys = KeyMap::new();
+        for commit in repository.all_commits() {
+            keys.insert_trailers(commit.trailers());
+        }
+        for (key, vertex_id) in pending_keys.iter() {
+            keys.insert(key, vertex_id);
+        }
+        let next_vertex_id = repository.all_commits()
+            .flat_map(|commit| commit.item().iter().flat_map(vertex_ids))
+            .chain(keys.iter().map(|(_, vertex_id)| vertex_id))
+            .map(|VertexId(id)| id.saturating_add(1))
+            .max()
+            .unwrap_or(0);
+        let mut graph = HistorizedGraph {
+            repository,
+            graph: DirectedGraph::with_mode(mode),
+            redo_buffer,
+            checkpoints: RefCell::new(checkpoints.into_iter().collect()),
+            checkpoint_policy,
+            replay_metrics: Cell::new(ReplayMetrics::default()),
+            commit_info: default_commit_info(),
+            keys,
+            pending_keys,
+            next_vertex_id,
+        };
+        graph.graph = graph.materialize(graph.repository.current().hashs())?;
+        Ok(graph)
+    }
+
+    pub fn repository(&self) -> &Repository<Commands, CommandHasher> {
+        &self.repository
+    }
+
+    /// Returns the hashes of the commits that can be redone, the next one last.
+    pub fn redo_buffer(&self) -> &[NodeHash] {
+        &self.redo_buffer
+    }
+
+    /// Returns the current state of the graph.
+    pub fn graph(&self) -> &DirectedGraph {
+        &self.graph
+    }
+
+    pub fn mode(&self) -> GraphMode {
+        self.graph.mode()
+    }
+
+    pub fn checkpoint_policy(&self) -> CheckpointPolicy {
+        self.checkpoint_policy
+    }
+
+    pub fn set_checkpoint_policy(&mut self, checkpoint_policy: CheckpointPolicy) {
+        self.checkpoint_policy = checkpoint_policy;
+    }
+
+    /// Sets the metadata of the next commits. The author timestamp, if any, is used as is, so it
+    /// is usually left unset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::historized_graph::HistorizedGraph;
+    /// use histo_graph_core::history::history::{Author, Comment, CommitInfo};
+    /// use histo_graph_core::graph::graph::VertexId;
+    ///
+    /// let mut graph = HistorizedGraph::new();
+    /// graph.set_commit_info(CommitInfo::new(Author("lauren".to_string()), Comment("import".to_string()))
+    ///     .with_author_email("lauren@example.com"));
+    /// graph.add_vertex(VertexId(1));
+    ///
+    /// assert_eq!(graph.repository().head().unwrap().author_email(), Some("lauren@example.com"));
+    /// ```
+    pub fn set_commit_info(&mut self, commit_info: CommitInfo) {
+        self.commit_info = commit_info;
+    }
+
+    /// Returns the keys of the vertices: the bindings recorded by the commits of the current
+    /// reference, and the pending ones.
+    pub fn keys(&self) -> KeyMap {
+        let mut keys = self.keys_of(&self.repository.current().hashs());
+        for (key, vertex_id) in self.pending_keys.iter() {
+            keys.insert(key, vertex_id);
+        }
+        keys
+    }
+
+    /// Returns the keys of the vertices as of a revision: the bindings recorded by its commits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::historized_graph::HistorizedGraph;
+    /// use histo_graph_core::graph::graph::VertexId;
+    ///
+    /// let mut graph = HistorizedGraph::new();
+    /// graph.add_vertex(VertexId(1));
+    /// graph.tag("v1").unwrap();
+    /// let serde = graph.intern("serde");
+    /// graph.add_vertex(serde);
+    ///
+    /// assert_eq!(graph.keys_at("HEAD").unwrap().vertex_id("serde"), Some(serde));
+    /// assert_eq!(graph.keys_at("v1").unwrap().vertex_id("serde"), None);
+    /// ```
+    pub fn keys_at(&self, rev: &str) -> Result<KeyMap, String> {
+        Ok(self.keys_of(&self.repository.resolve(rev)?))
+    }
+
+    /// Returns the bindings that no commit records yet: they are recorded by the next commit.
+    pub fn pending_keys(&self) -> &KeyMap {
+        &self.pending_keys
+    }
+
+    fn keys_of(&self, hashs: &Hashs) -> KeyMap {
+        let commits: Vec<&Commit<Commands>> = hashs
+            .iter()
+            .filter_map(|hash| self.repository.find_commit(hash))
+            .collect();
+        let mut keys = KeyMap::new();
+        for commit in commits.into_iter().rev() {
+            keys.insert_trailers(commit.trailers());
+        }
+        keys
+    }
+
+    /// Returns the vertex id of `key`. A key without vertex id is bound to a fresh one, greater
+    /// than the vertex ids of all the commits and of the other keys.
+    ///
+    /// The binding is recorded by the next commit, as a `Key` trailer, unless the current
+    /// reference already records it. A key is never unbound: once bound, it keeps its vertex id
+    /// in all the branches, even after its vertex has been removed. Binding a key doesn't change
+    /// the graph.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::historized_graph::HistorizedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut graph = HistorizedGraph::new();
+    /// graph.add_vertex(VertexId(7));
+    ///
+    /// let serde = graph.intern("serde");
+    /// let tokio = graph.intern("tokio");
+    /// assert_eq!(serde, VertexId(8));
+    /// assert_eq!(graph.intern("serde"), serde);
+    ///
+    /// graph.add_edge(Edge(tokio, serde));
+    /// assert_eq!(graph.keys().key(VertexId(9)), Some("tokio"));
+    /// ```
+    pub fn intern(&mut self, key: &str) -> VertexId {
+        let vertex_id = match self.keys.vertex_id(key) {
+            Some(vertex_id) => vertex_id,
+            None => {
+                let vertex_id = VertexId(self.next_vertex_id);
+                self.keys.insert(key, vertex_id);
+                self.reserve_vertex_id(vertex_id);
+                vertex_id
+            }
+        };
+        self.pending_keys.insert(key, vertex_id);
+        vertex_id
+    }
+
+    /// Keeps `vertex_id` from being bound to a key by `intern`, e.g. when it is about to be added.
+    /// The vertex ids of the commits are reserved when they are committed.
+    pub fn reserve_vertex_id(&mut self, VertexId(id): VertexId) {
+        self.next_vertex_id = self.next_vertex_id.max(id.saturating_add(1));
+    }
+
+    /// Returns the number of commits replayed so far to rebuild states of the graph.
+    pub fn replay_metrics(&self) -> ReplayMetrics {
+        self.replay_metrics.get()
+    }
+
+    pub fn is_empty(&self) -> bool {
         self.graph.is_empty()
     }
-    fn vertex_count(&self) -> usize {
+    pub fn vertex_count(&self) -> usize {
         self.graph.vertex_count()
     }
-    fn edge_count(&self) -> usize {
+    pub fn edge_count(&self) -> usize {
         self.graph.edge_count()
     }
-    fn contains_vertex(&self, vertex_id: VertexId) -> bool {
+    pub fn contains_vertex(&self, vertex_id: VertexId) -> bool {
         self.graph.contains_vertex(vertex_id)
     }
-    fn vertices(&self) -> impl Iterator<Item=&VertexId> {
+    pub fn vertices(&self) -> impl Iterator<Item=&VertexId> {
         self.graph.vertices()
     }
-    fn contains_edge(&self, edge: Edge) -> bool {
+    pub fn contains_edge(&self, edge: Edge) -> bool {
         self.graph.contains_edge(edge)
     }
-    fn edges(&self) -> impl Iterator<Item=&Edge> {
+    pub fn edges(&self) -> impl Iterator<Item=&Edge> {
         self.graph.edges()
     }
 
-    fn outbound_edges(&self, vertex_id: VertexId) -> impl Iterator<Item=&Edge> {
+    pub fn outbound_edges(&self, vertex_id: VertexId) -> impl Iterator<Item=&Edge> {
         self.graph.outbound_edges(vertex_id)
     }
-    fn inbound_edges(&self, vertex_id: VertexId) -> impl Iterator<Item=&Edge> {
+    pub fn inbound_edges(&self, vertex_id: VertexId) -> impl Iterator<Item=&Edge> {
         self.graph.inbound_edges(vertex_id)
     }
-    fn degree_out(&self, vertex_id: VertexId) -> usize {
+    pub fn degree_out(&self, vertex_id: VertexId) -> usize {
         self.graph.degree_out(vertex_id)
     }
-    fn degree_in(&self, vertex_id: VertexId) -> usize {
+    pub fn degree_in(&self, vertex_id: VertexId) -> usize {
         self.graph.degree_in(vertex_id)
     }
 
-    fn add_vertex(&mut self, vertex_id: VertexId) -> bool {
-        match commit_command(self, GraphCommand::AddVertex(vertex_id)) {
-            Err(_) => false,
-            Ok(_) => self.graph.add_vertex(vertex_id),
+    // Commands that don't change the graph are not committed.
+    // Use `try_execute` to tell a read-only reference apart from a command that changes nothing.
+
+    /// Adds a vertex. Returns true if the graph already contained `vertex_id`.
+    /// Returns false if the current reference can't be modified, just as for a new vertex: callers
+    /// that must tell a read-only reference apart from an existing vertex have to use `try_execute`.
+    pub fn add_vertex(&mut self, vertex_id: VertexId) -> bool {
+        match execute_command(self, GraphCommand::AddVertex(vertex_id)) {
+            None => false,
+            Some(undo) => undo.is_empty(),
         }
     }
-    fn remove_vertex(&mut self, vertex_id: VertexId) -> bool {
-        match commit_command(self, GraphCommand::RemoveVertex(vertex_id)) {
-            Err(_) => false,
-            Ok(_) => self.graph.remove_vertex(vertex_id),
+    pub fn remove_vertex(&mut self, vertex_id: VertexId) -> bool {
+        match execute_command(self, GraphCommand::RemoveVertex(vertex_id)) {
+            None => false,
+            Some(undo) => !undo.is_empty(),
         }
     }
-    fn add_edge(&mut self, edge: Edge) -> bool {
+    pub fn add_edge(&mut self, edge: Edge) -> bool {
         let Edge(v1, v2) = edge;
-        match commit_command(self, GraphCommand::AddEdge(v1, v2)) {
-            Err(_) => false,
-            Ok(_) => {
-                self.graph.add_edge(edge);
-                true
-            },
+        match execute_command(self, GraphCommand::AddEdge(v1, v2)) {
+            None => false,
+            Some(undo) => !undo.is_empty(),
         }
     }
-    fn remove_edge(&mut self, edge: Edge) -> bool {
+    pub fn remove_edge(&mut self, edge: Edge) -> bool {
         let Edge(v1, v2) = edge;
-        match commit_command(self, GraphCommand::RemoveEdge(v1, v2)) {
+        match execute_command(self, GraphCommand::RemoveEdge(v1, v2)) {
+            None => false,
+            Some(undo) => !undo.is_empty(),
+        }
+    }
+
+
+    /// Applies the commands, and commits their explicit form as a single commit if they changed
+    /// the graph. Returns false if the current reference can't be modified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::historized_graph::HistorizedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    /// use histo_graph_core::graph::command::GraphCommand::*;
+    ///
+    /// let mut graph = HistorizedGraph::new();
+    /// assert!(graph.execute(&[AddEdge(VertexId(1), VertexId(2)), AddVertex(VertexId(3))]));
+    ///
+    /// assert_eq!(graph.vertex_count(), 3);
+    /// assert_eq!(graph.repository().commits().count(), 1);
+    /// ```
+    pub fn execute(&mut self, commands: &[GraphCommand]) -> bool {
+        execute_commands(self, commands).is_ok()
+    }
+
+    /// Applies the commands like `execute`. Returns whether they changed the graph, or an error if
+    /// the current reference can't be modified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::historized_graph::HistorizedGraph;
+    /// use histo_graph_core::graph::graph::VertexId;
+    /// use histo_graph_core::graph::command::GraphCommand::*;
+    ///
+    /// let mut graph = HistorizedGraph::new();
+    /// assert_eq!(graph.try_execute(&[AddVertex(VertexId(1))]), Ok(true));
+    /// assert_eq!(graph.try_execute(&[AddVertex(VertexId(1))]), Ok(false));
+    ///
+    /// graph.tag("v1").unwrap();
+    /// graph.checkout("v1").unwrap();
+    /// assert!(graph.try_execute(&[AddVertex(VertexId(2))]).is_err());
+    /// ```
+    pub fn try_execute(&mut self, commands: &[GraphCommand]) -> Result<bool, String> {
+        execute_commands(self, commands).map(|undo| !undo.is_empty())
+    }
+
+    // References: they don't change the graph, they are managed by the repository.
+
+    /// Tags the current commit with the given name.
+    /// Returns an error if a branch or a tag already has this name.
+    pub fn tag(&mut self, name: &str) -> Result<Ref, String> {
+        self.repository.tag(name)
+    }
+
+    /// Creates a branch with the given name on the current commit, without checking it out.
+    /// Returns an error if a branch or a tag already has this name.
+    pub fn branch(&mut self, name: &str) -> Result<Ref, String> {
+        self.repository.branch(name)
+    }
+
+    /// Deletes a branch, which must not be the current one.
+    pub fn delete_branch(&mut self, name: &str) -> Result<Ref, String> {
+        self.repository.delete_branch(name)
+    }
+
+    /// Deletes a tag. If the tag is checked out, the current reference becomes detached.
+    pub fn delete_tag(&mut self, name: &str) -> Result<Ref, String> {
+        self.repository.delete_tag(name)
+    }
+
+    /// Renames a branch, which may be the current one.
+    pub fn rename_branch(&mut self, name: &str, new_name: &str) -> Result<Ref, String> {
+        self.repository.rename_branch(name, new_name)
+    }
+
+    // Blame
+
+    /// Returns the commits of the current reference that added or removed the vertex, or an edge
+    /// from or to it, from the most recent to the oldest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::historized_graph::HistorizedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut graph = HistorizedGraph::new();
+    /// graph.add_vertex(VertexId(1));
+    /// graph.add_vertex(VertexId(2));
+    /// graph.add_edge(Edge(VertexId(2), VertexId(1)));
+    ///
+    /// assert_eq!(graph.vertex_history(VertexId(1)).len(), 2);
+    /// assert_eq!(graph.vertex_history(VertexId(3)).len(), 0);
+    /// ```
+    pub fn vertex_history(&self, vertex_id: VertexId) -> Vec<&Commit<Commands>> {
+        self.repository.find_commits(|commands| {
+            commands.iter().any(|command| command.touches_vertex(vertex_id))
+        })
+    }
+
+    /// Returns the commits of the current reference that added or removed the edge, from the most
+    /// recent to the oldest. This includes the removal of an edge along with one of its vertices.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::historized_graph::HistorizedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut graph = HistorizedGraph::new();
+    /// let edge = Edge(VertexId(1), VertexId(2));
+    /// graph.add_edge(edge);
+    /// graph.add_edge(Edge(VertexId(2), VertexId(3)));
+    /// graph.remove_vertex(VertexId(1));
+    ///
+    /// let history = graph.edge_history(edge);
+    /// assert_eq!(history.len(), 2);
+    /// assert_eq!(history[1].hash(), graph.repository().current().hashs().tail().tail().head());
+    /// ```
+    pub fn edge_history(&self, edge: Edge) -> Vec<&Commit<Commands>> {
+        // commits are in explicit form: removing a vertex is preceded by the removal of its edges
+        self.repository.find_commits(|commands| {
+            commands.iter().any(|command| command.touches_edge(edge))
+        })
+    }
+
+    // Time travel
+
+    /// Returns the state of the graph as of a revision, without changing the current reference.
+    ///
+    /// The revision can be `HEAD`, a branch, a tag or the (possibly abbreviated) hash of a commit.
+    /// The state is rebuilt from the nearest cached checkpoint, and checkpoints are cached along
+    /// the way, so that the next queries replay fewer commits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::historized_graph::HistorizedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut graph = HistorizedGraph::new();
+    /// graph.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// graph.tag("v1").unwrap();
+    /// graph.add_edge(Edge(VertexId(2), VertexId(3)));
+    ///
+    /// let past = graph.at("v1").unwrap();
+    /// assert_eq!(past.edge_count(), 1);
+    /// assert_eq!(graph.edge_count(), 2);
+    /// ```
+    pub fn at(&self, rev: &str) -> Result<DirectedGraph, String> {
+        let hashs = self.repository.resolve(rev)?;
+        self.materialize(hashs)
+    }
+
+    /// Returns the state of the graph on the current reference as of the given time, i.e. after
+    /// the last commit created at or before `timestamp`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::historized_graph::HistorizedGraph;
+    /// use histo_graph_core::graph::graph::VertexId;
+    /// use histo_graph_core::history::history::Timestamp;
+    ///
+    /// let mut graph = HistorizedGraph::new();
+    /// graph.add_vertex(VertexId(1));
+    ///
+    /// assert!(graph.at_time(Timestamp(0)).unwrap().is_empty());
+    /// assert_eq!(graph.at_time(Timestamp::now()).unwrap().vertex_count(), 1);
+    /// ```
+    pub fn at_time(&self, timestamp: Timestamp) -> Result<DirectedGraph, String> {
+        self.materialize(self.repository.resolve_time(timestamp))
+    }
+
+    /// Returns the metrics of the graph after each commit of the current reference, from the
+    /// first commit to the head, with the hash of the commit. The commits are replayed once, from
+    /// an empty graph.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::historized_graph::HistorizedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut graph = HistorizedGraph::new();
+    /// graph.add_vertex(VertexId(1));
+    /// graph.add_edge(Edge(VertexId(1), VertexId(2)));
+    ///
+    /// let edges: Vec<usize> = graph.metrics_history().iter().map(|(_, metrics)| metrics.edges).collect();
+    /// assert_eq!(edges, vec![0, 1]);
+    /// ```
+    pub fn metrics_history(&self) -> Vec<(NodeHash, GraphMetrics)> {
+        let hashs: Vec<NodeHash> = self.repository.current().hashs().iter().collect();
+        let mut graph = DirectedGraph::with_mode(self.mode());
+        hashs
+            .into_iter()
+            .rev()
+            .filter_map(|hash| self.repository.find_commit(hash))
+            .map(|commit| {
+                GraphCommand::apply_commands(commit.item().clone(), &mut graph);
+                (commit.hash(), graph.metrics())
+            })
+            .collect()
+    }
+
+    /// Checks out a revision, and rebuilds the graph from the nearest checkpoint.
+    /// The redo buffer is cleared.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::historized_graph::HistorizedGraph;
+    /// use histo_graph_core::graph::{command::GraphCommand, graph::VertexId};
+    ///
+    /// let mut graph = HistorizedGraph::new();
+    /// graph.add_vertex(VertexId(1));
+    /// graph.tag("v1").unwrap();
+    /// graph.add_vertex(VertexId(2));
+    ///
+    /// graph.checkout("v1").unwrap();
+    /// assert_eq!(graph.vertex_count(), 1);
+    /// // a tag can't be modified
+    /// assert!(graph.try_execute(&[GraphCommand::AddVertex(VertexId(3))]).is_err());
+    /// assert_eq!(graph.vertex_count(), 1);
+    /// ```
+    pub fn checkout(&mut self, rev: &str) -> Result<Ref, String> {
+        let hashs = self.repository.resolve(rev)?;
+        let graph = self.materialize(hashs)?;
+        let r = self.repository.checkout(rev)?;
+        self.graph = graph;
+        self.redo_buffer.clear();
+        Ok(r)
+    }
+
+    /// Keeps the current state of the graph as a checkpoint, whatever the checkpoint policy.
+    /// Returns false if there is no commit yet.
+    pub fn checkpoint(&self) -> bool {
+        match self.repository.current().hashs().head_option() {
+            None => false,
+            Some(hash) => {
+                self.checkpoints.borrow_mut().insert(hash, self.graph.clone());
+                true
+            }
+        }
+    }
+
+    /// Adds a checkpoint: the state of the graph after the commit `hash`, e.g. after it has been
+    /// read from a storage.
+    pub fn add_checkpoint(&self, hash: NodeHash, graph: DirectedGraph) {
+        self.checkpoints.borrow_mut().insert(hash, graph);
+    }
+
+    /// Returns the checkpoints, sorted by the hash of their commit.
+    pub fn checkpoints(&self) -> Vec<(NodeHash, DirectedGraph)> {
+        let mut checkpoints: Vec<(NodeHash, DirectedGraph)> = self.checkpoints
+            .borrow()
+            .iter()
+            .map(|(&hash, graph)| (hash, graph.clone()))
+            .collect();
+        checkpoints.sort_by_key(|&(hash, _)| hash);
+        checkpoints
+    }
+
+    /// Rebuilds the state of the graph after the given commits, from the most recent to the oldest.
+    fn materialize(&self, hashs: Hashs) -> Result<DirectedGraph, String> {
+        let mut checkpoints = self.checkpoints.borrow_mut();
+
+        // walks back to the nearest checkpoint, or to the root
+        let mut to_replay: Vec<NodeHash> = Vec::new();
+        let mut graph = DirectedGraph::with_mode(self.mode());
+        for hash in hashs.iter() {
+            if let Some(checkpoint) = checkpoints.get(&hash) {
+                graph = checkpoint.clone();
+                break;
+            }
+            to_replay.push(hash);
+        }
+
+        // checkpoints are taken at a fixed distance from the previous ones, without walking the
+        // history to the root, so that they are shared by the queries
+        for (replayed, &hash) in to_replay.iter().rev().enumerate() {
+            let commit = self.repository
+                .find_commit(hash)
+                .ok_or_else(|| format!("Commit {:?} doesn't exist", hash))?;
+            GraphCommand::apply_commands(commit.item().clone(), &mut graph);
+            if let CheckpointPolicy::Every(interval) = self.checkpoint_policy {
+                if interval > 0 && (replayed + 1) % interval == 0 {
+                    checkpoints.insert(hash, graph.clone());
+                }
+            }
+        }
+
+        let mut metrics = self.replay_metrics.get();
+        metrics.record(to_replay.len());
+        self.replay_metrics.set(metrics);
+        Ok(graph)
+    }
+
+    // Cherry-pick / Revert
+
+    /// Applies the commands of a commit of any reference on top of the current branch, and commits
+    /// them with the metadata of the picked commit and a `Cherry-picked-from` trailer.
+    ///
+    /// Returns the conflicts if the commands don't apply cleanly to the current graph, in which
+    /// case nothing is committed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::historized_graph::HistorizedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut graph = HistorizedGraph::new();
+    /// graph.add_vertex(VertexId(1));
+    /// graph.branch("feature").unwrap();
+    /// graph.checkout("feature").unwrap();
+    /// graph.add_edge(Edge(VertexId(1), VertexId(2)));
+    ///
+    /// graph.checkout("master").unwrap();
+    /// graph.cherry_pick("feature").unwrap();
+    /// assert!(graph.contains_edge(Edge(VertexId(1), VertexId(2))));
+    ///
+    /// // the vertex 2 has already been added
+    /// assert!(graph.cherry_pick("feature").is_err());
+    /// ```
+    pub fn cherry_pick(&mut self, rev: &str) -> Result<Ref, PickError> {
+        let hash = self.resolve_commit(rev)?;
+        let commands = self.find_commands(hash)?;
+        let undo = self.apply_cleanly(&commands)?;
+        self.commit_picked(undo, |repository| repository.cherry_pick(hash))
+    }
+
+    /// Commits the revert of a commit of any reference: its commands are reverted, in reverse
+    /// order. The commit has a `Revert "<comment>"` comment and a `Reverts` trailer.
+    ///
+    /// Returns the conflicts if the reverted commands don't apply cleanly to the current graph, in
+    /// which case nothing is committed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::historized_graph::HistorizedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut graph = HistorizedGraph::new();
+    /// graph.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// graph.tag("v1").unwrap();
+    /// graph.add_vertex(VertexId(3));
+    ///
+    /// graph.revert("v1").unwrap();
+    /// assert_eq!(graph.vertices().collect::<Vec<_>>(), vec![&VertexId(3)]);
+    /// assert_eq!(graph.repository().head().unwrap().comment().0, "Revert \"auto\"");
+    /// ```
+    pub fn revert(&mut self, rev: &str) -> Result<Ref, PickError> {
+        let hash = self.resolve_commit(rev)?;
+        let commands = GraphCommand::revert_commands(&self.find_commands(hash)?);
+        let comment = self.repository.find_commit(hash).map(|c| c.comment().0.clone()).unwrap_or_default();
+        let info = CommitInfo::new(self.commit_info.author.clone(), Comment(format!("Revert \"{}\"", comment)))
+            .with_trailer("Reverts", &hash.to_string());
+        let info = with_pending_keys(self, info);
+        let undo = self.apply_cleanly(&commands)?;
+        self.commit_picked(undo, |repository| repository.commit_with(commands, info, Timestamp::now()))?;
+        self.pending_keys = KeyMap::new();
+        Ok(self.repository.current().clone())
+    }
+
+    // Squash
+
+    /// Replaces the commits of the range `from..to` of the current branch by a single commit.
+    ///
+    /// The commands of the new commit are the diff between the states of the graph at `from` and
+    /// at `to`, so that a vertex or an edge added and removed again within the range disappears.
+    /// The commits that follow `to` are committed again on top of it, and the redo buffer is
+    /// cleared. The graph itself is unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::historized_graph::HistorizedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut graph = HistorizedGraph::new();
+    /// graph.add_vertex(VertexId(1));
+    /// graph.tag("v1").unwrap();
+    /// graph.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// graph.remove_vertex(VertexId(2));
+    /// graph.add_vertex(VertexId(3));
+    ///
+    /// graph.squash("v1", "HEAD").unwrap();
+    /// assert_eq!(graph.repository().commits().count(), 2);
+    /// assert_eq!(graph.repository().head().unwrap().item().len(), 1);
+    /// assert_eq!(graph.vertex_count(), 2);
+    /// ```
+    pub fn squash(&mut self, from: &str, to: &str) -> Result<Ref, String> {
+        let range = format!("{}..{}", from, to);
+        let count = self.repository.log(&range)?.count();
+        let commands = diff(&self.at(from)?, &self.at(to)?).as_commands();
+        let comment = Comment(format!("Squash {} commits {}..{}", count, from, to));
+        let mut info = CommitInfo::new(self.commit_info.author.clone(), comment);
+        // the squashed commit records the key bindings of the commits it replaces
+        let replaced = self.repository.log(&range)?.collect::<Result<Vec<_>, String>>()?;
+        for commit in replaced.into_iter().rev() {
+            info.trailers.extend(commit.trailers().iter().filter(|(name, _)| name == KEY_TRAILER).cloned());
+        }
+        let r = self.repository.squash(from, to, commands, info)?;
+        self.redo_buffer.clear();
+        Ok(r)
+    }
+
+    fn resolve_commit(&self, rev: &str) -> Result<NodeHash, String> {
+        self.repository
+            .resolve(rev)?
+            .head_option()
+            .ok_or_else(|| format!("Revision {} has no commit", rev))
+    }
+
+    fn find_commands(&self, hash: NodeHash) -> Result<Commands, String> {
+        self.repository
+            .find_commit(hash)
+            .map(|commit| commit.item().clone())
+            .ok_or_else(|| format!("Commit {:?} doesn't exist", hash))
+    }
+
+    /// Applies the commands to the graph if they all apply cleanly, and returns the commands that
+    /// revert them. Otherwise the graph is left unchanged.
+    fn apply_cleanly(&mut self, commands: &[GraphCommand]) -> Result<Commands, PickError> {
+        if self.repository.current().is_read_only() {
+            let message = format!("Cannot modify Reference {}", self.repository.current().name());
+            return Err(PickError::History(message));
+        }
+        let mut conflicts: Vec<CommandConflict> = Vec::new();
+        let mut undo: Commands = Vec::new();
+        for &command in commands {
+            if let Some(conflict) = command.conflict(&self.graph) {
+                conflicts.push(CommandConflict { command, conflict });
+            }
+            // the next commands are checked against the graph as the previous ones left it
+            let mut command_undo = command.apply_with_undo(&mut self.graph);
+            command_undo.append(&mut undo);
+            undo = command_undo;
+        }
+        if conflicts.is_empty() {
+            Ok(undo)
+        } else {
+            GraphCommand::apply_commands(undo, &mut self.graph);
+            Err(PickError::Conflicts(conflicts))
+        }
+    }
+
+    /// Commits applied commands, or reverts them if the commit fails.
+    fn commit_picked<F>(&mut self, undo: Commands, commit: F) -> Result<Ref, PickError>
+    where
+        F: FnOnce(&mut Repository<Commands, CommandHasher>) -> Result<Ref, String>,
+    {
+        match commit(&mut self.repository) {
+            Err(message) => {
+                GraphCommand::apply_commands(undo, &mut self.graph);
+                Err(PickError::History(message))
+            }
+            Ok(r) => {
+                self.redo_buffer.clear();
+                Ok(r)
+            }
+        }
+    }
+
+    // Undo / Redo
+
+    /// Undoes the most recent commit of the current branch, and reverts its commands on the graph.
+    /// The commit can be redone until the next mutation of the graph.
+    /// Returns false if there is no commit to undo, or if the current reference can't be modified.
+    pub fn undo(&mut self) -> bool {
+        match self.repository.uncommit() {
             Err(_) => false,
-            Ok(_) => self.graph.remove_edge(edge),
+            Ok(commit) => {
+                let undo = GraphCommand::revert_commands(commit.item());
+                GraphCommand::apply_commands(undo, &mut self.graph);
+                self.redo_buffer.push(commit.hash());
+                true
+            }
         }
     }
+
+    /// Redoes the most recently undone commit, and applies its commands to the graph.
+    /// Returns false if there is no commit to redo, or if the current reference can't be modified.
+    pub fn redo(&mut self) -> bool {
+        let hash = match self.redo_buffer.last() {
+            None => return false,
+            Some(&hash) => hash,
+        };
+        if self.repository.recommit(hash).is_err() {
+            return false;
+        }
+        self.redo_buffer.pop();
+        let commands = self.repository.head().map(|c| c.item().clone()).unwrap_or_default();
+        GraphCommand::apply_commands(commands, &mut self.graph);
+        true
+    }
 }
 
 // Helpers
 
-fn commit_command(repo: &mut HistorizedGraph, command: GraphCommand) -> Result<Ref, String> {
-    repo.repository.commit(
-        vec![command],
-        Author("auto".to_string()),
-        Comment("auto".to_string()),
-    )
+/// Applies the command to the graph, and commits its explicit form if it changed the graph.
+/// A commit clears the redo buffer.
+/// Returns the commands that revert it, or `None` if the current reference can't be modified.
+fn execute_command(repo: &mut HistorizedGraph, command: GraphCommand) -> Option<Vec<GraphCommand>> {
+    execute_commands(repo, &[command]).ok()
+}
+
+/// Applies the commands to the graph, and commits their explicit form as a single commit if they
+/// changed the graph. See `execute_command`, the error tells why nothing could be committed.
+fn execute_commands(repo: &mut HistorizedGraph, commands: &[GraphCommand]) -> Result<Vec<GraphCommand>, String> {
+    if repo.repository.current().is_read_only() {
+        return Err(format!("Cannot modify Reference {}", repo.repository.current().name()));
+    }
+    let undo = GraphCommand::apply_commands_with_undo(commands, &mut repo.graph);
+    if undo.is_empty() {
+        return Ok(undo);
+    }
+    match commit_commands(repo, GraphCommand::revert_commands(&undo)) {
+        Err(message) => {
+            GraphCommand::apply_commands(undo, &mut repo.graph);
+            Err(message)
+        }
+        Ok(_) => {
+            repo.redo_buffer.clear();
+            for vertex_id in commands.iter().flat_map(vertex_ids) {
+                repo.reserve_vertex_id(vertex_id);
+            }
+            Ok(undo)
+        }
+    }
+}
+
+fn vertex_ids(command: &GraphCommand) -> Vec<VertexId> {
+    match *command {
+        GraphCommand::AddVertex(vertex_id) | GraphCommand::RemoveVertex(vertex_id) => vec![vertex_id],
+        GraphCommand::AddEdge(v1, v2) | GraphCommand::RemoveEdge(v1, v2) => vec![v1, v2],
+    }
+}
+
+fn commit_commands(repo: &mut HistorizedGraph, commands: Commands) -> Result<Ref, String> {
+    let info = with_pending_keys(repo, repo.commit_info.clone());
+    let r = repo.repository.commit_with(commands, info, Timestamp::now())?;
+    repo.pending_keys = KeyMap::new();
+    Ok(r)
+}
+
+/// Adds the pending key bindings that the current reference doesn't record yet to the trailers of
+/// a commit.
+fn with_pending_keys(repo: &HistorizedGraph, mut info: CommitInfo) -> CommitInfo {
+    if repo.pending_keys.is_empty() {
+        return info;
+    }
+    let recorded = repo.keys_of(&repo.repository.current().hashs());
+    let mut pending = KeyMap::new();
+    for (key, vertex_id) in repo.pending_keys.iter().filter(|&(key, _)| recorded.vertex_id(key).is_none()) {
+        pending.insert(key, vertex_id);
+    }
+    info.trailers.extend(pending.trailers());
+    info
+}
+
+fn default_commit_info() -> CommitInfo {
+    CommitInfo::new(Author("auto".to_string()), Comment("auto".to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_command_hasher_chains() {
+        let hasher = CommandHasher {};
+        let commands = vec![GraphCommand::AddEdge(VertexId(1), VertexId(2))];
+        let info = CommitInfo::new(Author("alice".to_string()), Comment("edge".to_string()));
+        let timestamp = Timestamp(10);
+
+        let first = hasher.hash(&commands, &info, timestamp, &[]);
+        let second = hasher.hash(&commands, &info, timestamp, &[first]);
+
+        assert_eq!(first, hasher.hash(&commands, &info, timestamp, &[]));
+        assert_ne!(first, second);
+        assert_ne!(first, hasher.hash(&vec![GraphCommand::AddEdge(VertexId(2), VertexId(1))], &info, timestamp, &[]));
+    }
+
+    #[test]
+    fn test_command_hasher_covers_metadata() {
+        let hasher = CommandHasher {};
+        let commands = vec![GraphCommand::AddVertex(VertexId(1))];
+        let info = CommitInfo::new(Author("alice".to_string()), Comment("vertex".to_string()));
+        let hash = hasher.hash(&commands, &info, Timestamp(10), &[]);
+
+        // an author timestamp that isn't set is the timestamp of the commit
+        assert_eq!(hash, hasher.hash(&commands, &info.clone().with_author_timestamp(Timestamp(10)), Timestamp(10), &[]));
+
+        let others = vec![
+            (CommitInfo::new(Author("bob".to_string()), Comment("vertex".to_string())), Timestamp(10)),
+            (info.clone().with_author_email("alice@example.com"), Timestamp(10)),
+            (info.clone().with_author_timestamp(Timestamp(5)), Timestamp(10)),
+            (info.clone(), Timestamp(11)),
+            (CommitInfo::new(Author("alice".to_string()), Comment("other".to_string())), Timestamp(10)),
+            (info.clone().with_trailer("Ticket", "GRAPH-12"), Timestamp(10)),
+            (CommitInfo::new(Author("ali".to_string()), Comment("cevertex".to_string())), Timestamp(10)),
+        ];
+        for (other, timestamp) in others {
+            assert_ne!(hash, hasher.hash(&commands, &other, timestamp, &[]));
+        }
+    }
+
+    #[test]
+    fn test_commit_does_not_replace_another_commit_with_the_same_hash() {
+        struct ConstantHasher { }
+        impl Hasher<Commands> for ConstantHasher {
+            fn hash(&self, _: &Commands, _: &CommitInfo, _: Timestamp, _: &[NodeHash]) -> NodeHash {
+                NodeHash(1)
+            }
+        }
+
+        let mut repository = Repository::new(ConstantHasher {});
+        repository.commit(vec![], Author("alice".to_string()), Comment("first".to_string())).unwrap();
+        assert!(repository.commit(vec![], Author("bob".to_string()), Comment("first".to_string())).is_err());
+        assert_eq!(repository.find_commit(NodeHash(1)).unwrap().author().0, "alice");
+    }
+
+    #[test]
+    fn test_same_commands_by_other_authors() {
+        let mut graph = HistorizedGraph::new();
+        graph.set_commit_info(CommitInfo::new(Author("alice".to_string()), Comment("add".to_string())));
+        graph.add_vertex(VertexId(1));
+        assert!(graph.undo());
+        graph.set_commit_info(CommitInfo::new(Author("bob".to_string()), Comment("add".to_string())));
+        graph.add_vertex(VertexId(1));
+
+        let authors: std::collections::HashSet<&str> = graph.repository()
+            .all_commits()
+            .map(|commit| commit.author().0.as_str())
+            .collect();
+        assert_eq!(authors, ["alice", "bob"].iter().copied().collect());
+    }
+
+    #[test]
+    fn test_simple_mode_rejects_parallel_edges() {
+        let mut graph = HistorizedGraph::with_mode(GraphMode::Simple);
+        let edge = Edge(VertexId(1), VertexId(2));
+
+        assert!(graph.add_edge(edge));
+        assert!(!graph.add_edge(edge));
+        assert_eq!(graph.edge_count(), 1);
+    }
+
+    #[test]
+    fn test_no_op_is_not_committed() {
+        let mut graph = HistorizedGraph::new();
+
+        assert!(!graph.add_vertex(VertexId(1)));
+        assert!(graph.add_vertex(VertexId(1)));
+        assert!(!graph.remove_edge(Edge(VertexId(1), VertexId(2))));
+        assert!(!graph.remove_vertex(VertexId(2)));
+
+        assert_eq!(graph.repository.current().hashs().iter().count(), 1);
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut graph = HistorizedGraph::new();
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+        graph.add_edge(Edge(VertexId(1), VertexId(3)));
+        graph.remove_vertex(VertexId(1));
+        assert!(!graph.is_empty());
+        assert_eq!(graph.edge_count(), 0);
+
+        // undoing the removal restores the edges of the vertex
+        assert!(graph.undo());
+        assert_eq!(graph.edge_count(), 2);
+
+        // undoing the addition of an edge removes the vertices it added
+        assert!(graph.undo());
+        assert!(!graph.contains_vertex(VertexId(3)));
+
+        assert!(graph.redo());
+        assert!(graph.redo());
+        assert!(!graph.redo());
+        assert!(!graph.contains_vertex(VertexId(1)));
+
+        assert!(graph.undo());
+        assert!(graph.undo());
+        assert!(graph.undo());
+        assert!(!graph.undo());
+        assert!(graph.is_empty());
+    }
+
+    #[test]
+    fn test_mutation_clears_redo_buffer() {
+        let mut graph = HistorizedGraph::new();
+        graph.add_vertex(VertexId(1));
+        graph.add_vertex(VertexId(2));
+
+        assert!(graph.undo());
+        assert_eq!(graph.redo_buffer().len(), 1);
+
+        graph.add_vertex(VertexId(3));
+        assert!(graph.redo_buffer().is_empty());
+        assert!(!graph.redo());
+    }
+
+    #[test]
+    fn test_restore() {
+        let mut graph = HistorizedGraph::with_mode(GraphMode::Simple);
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+        graph.add_edge(Edge(VertexId(2), VertexId(3)));
+        graph.undo();
+
+        let repository = Repository::restore(
+            CommandHasher {},
+            graph.repository().current().clone(),
+            graph.repository().list_refs().into_iter().cloned().collect(),
+            graph.repository().all_commits().cloned().collect(),
+            graph.repository().all_reflog().to_vec(),
+        );
+        let mut restored = HistorizedGraph::restore(
+            repository,
+            GraphMode::Simple,
+            graph.redo_buffer().to_vec(),
+            CheckpointPolicy::default(),
+            Vec::new(),
+            KeyMap::new(),
+        ).unwrap();
+        assert_eq!(restored.graph(), graph.graph());
+
+        assert!(restored.redo());
+        assert!(restored.contains_edge(Edge(VertexId(2), VertexId(3))));
+    }
+
+    #[test]
+    fn test_keys() {
+        let mut graph = HistorizedGraph::new();
+        let serde = graph.intern("serde");
+        graph.add_edge(Edge(serde, VertexId(10)));
+        graph.undo();
+
+        // the undone commit still reserves its vertex ids
+        let tokio = graph.intern("tokio");
+        assert_eq!((serde, tokio), (VertexId(0), VertexId(11)));
+        graph.reserve_vertex_id(VertexId(20));
+        assert_eq!(graph.intern("rand"), VertexId(21));
+
+        let repository = Repository::restore(
+            CommandHasher {},
+            graph.repository().current().clone(),
+            graph.repository().list_refs().into_iter().cloned().collect(),
+            graph.repository().all_commits().cloned().collect(),
+            graph.repository().all_reflog().to_vec(),
+        );
+        let mut restored = HistorizedGraph::restore(
+            repository,
+            GraphMode::Multi,
+            graph.redo_buffer().to_vec(),
+            CheckpointPolicy::default(),
+            Vec::new(),
+            graph.pending_keys().clone(),
+        ).unwrap();
+        assert_eq!(restored.intern("tokio"), tokio);
+        assert_eq!(restored.intern("libc"), VertexId(22));
+    }
+
+    #[test]
+    fn test_keys_are_recorded_by_commits() {
+        let mut graph = HistorizedGraph::new();
+        let serde = graph.intern("serde");
+        assert_eq!(graph.keys().vertex_id("serde"), Some(serde));
+        graph.add_vertex(serde);
+        assert_eq!(graph.repository().head().unwrap().trailer(KEY_TRAILER), Some("0 serde"));
+        assert!(graph.pending_keys().is_empty());
+
+        // a binding already recorded by the current reference isn't recorded again
+        graph.intern("serde");
+        let tokio = graph.intern("tokio");
+        graph.add_edge(Edge(tokio, serde));
+        let trailers: Vec<&str> = graph.repository().head().unwrap().trailers().iter().map(|(_, v)| v.as_str()).collect();
+        assert_eq!(trailers, vec!["1 tokio"]);
+
+        // an undone commit takes its bindings along, but a key keeps its vertex id
+        assert!(graph.undo());
+        assert_eq!(graph.keys().vertex_id("tokio"), None);
+        assert_eq!(graph.intern("tokio"), tokio);
+
+        // the squashed commit records the bindings of the commits it replaces
+        graph.tag("base").unwrap();
+        graph.add_edge(Edge(tokio, serde));
+        graph.add_vertex(VertexId(50));
+        graph.squash("base", "HEAD").unwrap();
+        assert_eq!(graph.repository().commits().count(), 2);
+        assert_eq!(graph.repository().head().unwrap().trailer(KEY_TRAILER), Some("1 tokio"));
+        assert_eq!(graph.keys(), graph.keys_at("HEAD").unwrap());
+        assert_eq!(graph.keys().vertex_id("tokio"), Some(tokio));
+    }
+
+    #[test]
+    fn test_at_revision() {
+        let mut graph = HistorizedGraph::new();
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+        let first = graph.repository().current().hashs().head();
+        graph.tag("first").unwrap();
+        graph.remove_vertex(VertexId(2));
+        graph.add_edge(Edge(VertexId(3), VertexId(3)));
+
+        let expected = graph.at("first").unwrap();
+        assert!(expected.contains_edge(Edge(VertexId(1), VertexId(2))));
+        assert_eq!(graph.at(&first.to_string()).unwrap(), expected);
+        assert_eq!(graph.at(&first.to_string()[..6]).unwrap(), expected);
+        assert_eq!(&graph.at("HEAD").unwrap(), graph.graph());
+        assert!(graph.at("unknown").is_err());
+
+        // time travel doesn't move the head
+        assert!(graph.contains_edge(Edge(VertexId(3), VertexId(3))));
+    }
+
+    #[test]
+    fn test_at_uses_checkpoints() {
+        let mut graph = HistorizedGraph::new();
+        let mut hashs = Vec::new();
+        for i in 0..200 {
+            graph.add_edge(Edge(VertexId(i), VertexId(i + 1)));
+            hashs.push(graph.repository().current().hashs().head());
+        }
+
+        assert_eq!(&graph.at("HEAD").unwrap(), graph.graph());
+        assert_eq!(graph.checkpoints().len(), 3);
+        assert_eq!(graph.replay_metrics().last_replay_length(), 200);
+
+        for (i, hash) in hashs.iter().enumerate() {
+            let past = graph.at(&hash.to_string()).unwrap();
+            assert_eq!(past.edge_count(), i + 1);
+            assert!(graph.replay_metrics().last_replay_length() < 64);
+        }
+        assert!(graph.replay_metrics().max_replay_length() == 200);
+    }
+
+    #[test]
+    fn test_checkpoint_on_demand() {
+        let mut graph = HistorizedGraph::new();
+        graph.set_checkpoint_policy(CheckpointPolicy::OnDemand);
+        assert!(!graph.checkpoint());
+
+        for i in 0..100 {
+            graph.add_vertex(VertexId(i));
+            if i == 49 {
+                assert!(graph.checkpoint());
+            }
+        }
+
+        assert_eq!(graph.at("HEAD").unwrap().vertex_count(), 100);
+        assert_eq!(graph.replay_metrics().last_replay_length(), 50);
+        assert_eq!(graph.checkpoints().len(), 1);
+    }
+
+    #[test]
+    fn test_restore_from_checkpoint() {
+        let mut graph = HistorizedGraph::new();
+        for i in 0..100 {
+            graph.add_vertex(VertexId(i));
+        }
+        graph.at("HEAD").unwrap();
+
+        let repository = Repository::restore(
+            CommandHasher {},
+            graph.repository().current().clone(),
+            graph.repository().list_refs().into_iter().cloned().collect(),
+            graph.repository().all_commits().cloned().collect(),
+            graph.repository().all_reflog().to_vec(),
+        );
+        let restored = HistorizedGraph::restore(
+            repository,
+            GraphMode::Multi,
+            Vec::new(),
+            CheckpointPolicy::default(),
+            graph.checkpoints(),
+            KeyMap::new(),
+        ).unwrap();
+
+        assert_eq!(restored.graph(), graph.graph());
+        assert_eq!(restored.replay_metrics().last_replay_length(), 100 - 64);
+    }
+
+    #[test]
+    fn test_checkout() {
+        let mut graph = HistorizedGraph::new();
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+        let first = graph.repository().current().hashs().head();
+        graph.add_edge(Edge(VertexId(2), VertexId(3)));
+        graph.undo();
+
+        let r = graph.checkout(&first.to_string()).unwrap();
+        assert!(r.is_read_only());
+        assert_eq!(graph.edge_count(), 1);
+        assert!(graph.redo_buffer().is_empty());
+        assert!(graph.checkout("unknown").is_err());
+
+        // the branch has been advanced by the commits
+        graph.checkout("master").unwrap();
+        assert_eq!(graph.edge_count(), 1);
+        assert!(graph.add_vertex(VertexId(1)));
+    }
+
+    #[test]
+    fn test_checkout_reflog() {
+        let mut graph = HistorizedGraph::new();
+        graph.add_vertex(VertexId(1));
+        graph.add_vertex(VertexId(2));
+        graph.add_vertex(VertexId(3));
+        graph.undo();
+        graph.undo();
+
+        let operations: Vec<&str> = graph.repository().reflog("HEAD").iter().map(|e| e.operation()).collect();
+        assert_eq!(operations, vec!["uncommit", "uncommit", "commit: auto", "commit: auto", "commit: auto"]);
+        assert_eq!(graph.repository().reflog("master").len(), 5);
+
+        // the undone commits aren't on any reference anymore, HEAD@{2} is the third commit
+        graph.checkout("HEAD@{2}").unwrap();
+        assert_eq!(graph.vertex_count(), 3);
+        assert!(graph.repository().current().is_read_only());
+
+        graph.checkout("master@{0}").unwrap();
+        assert_eq!(graph.vertex_count(), 1);
+        assert!(graph.checkout("HEAD@{10}").is_err());
+    }
+
+    #[test]
+    fn test_refs() {
+        let mut graph = HistorizedGraph::new();
+        graph.add_vertex(VertexId(1));
+        graph.branch("feature").unwrap();
+        graph.tag("v1").unwrap();
+        assert!(graph.tag("feature").is_err());
+        assert!(graph.branch("v1").is_err());
+
+        graph.checkout("feature").unwrap();
+        graph.add_vertex(VertexId(2));
+        graph.rename_branch("feature", "topic").unwrap();
+        graph.checkout("master").unwrap();
+        assert_eq!(graph.vertex_count(), 1);
+        graph.checkout("topic").unwrap();
+        assert_eq!(graph.vertex_count(), 2);
+
+        let names: Vec<&str> = graph.repository().list_refs().iter().map(|r| r.name()).collect();
+        assert_eq!(names, vec!["master", "topic", "v1"]);
+
+        assert!(graph.delete_branch("topic").is_err());
+        assert!(graph.delete_branch("v1").is_err());
+        graph.delete_branch("master").unwrap();
+        graph.checkout("v1").unwrap();
+        graph.delete_tag("v1").unwrap();
+        assert!(graph.repository().current().is_read_only());
+        assert_eq!(graph.repository().list_refs().len(), 1);
+    }
+
+    #[test]
+    fn test_cherry_pick() {
+        let mut graph = HistorizedGraph::new();
+        graph.add_vertex(VertexId(1));
+        graph.branch("feature").unwrap();
+        graph.checkout("feature").unwrap();
+        graph.set_commit_info(CommitInfo::new(Author("lauren".to_string()), Comment("edge".to_string())));
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+        let picked = graph.repository().head().unwrap().hash();
+
+        graph.checkout("master").unwrap();
+        graph.add_vertex(VertexId(3));
+        graph.cherry_pick("feature").unwrap();
+        assert!(graph.contains_edge(Edge(VertexId(1), VertexId(2))));
+        assert_eq!(graph.vertex_count(), 3);
+
+        let head = graph.repository().head().unwrap();
+        assert_eq!(head.author().0, "lauren");
+        assert_eq!(head.comment().0, "edge");
+        assert_eq!(head.trailer("Cherry-picked-from"), Some(picked.to_string().as_str()));
+        assert_eq!(graph.at("master").unwrap(), graph.graph().clone());
+
+        // picking again conflicts: the vertex 2 exists, the edge is allowed in a multigraph
+        let before = graph.graph().clone();
+        match graph.cherry_pick(&picked.to_string()) {
+            Err(PickError::Conflicts(conflicts)) => assert_eq!(conflicts, vec![CommandConflict {
+                command: GraphCommand::AddVertex(VertexId(2)),
+                conflict: Conflict::VertexExists,
+            }]),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(graph.graph(), &before);
+        assert!(graph.cherry_pick("unknown").is_err());
+    }
+
+    #[test]
+    fn test_revert() {
+        let mut graph = HistorizedGraph::with_mode(GraphMode::Simple);
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+        graph.tag("v1").unwrap();
+        graph.remove_vertex(VertexId(2));
+        graph.tag("v2").unwrap();
+
+        // the vertex 2 has been removed: the edge to it can't be removed
+        let before = graph.graph().clone();
+        match graph.revert("v1") {
+            Err(PickError::Conflicts(conflicts)) => {
+                let kinds: Vec<Conflict> = conflicts.iter().map(|c| c.conflict).collect();
+                assert_eq!(kinds, vec![Conflict::EdgeMissing, Conflict::VertexMissing]);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(graph.graph(), &before);
+
+        graph.revert("v2").unwrap();
+        assert!(graph.contains_edge(Edge(VertexId(1), VertexId(2))));
+        let head = graph.repository().head().unwrap();
+        assert_eq!(head.comment().0, "Revert \"auto\"");
+        let reverted = graph.repository().resolve("v2").unwrap().head();
+        assert_eq!(head.trailer("Reverts"), Some(reverted.to_string().as_str()));
+
+        // a tag can't be modified
+        graph.checkout("v1").unwrap();
+        assert!(matches!(graph.revert("v1"), Err(PickError::History(_))));
+    }
+
+    #[test]
+    fn test_squash() {
+        let mut graph = HistorizedGraph::new();
+        graph.add_vertex(VertexId(1));
+        graph.tag("v1").unwrap();
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+        graph.add_vertex(VertexId(3));
+        graph.remove_edge(Edge(VertexId(1), VertexId(2)));
+        graph.remove_vertex(VertexId(3));
+        graph.add_edge(Edge(VertexId(2), VertexId(4)));
+        graph.tag("v2").unwrap();
+        graph.add_vertex(VertexId(5));
+        graph.undo();
+        let before = graph.graph().clone();
+
+        graph.squash("v1", "v2").unwrap();
+        assert!(graph.redo_buffer().is_empty());
+        assert_eq!(graph.graph(), &before);
+        assert_eq!(graph.at("HEAD").unwrap(), before);
+
+        let commits: Vec<&Commit<Commands>> = graph.repository().commits().map(Result::unwrap).collect();
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].comment().0, "Squash 5 commits v1..v2");
+        assert_eq!(commits[0].item(), &vec![
+            GraphCommand::AddVertex(VertexId(2)),
+            GraphCommand::AddVertex(VertexId(4)),
+            GraphCommand::AddEdge(VertexId(2), VertexId(4)),
+        ]);
+        // the tag still points to the replaced commits
+        assert_eq!(graph.at("v2").unwrap(), before);
+
+        // the commits that follow the range are kept
+        graph.add_vertex(VertexId(6));
+        graph.add_vertex(VertexId(7));
+        graph.squash("v1", "HEAD@{1}").unwrap();
+        let comments: Vec<String> = graph.repository().commits().map(|c| c.unwrap().comment().0.clone()).collect();
+        assert_eq!(comments, vec!["auto", "Squash 2 commits v1..HEAD@{1}", "auto"]);
+        assert_eq!(graph.at("HEAD").unwrap(), graph.graph().clone());
+
+        assert!(graph.squash("HEAD", "v1").is_err());
+        assert!(graph.squash("v1", "v1").is_err());
+        graph.checkout("v1").unwrap();
+        assert!(graph.squash("v1", "v2").is_err());
+    }
+
+    #[test]
+    fn test_at_time() {
+        let mut graph = HistorizedGraph::new();
+        for (i, &(from, to)) in [(1, 2), (2, 3), (3, 4)].iter().enumerate() {
+            graph.repository.commit_at(
+                vec![GraphCommand::AddEdge(VertexId(from), VertexId(to))],
+                Author("test".to_string()),
+                Comment("test".to_string()),
+                Timestamp(100 * (i as u64 + 1)),
+            ).unwrap();
+        }
+
+        assert!(graph.at_time(Timestamp(99)).unwrap().is_empty());
+        assert_eq!(graph.at_time(Timestamp(100)).unwrap().edge_count(), 1);
+        assert_eq!(graph.at_time(Timestamp(250)).unwrap().edge_count(), 2);
+        assert_eq!(graph.at_time(Timestamp(1000)).unwrap().edge_count(), 3);
+    }
+
+    #[test]
+    fn test_blame() {
+        let mut graph = HistorizedGraph::new();
+        let edge = Edge(VertexId(1), VertexId(2));
+        graph.add_edge(edge);
+        graph.add_edge(edge);
+        graph.add_vertex(VertexId(3));
+        graph.remove_edge(edge);
+        graph.remove_vertex(VertexId(2));
+        graph.add_vertex(VertexId(4));
+
+        let commits: Vec<NodeHash> = graph.repository().current().hashs().iter().collect();
+
+        let vertex_history: Vec<NodeHash> = graph.vertex_history(VertexId(2)).iter().map(|c| c.hash()).collect();
+        assert_eq!(vertex_history, vec![commits[1], commits[2], commits[4], commits[5]]);
+
+        let edge_history: Vec<NodeHash> = graph.edge_history(edge).iter().map(|c| c.hash()).collect();
+        assert_eq!(edge_history, vec![commits[1], commits[2], commits[4], commits[5]]);
+
+        assert!(graph.edge_history(edge.reverse()).is_empty());
+    }
+
+    #[test]
+    fn test_multi_mode_accepts_parallel_edges() {
+        let mut graph = HistorizedGraph::new();
+        let edge = Edge(VertexId(1), VertexId(2));
+
+        assert!(graph.add_edge(edge));
+        assert!(graph.add_edge(edge));
+        assert_eq!(graph.graph().edge_multiplicity(edge), 2);
+    }
 }
@@ -1,8 +1,26 @@
+use std::fmt;
 use std::rc::Rc;
+use std::str::FromStr;
 
 // Node hash
-#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
-pub struct NodeHash(u64);
+#[derive(PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord, Debug)]
+pub struct NodeHash(pub u64);
+
+/// Formats the hash as 16 lowercase hexadecimal digits.
+impl fmt::Display for NodeHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Parses a hash from its hexadecimal representation.
+impl FromStr for NodeHash {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<NodeHash, Self::Err> {
+        u64::from_str_radix(s, 16).map(NodeHash)
+    }
+}
 
 // HashList
 #[derive(PartialEq, Eq, Clone, Hash, Debug)]
@@ -21,15 +39,15 @@ impl HashList {
     pub fn singleton(hash: NodeHash) -> Rc<HashList> {
         use HashList::*;
         Rc::new(Node {
-            hash: hash,
+            hash,
             tail: Rc::new(Nil),
         })
     }
 
     pub fn cons(hash: NodeHash, list: Rc<HashList>) -> Rc<HashList> {
         Rc::new(HashList::Node {
-            hash: hash,
-            tail: list.clone(),
+            hash,
+            tail: list,
         })
     }
 
@@ -51,10 +69,7 @@ impl HashList {
     // List functions
 
     pub fn is_empty(&self) -> bool {
-        match self {
-            HashList::Nil => true,
-            _ => false,
-        }
+        matches!(self, HashList::Nil)
     }
 
     pub fn head(&self) -> NodeHash {
@@ -118,18 +133,12 @@ impl HashList {
     where
         P: Fn(&NodeHash) -> bool,
     {
-        use HashList::*;
-        match self {
-            Node {
-                hash: x, tail: xs, ..
-            } => {
-                if predicate(x) {
-                    xs.take_while(predicate)
-                } else {
-                    xs.clone()
-                }
+        let mut current = Rc::new(self.clone());
+        loop {
+            match current.head_option() {
+                Some(x) if predicate(&x) => current = current.tail(),
+                _ => return current,
             }
-            _ => Rc::new(Nil),
         }
     }
 
@@ -151,7 +160,41 @@ impl Iterator for HashListIter {
     type Item = NodeHash;
     fn next(&mut self) -> Option<Self::Item> {
         let nxt = self.current.head_option();
-        self.current = self.current.tail().clone();
+        if let Some(tail) = self.current.tail_option() {
+            self.current = tail;
+        }
         nxt
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{HashList, NodeHash};
+
+    fn list(hashs: &[u64]) -> std::rc::Rc<HashList> {
+        hashs
+            .iter()
+            .rev()
+            .fold(HashList::empty(), |l, &h| HashList::cons(NodeHash(h), l))
+    }
+
+    #[test]
+    fn test_iter() {
+        let hashs: Vec<NodeHash> = list(&[3, 2, 1]).iter().collect();
+        assert_eq!(hashs, vec![NodeHash(3), NodeHash(2), NodeHash(1)]);
+        assert_eq!(HashList::empty().iter().count(), 0);
+    }
+
+    #[test]
+    fn test_skip_while() {
+        assert_eq!(list(&[4, 3, 2, 1]).skip_while(|&h| h != NodeHash(2)), list(&[2, 1]));
+        assert!(list(&[4, 3]).skip_while(|&h| h != NodeHash(2)).is_empty());
+    }
+
+    #[test]
+    fn test_display_and_parse() {
+        let hash = NodeHash(0x2a);
+        assert_eq!(hash.to_string(), "000000000000002a");
+        assert_eq!("000000000000002a".parse::<NodeHash>(), Ok(hash));
+    }
+}
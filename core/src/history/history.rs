@@ -1,13 +1,15 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use core::fmt::Debug;
 use core::hash::Hash;
 
 use crate::history::hashlist::*;
 use crate::history::history::Ref::*;
+use crate::history::log::Log;
 
-type Hashs = Rc<HashList>;
+pub type Hashs = Rc<HashList>;
 
 #[derive(PartialEq, Eq, Clone, Hash, Debug)]
 pub enum Ref {
@@ -22,23 +24,245 @@ pub struct Author(pub String);
 #[derive(PartialEq, Eq, Clone, Hash, Debug)]
 pub struct Comment(pub String);
 
+/// A point in time, in seconds since the UNIX epoch.
+#[derive(PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord, Debug)]
+pub struct Timestamp(pub u64);
+
+impl Timestamp {
+    /// Returns the current time
+    pub fn now() -> Timestamp {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Timestamp(seconds)
+    }
+}
+
+/// The metadata of a commit that are provided by its author.
+///
+/// # Examples
+///
+/// ```
+/// use histo_graph_core::history::history::{Author, Comment, CommitInfo, Timestamp};
+///
+/// let info = CommitInfo::new(Author("lauren".to_string()), Comment("fix".to_string()))
+///     .with_author_email("lauren@example.com")
+///     .with_author_timestamp(Timestamp(1_000))
+///     .with_trailer("Ticket", "GRAPH-12");
+///
+/// assert_eq!(info.author_email.as_deref(), Some("lauren@example.com"));
+/// ```
+#[derive(PartialEq, Eq, Clone, Hash, Debug)]
+pub struct CommitInfo {
+    pub author: Author,
+    pub author_email: Option<String>,
+    /// The time at which the change has been authored. Defaults to the time of the commit.
+    pub author_timestamp: Option<Timestamp>,
+    pub comment: Comment,
+    /// Free-form key/value pairs, e.g. ticket ids, in the order they have been added.
+    pub trailers: Vec<(String, String)>,
+}
+
+impl CommitInfo {
+    pub fn new(author: Author, comment: Comment) -> CommitInfo {
+        CommitInfo {
+            author,
+            author_email: None,
+            author_timestamp: None,
+            comment,
+            trailers: Vec::new(),
+        }
+    }
+
+    pub fn with_author_email(mut self, email: &str) -> CommitInfo {
+        self.author_email = Some(email.to_string());
+        self
+    }
+
+    pub fn with_author_timestamp(mut self, timestamp: Timestamp) -> CommitInfo {
+        self.author_timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn with_trailer(mut self, key: &str, value: &str) -> CommitInfo {
+        self.trailers.push((key.to_string(), value.to_string()));
+        self
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Hash, Debug)]
 pub struct Commit<Item>
 where
     Item: PartialEq + Eq + Clone + Hash + Debug,
 {
     author: Author,
+    author_email: Option<String>,
+    author_timestamp: Timestamp,
     comment: Comment,
+    timestamp: Timestamp,
+    parents: Vec<NodeHash>,
+    trailers: Vec<(String, String)>,
     hash: NodeHash,
     item: Item,
 }
 
-// Trait for a Hasher, in charge of hashing an Item for a repository
+impl<Item> Commit<Item>
+where
+    Item: PartialEq + Eq + Clone + Hash + Debug,
+{
+    /// Creates a commit without parents. The `hash` is expected to be the one computed by the
+    /// `Hasher` of the repository the commit belongs to. The author timestamp is the `timestamp`
+    /// of the commit.
+    pub fn new(author: Author, comment: Comment, timestamp: Timestamp, hash: NodeHash, item: Item) -> Commit<Item> {
+        Commit {
+            author,
+            author_email: None,
+            author_timestamp: timestamp,
+            comment,
+            timestamp,
+            parents: Vec::new(),
+            trailers: Vec::new(),
+            hash,
+            item,
+        }
+    }
+
+    /// Creates a commit from the metadata provided by its author.
+    pub fn with_info(info: CommitInfo, timestamp: Timestamp, parents: Vec<NodeHash>, hash: NodeHash, item: Item) -> Commit<Item> {
+        Commit {
+            author: info.author,
+            author_email: info.author_email,
+            author_timestamp: info.author_timestamp.unwrap_or(timestamp),
+            comment: info.comment,
+            timestamp,
+            parents,
+            trailers: info.trailers,
+            hash,
+            item,
+        }
+    }
+
+    pub fn author(&self) -> &Author {
+        &self.author
+    }
+
+    pub fn author_email(&self) -> Option<&str> {
+        self.author_email.as_deref()
+    }
+
+    /// The time at which the change has been authored
+    pub fn author_timestamp(&self) -> Timestamp {
+        self.author_timestamp
+    }
+
+    pub fn comment(&self) -> &Comment {
+        &self.comment
+    }
+
+    /// The time at which the commit has been created
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    /// The hashes of the commits this commit follows. A root commit has no parent.
+    pub fn parents(&self) -> &[NodeHash] {
+        &self.parents
+    }
+
+    /// The key/value trailers of the commit, in the order they have been added
+    pub fn trailers(&self) -> &[(String, String)] {
+        &self.trailers
+    }
+
+    /// Returns the value of the first trailer with the given key
+    pub fn trailer(&self, key: &str) -> Option<&str> {
+        self.trailers
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the metadata provided by the author of the commit
+    pub fn info(&self) -> CommitInfo {
+        CommitInfo {
+            author: self.author.clone(),
+            author_email: self.author_email.clone(),
+            author_timestamp: Some(self.author_timestamp),
+            comment: self.comment.clone(),
+            trailers: self.trailers.clone(),
+        }
+    }
+
+    pub fn hash(&self) -> NodeHash {
+        self.hash
+    }
+
+    pub fn item(&self) -> &Item {
+        &self.item
+    }
+}
+
+/// An entry of the reflog: a move of `HEAD`, i.e. of the current reference, or of a branch head.
+#[derive(PartialEq, Eq, Clone, Hash, Debug)]
+pub struct ReflogEntry {
+    reference: String,
+    old: Option<NodeHash>,
+    new: Option<NodeHash>,
+    operation: String,
+    timestamp: Timestamp,
+}
+
+impl ReflogEntry {
+    pub fn new(
+        reference: &str,
+        old: Option<NodeHash>,
+        new: Option<NodeHash>,
+        operation: &str,
+        timestamp: Timestamp,
+    ) -> ReflogEntry {
+        ReflogEntry {
+            reference: reference.to_string(),
+            old,
+            new,
+            operation: operation.to_string(),
+            timestamp,
+        }
+    }
+
+    /// `HEAD`, or the name of a branch
+    pub fn reference(&self) -> &str {
+        &self.reference
+    }
+
+    /// The hash of the commit the reference pointed to, `None` if it had no commit
+    pub fn old(&self) -> Option<NodeHash> {
+        self.old
+    }
+
+    /// The hash of the commit the reference points to, `None` if it has no commit
+    pub fn new_hash(&self) -> Option<NodeHash> {
+        self.new
+    }
+
+    /// A description of the operation that moved the reference
+    pub fn operation(&self) -> &str {
+        &self.operation
+    }
+
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+}
+
+// Trait for a Hasher, in charge of hashing the commits of an Item for a repository
 pub trait Hasher<Item>
 where
     Item: PartialEq + Eq + Clone + Hash + Debug,
 {
-    fn hash(&self, item: &Item, previous: Option<NodeHash>) -> NodeHash;
+    /// Hashes a commit: its item, its metadata, its timestamp and the hashes of its parents.
+    /// An author timestamp that isn't set is the `timestamp` of the commit.
+    fn hash(&self, item: &Item, info: &CommitInfo, timestamp: Timestamp, parents: &[NodeHash]) -> NodeHash;
 }
 
 /// Git-like Repository for an item
@@ -49,8 +273,11 @@ where
 {
     current: Ref,
     hasher: ItemHasher,
-    refs: Vec<Ref>,
+    // the branches and the tags, by name: a name is either a branch or a tag
+    refs: BTreeMap<String, Ref>,
     commits: HashMap<NodeHash, Commit<Item>>,
+    // the moves of HEAD and of the branch heads, the oldest first
+    reflog: Vec<ReflogEntry>,
 }
 
 impl Ref {
@@ -75,10 +302,7 @@ impl Ref {
     }
 
     pub fn is_read_only(&self) -> bool {
-        match self {
-            Tag(_, _) | Detached(_) => true,
-            _ => false,
-        }
+        matches!(self, Tag(_, _) | Detached(_))
     }
 }
 
@@ -88,20 +312,103 @@ where
     ItemHasher: Hasher<RepoItem>,
 {
     /// Creates a new Repository
-    pub fn new<I, H>(hasher: H) -> Repository<I, H>
-    where
-        I: PartialEq + Eq + Clone + Hash + Debug,
-        H: Hasher<I>,
-    {
+    pub fn new(hasher: ItemHasher) -> Repository<RepoItem, ItemHasher> {
         let master = Branch(HashList::empty(), "master".to_string());
+        let mut refs = BTreeMap::new();
+        refs.insert("master".to_string(), master.clone());
         Repository {
-            current: master.clone(),
-            hasher: hasher,
-            refs: vec![master],
+            current: master,
+            hasher,
+            refs,
             commits: HashMap::new(),
+            reflog: Vec::new(),
+        }
+    }
+
+    /// Restores a Repository from its parts, e.g. after it has been read from a storage.
+    /// Detached references are ignored, and if several references have the same name, the last
+    /// one is kept.
+    pub fn restore(
+        hasher: ItemHasher,
+        current: Ref,
+        refs: Vec<Ref>,
+        commits: Vec<Commit<RepoItem>>,
+        reflog: Vec<ReflogEntry>,
+    ) -> Repository<RepoItem, ItemHasher> {
+        Repository {
+            current,
+            hasher,
+            refs: refs
+                .into_iter()
+                .filter(|r| !matches!(r, Detached(_)))
+                .map(|r| (r.name().to_string(), r))
+                .collect(),
+            commits: commits.into_iter().map(|c| (c.hash, c)).collect(),
+            reflog,
         }
     }
 
+    /// Returns the current reference
+    pub fn current(&self) -> &Ref {
+        &self.current
+    }
+
+    /// Returns all the references: branches and tags, sorted by name
+    pub fn list_refs(&self) -> Vec<&Ref> {
+        self.refs.values().collect()
+    }
+
+    /// Returns the reflog of `HEAD` or of a branch, the most recent move first
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::history::history::{Author, Comment, Repository};
+    /// use histo_graph_core::historized_graph::CommandHasher;
+    ///
+    /// let mut repository = Repository::new(CommandHasher {});
+    /// repository.commit(vec![], Author("a".to_string()), Comment("first".to_string())).unwrap();
+    /// let first = repository.head().unwrap().hash();
+    /// repository.tag("v1").unwrap();
+    /// repository.checkout("v1").unwrap();
+    ///
+    /// let reflog = repository.reflog("HEAD");
+    /// assert_eq!(reflog.len(), 2);
+    /// assert_eq!(reflog[0].operation(), "checkout: moving from master to v1");
+    /// assert_eq!(reflog[1].new_hash(), Some(first));
+    /// assert_eq!(repository.reflog("master").len(), 1);
+    /// ```
+    pub fn reflog(&self, reference: &str) -> Vec<&ReflogEntry> {
+        self.reflog
+            .iter()
+            .rev()
+            .filter(|entry| entry.reference == reference)
+            .collect()
+    }
+
+    /// Returns the whole reflog, the oldest move first
+    pub fn all_reflog(&self) -> &[ReflogEntry] {
+        &self.reflog
+    }
+
+    /// Iterates through all the commits of the repository, in arbitrary order
+    pub fn all_commits(&self) -> impl Iterator<Item = &Commit<RepoItem>> {
+        self.commits.values()
+    }
+
+    /// Returns the commit with the given hash
+    pub fn find_commit(&self, hash: NodeHash) -> Option<&Commit<RepoItem>> {
+        self.commits.get(&hash)
+    }
+
+    /// Returns the most recent commit of the current reference
+    pub fn head(&self) -> Option<&Commit<RepoItem>> {
+        self.current
+            .hashs()
+            .head_option()
+            .and_then(|hash| self.find_commit(hash))
+    }
+
     /// Creates and add a new commit to the current branch
     /// returns an error if current selection is not a branch
     pub fn commit(
@@ -110,77 +417,486 @@ where
         author: Author,
         comment: Comment,
     ) -> Result<Ref, String> {
+        self.commit_at(item, author, comment, Timestamp::now())
+    }
+
+    /// Creates and add a new commit with the given timestamp to the current branch
+    /// returns an error if current selection is not a branch
+    pub fn commit_at(
+        &mut self,
+        item: RepoItem,
+        author: Author,
+        comment: Comment,
+        timestamp: Timestamp,
+    ) -> Result<Ref, String> {
+        self.commit_with(item, CommitInfo::new(author, comment), timestamp)
+    }
+
+    /// Creates and add a new commit with the given metadata and timestamp to the current branch
+    /// returns an error if current selection is not a branch
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::history::history::{Author, Comment, CommitInfo, Repository, Timestamp};
+    /// use histo_graph_core::historized_graph::CommandHasher;
+    ///
+    /// let mut repository = Repository::new(CommandHasher {});
+    /// repository.commit(vec![], Author("a".to_string()), Comment("first".to_string())).unwrap();
+    /// let first = repository.head().unwrap().hash();
+    ///
+    /// let info = CommitInfo::new(Author("b".to_string()), Comment("second".to_string()))
+    ///     .with_author_timestamp(Timestamp(10))
+    ///     .with_trailer("Ticket", "GRAPH-12");
+    /// repository.commit_with(vec![], info, Timestamp(20)).unwrap();
+    ///
+    /// let head = repository.head().unwrap();
+    /// assert_eq!(head.parents(), &[first]);
+    /// assert_eq!(head.author_timestamp(), Timestamp(10));
+    /// assert_eq!(head.timestamp(), Timestamp(20));
+    /// assert_eq!(head.trailer("Ticket"), Some("GRAPH-12"));
+    /// ```
+    pub fn commit_with(
+        &mut self,
+        item: RepoItem,
+        info: CommitInfo,
+        timestamp: Timestamp,
+    ) -> Result<Ref, String> {
+        let operation = format!("commit: {}", info.comment.0);
+        self.append_commit(item, info, timestamp, &operation)
+    }
+
+    /// Creates a new commit on the current branch with the item of an existing commit, and its
+    /// metadata along with a `Cherry-picked-from` trailer.
+    /// returns an error if current selection is not a branch, or if the commit doesn't exist
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::history::history::{Author, Comment, Repository};
+    /// use histo_graph_core::historized_graph::CommandHasher;
+    ///
+    /// let mut repository = Repository::new(CommandHasher {});
+    /// repository.commit(vec![], Author("a".to_string()), Comment("first".to_string())).unwrap();
+    /// let first = repository.head().unwrap().hash();
+    /// repository.commit(vec![], Author("a".to_string()), Comment("second".to_string())).unwrap();
+    ///
+    /// repository.cherry_pick(first).unwrap();
+    /// let head = repository.head().unwrap();
+    /// assert_eq!(head.comment().0, "first");
+    /// assert_eq!(head.trailer("Cherry-picked-from"), Some(first.to_string().as_str()));
+    /// assert_ne!(head.hash(), first);
+    /// ```
+    pub fn cherry_pick(&mut self, hash: NodeHash) -> Result<Ref, String> {
+        let commit = self.find_commit(hash)
+            .ok_or_else(|| format!("Commit {:?} doesn't exist", hash))?;
+        let item = commit.item().clone();
+        let info = commit.info().with_trailer("Cherry-picked-from", &hash.to_string());
+        let operation = format!("cherry-pick: {}", info.comment.0);
+        self.append_commit(item, info, Timestamp::now(), &operation)
+    }
+
+    fn append_commit(&mut self, item: RepoItem, info: CommitInfo, timestamp: Timestamp, operation: &str) -> Result<Ref, String> {
         if self.current.is_read_only() {
             Err(format!("Cannot modify Reference {}", self.current.name()))
         } else {
-            let commit = self.create_commit(item, author, comment);
+            let commit = self.create_commit(item, info, timestamp);
             let new_head = HashList::cons(commit.hash, self.current.hashs());
             // Updating repo
-            self.commits.insert(commit.hash, commit);
-            self.current = Branch(new_head.clone(), self.current.name().to_string());
+            self.insert_commit(commit)?;
+            self.move_current_branch(new_head, operation, timestamp);
             Ok(self.current.clone())
         }
     }
 
-    fn create_commit(&self, item: RepoItem, author: Author, comment: Comment) -> Commit<RepoItem> {
+    /// Removes the most recent commit from the current branch, and returns it.
+    /// The commit is kept in the repository, so that it can be put back with `recommit`.
+    /// returns an error if current selection is not a branch, or if it has no commit
+    pub fn uncommit(&mut self) -> Result<Commit<RepoItem>, String> {
+        if self.current.is_read_only() {
+            return Err(format!("Cannot modify Reference {}", self.current.name()));
+        }
+        let hashs = self.current.hashs();
+        match (hashs.head_option(), hashs.tail_option()) {
+            (Some(hash), Some(tail)) => {
+                let commit = self.find_commit(hash)
+                    .cloned()
+                    .ok_or_else(|| format!("Commit {:?} doesn't exist", hash))?;
+                self.move_current_branch(tail, "uncommit", Timestamp::now());
+                Ok(commit)
+            }
+            _ => Err(format!("Reference {} has no commit", self.current.name())),
+        }
+    }
+
+    /// Puts an existing commit back on top of the current branch.
+    /// returns an error if current selection is not a branch, or if the commit doesn't follow
+    /// the head of the current branch
+    pub fn recommit(&mut self, hash: NodeHash) -> Result<Ref, String> {
+        if self.current.is_read_only() {
+            return Err(format!("Cannot modify Reference {}", self.current.name()));
+        }
+        let commit = self.find_commit(hash)
+            .ok_or_else(|| format!("Commit {:?} doesn't exist", hash))?;
         let last_hash = self.current.hashs().head_option();
-        let commit_hash = self.hasher.hash(&item, last_hash);
-        Commit {
-            author: author,
-            comment: comment,
-            hash: commit_hash,
-            item: item,
+        if commit.parents != last_hash.into_iter().collect::<Vec<NodeHash>>() {
+            return Err(format!(
+                "Commit {:?} doesn't follow the head of {}",
+                hash,
+                self.current.name()
+            ));
         }
+        let new_head = HashList::cons(hash, self.current.hashs());
+        self.move_current_branch(new_head, "recommit", Timestamp::now());
+        Ok(self.current.clone())
+    }
+
+    /// Replaces the commits of the range `from..to` of the current branch by a single commit of
+    /// `item`, which must have the same effect. The commits that follow `to` are committed again
+    /// on top of it, with their metadata: they keep their author timestamps, and get the time of
+    /// the squash as the timestamp of the commit.
+    /// The replaced commits are kept in the repository, so that they can still be resolved.
+    /// returns an error if current selection is not a branch, if `to` isn't on the current branch,
+    /// or if `from` isn't one of its ancestors
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::history::history::{Author, Comment, CommitInfo, Repository, Timestamp};
+    /// use histo_graph_core::historized_graph::CommandHasher;
+    ///
+    /// let mut repository = Repository::new(CommandHasher {});
+    /// for (i, comment) in ["first", "second", "third", "fourth"].iter().enumerate() {
+    ///     repository.commit_at(vec![], Author("a".to_string()), Comment(comment.to_string()), Timestamp(i as u64)).unwrap();
+    ///     if *comment == "first" {
+    ///         repository.tag("v1").unwrap();
+    ///     }
+    /// }
+    /// let fourth = repository.head().unwrap().hash();
+    ///
+    /// let info = CommitInfo::new(Author("a".to_string()), Comment("squashed".to_string()));
+    /// repository.squash("v1", "HEAD@{1}", vec![], info).unwrap();
+    ///
+    /// let comments: Vec<String> = repository.commits().map(|c| c.unwrap().comment().0.clone()).collect();
+    /// assert_eq!(comments, vec!["fourth", "squashed", "first"]);
+    ///
+    /// // the rewritten commit keeps its author timestamp, the replaced one is kept as it was
+    /// let head = repository.head().unwrap();
+    /// assert_eq!(head.author_timestamp(), Timestamp(3));
+    /// assert!(head.timestamp() > Timestamp(3));
+    /// assert_eq!(repository.find_commit(fourth).unwrap().timestamp(), Timestamp(3));
+    /// assert_eq!(repository.all_commits().count(), 6);
+    /// ```
+    pub fn squash(&mut self, from: &str, to: &str, item: RepoItem, info: CommitInfo) -> Result<Ref, String> {
+        if self.current.is_read_only() {
+            return Err(format!("Cannot modify Reference {}", self.current.name()));
+        }
+        let base = self.resolve(from)?;
+        let base_hash = base.head_option();
+        let to_hash = self.resolve(to)?
+            .head_option()
+            .ok_or_else(|| format!("Revision {} has no commit", to))?;
+
+        // the commits that follow `to`, from the most recent to the oldest
+        let mut following: Vec<NodeHash> = Vec::new();
+        let mut hashes = self.current.hashs().iter();
+        loop {
+            match hashes.next() {
+                None => return Err(format!("Revision {} is not on {}", to, self.current.name())),
+                Some(hash) if hash == to_hash => break,
+                Some(hash) => following.push(hash),
+            }
+        }
+        if base_hash.is_some() && !hashes.any(|hash| Some(hash) == base_hash) {
+            return Err(format!("Revision {} is not an ancestor of {}", from, to));
+        }
+
+        let timestamp = Timestamp::now();
+        let mut commits = vec![self.build_commit(item, info, timestamp, base_hash)];
+        for hash in following.into_iter().rev() {
+            let commit = self.find_commit(hash)
+                .ok_or_else(|| format!("Commit {:?} doesn't exist", hash))?;
+            let previous = commits.last().map(|c| c.hash);
+            commits.push(self.build_commit(commit.item.clone(), commit.info(), timestamp, previous));
+        }
+        for commit in commits.iter() {
+            self.check_insertable(commit)?;
+        }
+
+        let mut new_head = base;
+        for commit in commits {
+            new_head = HashList::cons(commit.hash, new_head);
+            self.commits.insert(commit.hash, commit);
+        }
+        self.move_current_branch(new_head, &format!("squash: {}..{}", from, to), timestamp);
+        Ok(self.current.clone())
+    }
+
+    fn create_commit(&self, item: RepoItem, info: CommitInfo, timestamp: Timestamp) -> Commit<RepoItem> {
+        self.build_commit(item, info, timestamp, self.current.hashs().head_option())
+    }
+
+    fn build_commit(&self, item: RepoItem, info: CommitInfo, timestamp: Timestamp, previous: Option<NodeHash>) -> Commit<RepoItem> {
+        let parents: Vec<NodeHash> = previous.into_iter().collect();
+        let commit_hash = self.hasher.hash(&item, &info, timestamp, &parents);
+        Commit::with_info(info, timestamp, parents, commit_hash, item)
+    }
+
+    /// Adds a commit to the repository.
+    /// returns an error if the repository already has a different commit with the same hash
+    fn insert_commit(&mut self, commit: Commit<RepoItem>) -> Result<(), String> {
+        self.check_insertable(&commit)?;
+        self.commits.insert(commit.hash, commit);
+        Ok(())
+    }
+
+    fn check_insertable(&self, commit: &Commit<RepoItem>) -> Result<(), String> {
+        match self.commits.get(&commit.hash) {
+            Some(existing) if existing != commit => Err(format!("Commit {} already exists with another content", commit.hash)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Resolves a revision to the hashes of its history, from the most recent to the oldest.
+    ///
+    /// The revision can be `HEAD`, the name of a branch or a tag, `<ref>@{n}` for the n-th
+    /// previous position of `HEAD` or of a branch in the reflog, or the hexadecimal hash of a
+    /// commit, which may be abbreviated as long as it is unambiguous.
+    pub fn resolve(&self, rev: &str) -> Result<Hashs, String> {
+        if rev == "HEAD" {
+            return Ok(self.current.hashs());
+        }
+        if let Some(r) = self.refs.get(rev) {
+            return Ok(r.hashs());
+        }
+        if let Some((reference, n)) = parse_reflog_rev(rev) {
+            if n == 0 {
+                return self.resolve(reference);
+            }
+            let entry = self.reflog(reference)
+                .get(n)
+                .cloned()
+                .ok_or_else(|| format!("Revision {} doesn't exist, the reflog of {} is too short", rev, reference))?;
+            return match entry.new {
+                None => Ok(HashList::empty()),
+                Some(hash) => self.history_of(hash),
+            };
+        }
+
+        let prefix = rev.to_lowercase();
+        let matching: Vec<NodeHash> = self.commits
+            .keys()
+            .filter(|hash| hash.to_string().starts_with(&prefix))
+            .cloned()
+            .collect();
+        match matching.as_slice() {
+            [] => Err(format!("Revision {} doesn't exist", rev)),
+            [hash] => self.history_of(*hash),
+            _ => Err(format!("Revision {} is ambiguous", rev)),
+        }
+    }
+
+    /// Returns the hashes of the history of a commit, from the commit to the oldest.
+    /// The history is shared with the current reference or a branch or a tag when the commit is
+    /// on one of them, otherwise it is rebuilt from the parents of the commits.
+    fn history_of(&self, hash: NodeHash) -> Result<Hashs, String> {
+        if let Some(hashs) = std::iter::once(&self.current)
+            .chain(self.refs.values())
+            .find_map(|r| self.find_hashes_from(r, hash)) {
+            return Ok(hashs);
+        }
+
+        let mut hashs = Vec::new();
+        let mut next = Some(hash);
+        while let Some(hash) = next {
+            let commit = self.find_commit(hash)
+                .ok_or_else(|| format!("Commit {} doesn't exist", hash))?;
+            hashs.push(hash);
+            next = commit.parents().first().cloned();
+        }
+        Ok(hashs
+            .into_iter()
+            .rev()
+            .fold(HashList::empty(), |list, hash| HashList::cons(hash, list)))
+    }
+
+    /// Returns the hashes of the history of the current reference as of the given time, i.e.
+    /// from the last commit created at or before `timestamp`.
+    pub fn resolve_time(&self, timestamp: Timestamp) -> Hashs {
+        self.current.hashs().skip_while(|&hash| {
+            self.find_commit(hash)
+                .map_or(false, |commit| commit.timestamp() > timestamp)
+        })
     }
 
     /// Iterates through the commits from the current ref in order
     /// From the most recent to the oldest
-    // FIXME Iterator here !!!
-    pub fn commits(&self) -> Vec<&Commit<RepoItem>> {
+    pub fn commits(&self) -> Log<'_, RepoItem, ItemHasher> {
+        Log::new(self, self.current.hashs(), HashSet::new())
+    }
+
+    /// Iterates through the commits of a revision range, from the most recent to the oldest.
+    ///
+    /// The range `a..b` holds the commits of `b` that are not in the history of `a`. Either side
+    /// defaults to `HEAD` when it is left out; a single revision holds all the commits of its
+    /// history. The revisions are resolved with `resolve`.
+    pub fn log(&self, range: &str) -> Result<Log<'_, RepoItem, ItemHasher>, String> {
+        let (excluded, included) = match range.find("..") {
+            None => (None, range),
+            Some(position) => (Some(&range[..position]), &range[position + 2..]),
+        };
+        let or_head = |rev: &str| if rev.is_empty() { "HEAD".to_string() } else { rev.to_string() };
+
+        let hashs = self.resolve(&or_head(included))?;
+        let excluded: HashSet<NodeHash> = match excluded {
+            None => HashSet::new(),
+            Some(rev) => self.resolve(&or_head(rev))?.iter().collect(),
+        };
+        Ok(Log::new(self, hashs, excluded))
+    }
+
+    /// Returns the commits of the current ref whose item satisfies the predicate,
+    /// from the most recent to the oldest
+    pub fn find_commits<P>(&self, predicate: P) -> Vec<&Commit<RepoItem>>
+    where
+        P: Fn(&RepoItem) -> bool,
+    {
         self.current
             .hashs()
             .iter()
-            .map(|x| self.find_commit(x).unwrap()) // FIXME self doesn't live long enough ?
+            .filter_map(|hash| self.find_commit(hash))
+            .filter(|commit| predicate(commit.item()))
             .collect()
     }
 
     // Branch / Tags
 
     /// Tag the current commit with the given name
-    pub fn tag(&mut self, name: &str) -> Ref {
-        let tag = Tag(self.current.hashs(), name.to_string());
-        self.refs.push(tag.clone());
-        tag
+    /// returns an error if a branch or a tag already has this name
+    pub fn tag(&mut self, name: &str) -> Result<Ref, String> {
+        self.add_ref(Tag(self.current.hashs(), name.to_string()))
+    }
+
+    /// Creates a branch with the given name on the current commit. The branch isn't checked out.
+    /// returns an error if a branch or a tag already has this name
+    pub fn branch(&mut self, name: &str) -> Result<Ref, String> {
+        let branch = self.add_ref(Branch(self.current.hashs(), name.to_string()))?;
+        let operation = format!("branch: created from {}", self.current.name());
+        self.log_move(name, None, branch.hashs().head_option(), &operation, Timestamp::now());
+        Ok(branch)
+    }
+
+    /// Deletes a branch, and returns it
+    /// returns an error if the branch doesn't exist, or if it is the current branch
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::history::history::Repository;
+    /// use histo_graph_core::historized_graph::CommandHasher;
+    ///
+    /// let mut repository = Repository::new(CommandHasher {});
+    /// repository.branch("feature").unwrap();
+    ///
+    /// assert!(repository.delete_branch("master").is_err());
+    /// assert!(repository.delete_branch("feature").is_ok());
+    /// assert_eq!(repository.list_refs().len(), 1);
+    /// ```
+    pub fn delete_branch(&mut self, name: &str) -> Result<Ref, String> {
+        self.find_branch(name)
+            .ok_or_else(|| format!("Branch {} doesn't exists", name))?;
+        if let Branch(_, current) = &self.current {
+            if current == name {
+                return Err(format!("Cannot delete the current branch {}", name));
+            }
+        }
+        let branch = self.refs.remove(name).expect("the branch exists");
+        self.log_move(name, branch.hashs().head_option(), None, "branch: deleted", Timestamp::now());
+        Ok(branch)
+    }
+
+    /// Deletes a tag, and returns it. If the tag is checked out, the current reference becomes
+    /// detached.
+    /// returns an error if the tag doesn't exist
+    pub fn delete_tag(&mut self, name: &str) -> Result<Ref, String> {
+        let tag = self.find_tag(name)
+            .ok_or_else(|| format!("Tag {} doesn't exists", name))?;
+        if self.current == tag {
+            self.set_current(Detached(tag.hashs()), &format!("tag: deleted {}", name), Timestamp::now());
+        }
+        Ok(self.refs.remove(name).expect("the tag exists"))
     }
 
-    pub fn branch(&mut self, name: &str) -> Ref {
-        let branch = Branch(self.current.hashs(), name.to_string());
-        self.refs.push(branch.clone());
-        branch
+    /// Renames a branch, and returns it. The current branch can be renamed.
+    /// returns an error if the branch doesn't exist, or if a branch or a tag already has the new
+    /// name
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::history::history::Repository;
+    /// use histo_graph_core::historized_graph::CommandHasher;
+    ///
+    /// let mut repository = Repository::new(CommandHasher {});
+    /// repository.tag("v1").unwrap();
+    ///
+    /// assert!(repository.rename_branch("master", "v1").is_err());
+    /// repository.rename_branch("master", "main").unwrap();
+    /// assert_eq!(repository.current().name(), "main");
+    /// ```
+    pub fn rename_branch(&mut self, name: &str, new_name: &str) -> Result<Ref, String> {
+        let branch = self.find_branch(name)
+            .ok_or_else(|| format!("Branch {} doesn't exists", name))?;
+        let renamed = self.add_ref(Branch(branch.hashs(), new_name.to_string()))?;
+        self.refs.remove(name);
+        let head = renamed.hashs().head_option();
+        let operation = format!("branch: renamed {} to {}", name, new_name);
+        self.log_move(name, head, None, &operation, Timestamp::now());
+        self.log_move(new_name, None, head, &operation, Timestamp::now());
+        if self.current == branch {
+            self.current = renamed.clone();
+        }
+        Ok(renamed)
     }
 
     // Checkout
 
     pub fn checkout_tag(&mut self, name: &str) -> Result<Ref, String> {
-        match self.find_tag(&name) {
+        match self.find_tag(name) {
             None => Err(format!("Tag {} doesn't exists", name)),
             Some(t) => {
-                self.current = t.clone();
-                Ok(t.clone())
+                self.checkout_ref(t.clone(), name);
+                Ok(t)
             }
         }
     }
 
     pub fn checkout_branch(&mut self, name: &str) -> Result<Ref, String> {
-        match self.find_branch(&name) {
+        match self.find_branch(name) {
             None => Err(format!("Branch {} doesn't exists", name)),
             Some(t) => {
-                self.current = t.clone();
-                Ok(t.clone())
+                self.checkout_ref(t.clone(), name);
+                Ok(t)
             }
         }
     }
 
+    /// Checks out a revision, as resolved by `resolve`. A branch or a tag is checked out as such,
+    /// any other revision is checked out as a detached reference.
+    pub fn checkout(&mut self, rev: &str) -> Result<Ref, String> {
+        if self.find_branch(rev).is_some() {
+            return self.checkout_branch(rev);
+        }
+        if self.find_tag(rev).is_some() {
+            return self.checkout_tag(rev);
+        }
+        if rev != "HEAD" {
+            let hashs = self.resolve(rev)?;
+            self.checkout_ref(Detached(hashs), rev);
+        }
+        Ok(self.current.clone())
+    }
+
     pub fn checkout_hash(&mut self, hash: NodeHash) -> Result<Ref, String> {
         let hashs = self.find_hashes_from(&self.current, hash);
         match hashs {
@@ -189,7 +905,7 @@ where
                 hash
             )),
             Some(xs) => {
-                self.current = Detached(xs.clone());
+                self.checkout_ref(Detached(xs), &hash.to_string());
                 Ok(self.current.clone())
             }
         }
@@ -213,28 +929,52 @@ where
 
     // Helpers
 
-    fn find_tag(&self, name: &str) -> Option<Ref> {
-        self.refs
-            .iter()
-            .find(|r| match r {
-                Tag(_, tag) => tag == name,
-                _ => false,
-            })
-            .map(|x| x.clone())
+    fn add_ref(&mut self, r: Ref) -> Result<Ref, String> {
+        let name = r.name().to_string();
+        if self.refs.contains_key(&name) {
+            return Err(format!("Reference {} already exists", name));
+        }
+        self.refs.insert(name, r.clone());
+        Ok(r)
     }
 
-    fn find_branch(&self, name: &str) -> Option<Ref> {
-        self.refs
-            .iter()
-            .find(|r| match r {
-                Branch(_, br) => br == name,
-                _ => false,
-            })
-            .map(|x| x.clone())
+    /// Moves the head of the current branch, both in `current` and in the references
+    fn move_current_branch(&mut self, hashs: Hashs, operation: &str, timestamp: Timestamp) {
+        let name = self.current.name().to_string();
+        let old = self.current.hashs().head_option();
+        self.set_current(Branch(hashs, name.clone()), operation, timestamp);
+        self.log_move(&name, old, self.current.hashs().head_option(), operation, timestamp);
+        self.refs.insert(name, self.current.clone());
     }
 
-    fn find_commit(&self, hash: NodeHash) -> Option<&Commit<RepoItem>> {
-        self.commits.get(&hash)
+    fn checkout_ref(&mut self, r: Ref, rev: &str) {
+        let operation = format!("checkout: moving from {} to {}", self.current.name(), rev);
+        self.set_current(r, &operation, Timestamp::now());
+    }
+
+    /// Changes the current reference, and records the move of `HEAD`
+    fn set_current(&mut self, current: Ref, operation: &str, timestamp: Timestamp) {
+        let old = self.current.hashs().head_option();
+        self.current = current;
+        self.log_move("HEAD", old, self.current.hashs().head_option(), operation, timestamp);
+    }
+
+    fn log_move(&mut self, reference: &str, old: Option<NodeHash>, new: Option<NodeHash>, operation: &str, timestamp: Timestamp) {
+        self.reflog.push(ReflogEntry::new(reference, old, new, operation, timestamp));
+    }
+
+    fn find_tag(&self, name: &str) -> Option<Ref> {
+        match self.refs.get(name) {
+            Some(tag @ Tag(_, _)) => Some(tag.clone()),
+            _ => None,
+        }
+    }
+
+    fn find_branch(&self, name: &str) -> Option<Ref> {
+        match self.refs.get(name) {
+            Some(branch @ Branch(_, _)) => Some(branch.clone()),
+            _ => None,
+        }
     }
 
     /// Find the hashes, from the given hash, on teh specified Reference
@@ -249,3 +989,10 @@ where
         }
     }
 }
+
+/// Parses a revision `<ref>@{n}`
+fn parse_reflog_rev(rev: &str) -> Option<(&str, usize)> {
+    let position = rev.find("@{")?;
+    let n = rev[position + 2..].strip_suffix('}')?.parse().ok()?;
+    Some((&rev[..position], n))
+}
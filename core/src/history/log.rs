@@ -0,0 +1,222 @@
+//! This module implements the querying of the commits of a [`Repository`].
+//!
+//! [`Repository`]: ../history/struct.Repository.html
+
+use std::collections::HashSet;
+
+use core::fmt::Debug;
+use core::hash::Hash;
+
+use crate::history::hashlist::{HashListIter, NodeHash};
+use crate::history::history::{Commit, Hashs, Hasher, Repository, Timestamp};
+
+/// A lazy iterator over the commits of a repository, from the most recent to the oldest.
+///
+/// The commits can be filtered with the builder methods. A commit that can't be found in the
+/// repository is returned as an error, which ends the iteration.
+///
+/// # Examples
+///
+/// ```
+/// use histo_graph_core::history::history::{Author, Comment, Repository};
+/// use histo_graph_core::historized_graph::CommandHasher;
+///
+/// let mut repository = Repository::new(CommandHasher {});
+/// repository.commit(vec![], Author("lauren".to_string()), Comment("first".to_string())).unwrap();
+/// repository.commit(vec![], Author("david".to_string()), Comment("second".to_string())).unwrap();
+/// repository.commit(vec![], Author("lauren".to_string()), Comment("third".to_string())).unwrap();
+///
+/// let comments: Vec<String> = repository.commits()
+///     .author("lauren")
+///     .max_count(1)
+///     .map(|commit| commit.unwrap().comment().0.clone())
+///     .collect();
+/// assert_eq!(comments, vec!["third".to_string()]);
+/// ```
+pub struct Log<'a, Item, ItemHasher: Hasher<Item>>
+where
+    Item: PartialEq + Eq + Clone + Hash + Debug,
+{
+    repository: &'a Repository<Item, ItemHasher>,
+    hashs: HashListIter,
+    // the commits of the excluded side of a range
+    excluded: HashSet<NodeHash>,
+    author: Option<String>,
+    comment: Option<String>,
+    since: Option<Timestamp>,
+    until: Option<Timestamp>,
+    max_count: Option<usize>,
+    count: usize,
+    done: bool,
+}
+
+impl<'a, Item, ItemHasher> Log<'a, Item, ItemHasher>
+where
+    Item: PartialEq + Eq + Clone + Hash + Debug,
+    ItemHasher: Hasher<Item>,
+{
+    /// Creates a log over the commits `hashs`, that stops at the first of the `excluded` commits.
+    pub(crate) fn new(repository: &'a Repository<Item, ItemHasher>, hashs: Hashs, excluded: HashSet<NodeHash>) -> Log<'a, Item, ItemHasher> {
+        Log {
+            repository,
+            hashs: hashs.iter(),
+            excluded,
+            author: None,
+            comment: None,
+            since: None,
+            until: None,
+            max_count: None,
+            count: 0,
+            done: false,
+        }
+    }
+
+    /// Keeps the commits whose author name or email contains `author`.
+    pub fn author(mut self, author: &str) -> Self {
+        self.author = Some(author.to_string());
+        self
+    }
+
+    /// Keeps the commits whose comment contains `comment`.
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.comment = Some(comment.to_string());
+        self
+    }
+
+    /// Keeps the commits created at or after `timestamp`.
+    pub fn since(mut self, timestamp: Timestamp) -> Self {
+        self.since = Some(timestamp);
+        self
+    }
+
+    /// Keeps the commits created at or before `timestamp`.
+    pub fn until(mut self, timestamp: Timestamp) -> Self {
+        self.until = Some(timestamp);
+        self
+    }
+
+    /// Returns at most `max_count` commits.
+    pub fn max_count(mut self, max_count: usize) -> Self {
+        self.max_count = Some(max_count);
+        self
+    }
+
+    fn matches(&self, commit: &Commit<Item>) -> bool {
+        let author_matches = self.author.as_ref().map_or(true, |author| {
+            commit.author().0.contains(author.as_str())
+                || commit.author_email().map_or(false, |email| email.contains(author.as_str()))
+        });
+        let comment_matches = self.comment
+            .as_ref()
+            .map_or(true, |comment| commit.comment().0.contains(comment.as_str()));
+        let since_matches = self.since.map_or(true, |since| commit.timestamp() >= since);
+        let until_matches = self.until.map_or(true, |until| commit.timestamp() <= until);
+
+        author_matches && comment_matches && since_matches && until_matches
+    }
+}
+
+impl<'a, Item, ItemHasher> Iterator for Log<'a, Item, ItemHasher>
+where
+    Item: PartialEq + Eq + Clone + Hash + Debug,
+    ItemHasher: Hasher<Item>,
+{
+    type Item = Result<&'a Commit<Item>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.max_count.map_or(false, |max_count| self.count >= max_count) {
+            self.done = true;
+        }
+        while !self.done {
+            let hash = match self.hashs.next() {
+                None => break,
+                Some(hash) => hash,
+            };
+            // a history is linear: the commits that follow an excluded commit are excluded too
+            if self.excluded.contains(&hash) {
+                break;
+            }
+            match self.repository.find_commit(hash) {
+                None => {
+                    self.done = true;
+                    return Some(Err(format!("Commit {} doesn't exist", hash)));
+                }
+                Some(commit) if self.matches(commit) => {
+                    self.count += 1;
+                    return Some(Ok(commit));
+                }
+                Some(_) => (),
+            }
+        }
+        self.done = true;
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::history::hashlist::{HashList, NodeHash};
+    use crate::history::history::{Author, Comment, CommitInfo, Ref, Repository, Timestamp};
+    use crate::historized_graph::CommandHasher;
+
+    fn repository() -> Repository<Vec<crate::graph::command::GraphCommand>, CommandHasher> {
+        let mut repository = Repository::new(CommandHasher {});
+        for i in 0..10u64 {
+            let author = if i % 2 == 0 { "even" } else { "odd" };
+            let info = CommitInfo::new(Author(author.to_string()), Comment(format!("commit {}", i)));
+            repository.commit_with(vec![], info, Timestamp(i * 10)).unwrap();
+            if i == 4 {
+                repository.tag("v4").unwrap();
+            }
+        }
+        repository
+    }
+
+    fn comments(log: Vec<Result<String, String>>) -> Vec<String> {
+        log.into_iter().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn test_log_filters() {
+        let repository = repository();
+        let log = |f: &dyn Fn(super::Log<_, _>) -> super::Log<_, _>| -> Vec<String> {
+            comments(f(repository.commits()).map(|c| c.map(|c| c.comment().0.clone())).collect())
+        };
+
+        assert_eq!(log(&|log| log).len(), 10);
+        assert_eq!(log(&|log| log.author("odd").max_count(2)), vec!["commit 9", "commit 7"]);
+        assert_eq!(log(&|log| log.comment("commit 3")), vec!["commit 3"]);
+        assert_eq!(log(&|log| log.since(Timestamp(30)).until(Timestamp(50))), vec!["commit 5", "commit 4", "commit 3"]);
+        assert_eq!(log(&|log| log.max_count(0)).len(), 0);
+    }
+
+    #[test]
+    fn test_log_range() {
+        let repository = repository();
+        let log = |range: &str| -> Vec<String> {
+            comments(repository.log(range).unwrap().map(|c| c.map(|c| c.comment().0.clone())).collect())
+        };
+
+        assert_eq!(log("v4..HEAD"), vec!["commit 9", "commit 8", "commit 7", "commit 6", "commit 5"]);
+        assert_eq!(log("v4.."), log("v4..HEAD"));
+        assert_eq!(log("v4").len(), 5);
+        assert!(log("HEAD..v4").is_empty());
+        assert!(log("..v4").is_empty());
+        assert!(repository.log("unknown..HEAD").is_err());
+    }
+
+    #[test]
+    fn test_log_missing_commit() {
+        let repository: Repository<Vec<crate::graph::command::GraphCommand>, CommandHasher> = Repository::restore(
+            CommandHasher {},
+            Ref::Branch(HashList::cons(NodeHash(2), HashList::singleton(NodeHash(1))), "master".to_string()),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+        );
+
+        let log: Vec<_> = repository.commits().collect();
+        assert_eq!(log.len(), 1);
+        assert!(log[0].is_err());
+    }
+}
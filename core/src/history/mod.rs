@@ -1,2 +1,4 @@
 pub mod hashlist;
+#[allow(clippy::module_inception)]
 pub mod history;
+pub mod log;
@@ -25,7 +25,7 @@ use std::collections::btree_map::BTreeMap;
 ///     println!("{}", i);
 /// }
 /// ```
-#[derive(Debug, Hash, PartialEq)]
+#[derive(Debug, Hash, PartialEq, Clone)]
 pub struct BTreeBag<T>
     where T: Ord {
     inner: BTreeMap<T, usize>
@@ -118,14 +118,31 @@ impl<T> BTreeBag<T>
                 *count == 0
             });
 
-        match must_remove {
-            Some(true) => { self.inner.remove(t); }
-            _ => ()
+        if let Some(true) = must_remove {
+            self.inner.remove(t);
         }
 
         has_been_removed
     }
 
+    /// Returns how many times the BTreeBag contains the element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::util::b_tree_bag::BTreeBag;
+    ///
+    /// let mut bag: BTreeBag<u32> = BTreeBag::new();
+    /// bag.insert(2);
+    /// bag.insert(2);
+    ///
+    /// assert_eq!(bag.count(&1), 0);
+    /// assert_eq!(bag.count(&2), 2);
+    /// ```
+    pub fn count(&self, t: &T) -> usize {
+        self.inner.get(t).cloned().unwrap_or(0)
+    }
+
     /// An iterator that visits the elements in the BTreeBag in sorted order.
     ///
     /// # Examples
@@ -165,6 +182,75 @@ impl<T> BTreeBag<T>
     pub fn len(&self) -> usize {
         self.iter().count()
     }
+
+    /// Returns true if the BTreeBag contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::util::b_tree_bag::BTreeBag;
+    ///
+    /// let mut bag: BTreeBag<u32> = BTreeBag::new();
+    /// assert!(bag.is_empty());
+    /// bag.insert(1);
+    /// assert!(!bag.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
+
+impl<T> Default for BTreeBag<T>
+    where T: Ord {
+    fn default() -> BTreeBag<T> {
+        BTreeBag::new()
+    }
+}
+
+/// A `BTreeBag` is serialized as the sequence of its elements in sorted order, where an element
+/// is repeated for each time it is contained, like a sorted `Vec`.
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use std::fmt;
+    use std::marker::PhantomData;
+
+    use serde::de::{SeqAccess, Visitor};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::BTreeBag;
+
+    impl<T> Serialize for BTreeBag<T>
+        where T: Ord + Serialize {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.iter())
+        }
+    }
+
+    struct BTreeBagVisitor<T>(PhantomData<T>);
+
+    impl<'de, T> Visitor<'de> for BTreeBagVisitor<T>
+        where T: Ord + Deserialize<'de> {
+        type Value = BTreeBag<T>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a sequence of elements")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<BTreeBag<T>, A::Error> {
+            let mut bag = BTreeBag::new();
+            while let Some(t) = seq.next_element()? {
+                bag.insert(t);
+            }
+            Ok(bag)
+        }
+    }
+
+    impl<'de, T> Deserialize<'de> for BTreeBag<T>
+        where T: Ord + Deserialize<'de> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<BTreeBag<T>, D::Error> {
+            deserializer.deserialize_seq(BTreeBagVisitor(PhantomData))
+        }
+    }
 }
 
 #[cfg(test)]
@@ -232,7 +318,7 @@ mod test {
         }
 
         // the iterator of the BTreeMap should visit the edges in sorted order
-        let rslt: Vec<Edge> = btb.iter().map(|edge| *edge).collect();
+        let rslt: Vec<Edge> = btb.iter().copied().collect();
         let sorted_edges = {edges.sort(); edges };
         assert_eq!(rslt,sorted_edges);
     }
@@ -268,4 +354,18 @@ mod test {
         let rslt: Vec<&Edge> = btb.iter().collect();
         assert_eq!(rslt, vec![&Edge(VertexId(0), VertexId(1))]);
     }
-}
\ No newline at end of file
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() -> Result<(), serde_json::error::Error> {
+        let mut bag: BTreeBag<Edge> = BTreeBag::new();
+        bag.insert(Edge(VertexId(2), VertexId(1)));
+        bag.insert(Edge(VertexId(1), VertexId(2)));
+        bag.insert(Edge(VertexId(2), VertexId(1)));
+
+        let str = serde_json::to_string(&bag)?;
+        assert_eq!(str, "[[1,2],[2,1],[2,1]]");
+        assert_eq!(serde_json::from_str::<BTreeBag<Edge>>(&str)?, bag);
+        Ok(())
+    }
+}
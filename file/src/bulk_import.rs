@@ -0,0 +1,360 @@
+//! This module implements the import of a large graph into a store in one pass.
+//!
+//! The vertices and the edges are collected by a [`BulkLoader`], without the commit of each
+//! change of a `HistorizedGraph`. The file of each vertex and of each edge is created when it is
+//! first added, once even for parallel edges, and the pending files are written to the store in
+//! batches of at most [`FLUSH_THRESHOLD`] files, skipping the files that the store already
+//! contains. The vertex and edge vectors are sorted, so that a graph gives the same files and the
+//! same `GraphHash` whatever the order in which it has been loaded.
+//!
+//! The loader only keeps the ids and the hashes of the vertices and of the edges, and the
+//! contents of the pending files. Writing the graph builds the vectors of hashes and the commands
+//! of the commit, whose size is proportional to the size of the graph.
+//!
+//! The history of the graph is a single commit, whose state is stored as a checkpoint, so that
+//! loading the history doesn't replay the import.
+//!
+//! [`BulkLoader`]: struct.BulkLoader.html
+//! [`FLUSH_THRESHOLD`]: constant.FLUSH_THRESHOLD.html
+
+use histo_graph_core::graph::{
+    command::GraphCommand,
+    directed_graph::GraphMode,
+    graph::{Edge, VertexId},
+    key_map::KeyMap,
+};
+use histo_graph_core::historized_graph::{CheckpointPolicy, CommandHasher};
+use histo_graph_core::history::{
+    hashlist::NodeHash,
+    history::{CommitInfo, Repository, Timestamp},
+};
+
+use crate::error::Error;
+use crate::file_storage::{
+    File, GraphHash, Hash, edge_to_file, save_graph_hash_as, vertex_to_file, write_edge_hash_vec_file,
+    write_file_in_dir, write_vertex_hash_vec_file,
+};
+use crate::history_storage::{StoredHistory, save_history_as};
+
+use futures::future::{self, Either, Future};
+use futures::stream::{self, Stream};
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    io,
+    iter,
+    mem,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+// the number of files that are written at the same time
+const WRITE_CONCURRENCY: usize = 64;
+
+/// The number of pending files above which a [`BulkLoader`] should be flushed.
+///
+/// [`BulkLoader`]: struct.BulkLoader.html
+pub const FLUSH_THRESHOLD: usize = 4096;
+
+/// The outcome of a bulk import.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct ImportSummary {
+    /// The hash of the commit of the import.
+    pub commit: NodeHash,
+    pub vertices: usize,
+    pub edges: usize,
+    /// The vertices that have been loaded more than once, and the edges that have been loaded
+    /// more than once into a simple graph.
+    pub duplicates: usize,
+    /// The number of vertex and edge files that have been written, the other ones being already
+    /// in the store.
+    pub written_files: usize,
+}
+
+/// Collects the vertices and the edges of a graph, to write them to a store with
+/// [`flush`](#method.flush) and [`write`](#method.write).
+///
+/// # Examples
+///
+/// ```
+/// use histo_graph_core::graph::directed_graph::GraphMode;
+/// use histo_graph_core::graph::graph::{VertexId, Edge};
+/// use histo_graph_file::bulk_import::BulkLoader;
+///
+/// let mut loader = BulkLoader::new(GraphMode::Simple);
+/// assert!(loader.add_edge(Edge(VertexId(1), VertexId(2))));
+/// assert!(!loader.add_edge(Edge(VertexId(1), VertexId(2))));
+/// assert!(!loader.add_vertex(VertexId(2)));
+///
+/// assert_eq!((loader.vertex_count(), loader.edge_count(), loader.duplicates()), (2, 1, 2));
+/// ```
+pub struct BulkLoader {
+    mode: GraphMode,
+    // the vertices with the hashes of their files
+    vertices: BTreeMap<VertexId, Hash>,
+    // the edges with the hashes of their files and their multiplicity
+    edges: BTreeMap<Edge, (Hash, usize)>,
+    edge_count: usize,
+    duplicates: usize,
+    // the files of the new vertices and edges, that haven't been written yet
+    pending_vertex_files: Vec<File>,
+    pending_edge_files: Vec<File>,
+    written_files: Arc<AtomicUsize>,
+}
+
+impl BulkLoader {
+
+    /// Creates an empty `BulkLoader` for a graph with the given mode.
+    pub fn new(mode: GraphMode) -> BulkLoader {
+        BulkLoader {
+            mode,
+            vertices: BTreeMap::new(),
+            edges: BTreeMap::new(),
+            edge_count: 0,
+            duplicates: 0,
+            pending_vertex_files: Vec::new(),
+            pending_edge_files: Vec::new(),
+            written_files: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edge_count
+    }
+
+    /// Returns the number of vertices that have been added more than once, and of edges that have
+    /// been added more than once to a simple graph.
+    pub fn duplicates(&self) -> usize {
+        self.duplicates
+    }
+
+    /// Returns the number of files that haven't been written yet.
+    pub fn pending_files(&self) -> usize {
+        self.pending_vertex_files.len() + self.pending_edge_files.len()
+    }
+
+    /// Returns true if the pending files have reached the [`FLUSH_THRESHOLD`], and should be
+    /// written with [`flush`](#method.flush).
+    ///
+    /// [`FLUSH_THRESHOLD`]: constant.FLUSH_THRESHOLD.html
+    pub fn needs_flush(&self) -> bool {
+        self.pending_files() >= FLUSH_THRESHOLD
+    }
+
+    // Adds a vertex and queues its file. Returns false if it has already been added.
+    fn insert_vertex(&mut self, vertex_id: VertexId) -> bool {
+        if self.vertices.contains_key(&vertex_id) {
+            return false;
+        }
+        let file = vertex_to_file(&vertex_id);
+        self.vertices.insert(vertex_id, file.hash);
+        self.pending_vertex_files.push(file);
+        true
+    }
+
+    /// Adds a vertex. Returns false if it has already been added.
+    pub fn add_vertex(&mut self, vertex_id: VertexId) -> bool {
+        let added = self.insert_vertex(vertex_id);
+        if !added {
+            self.duplicates += 1;
+        }
+        added
+    }
+
+    /// Adds an edge, and its vertices. Returns false if the graph is simple and the edge has
+    /// already been added.
+    pub fn add_edge(&mut self, edge: Edge) -> bool {
+        if let Some((_, count)) = self.edges.get_mut(&edge) {
+            if self.mode == GraphMode::Simple {
+                self.duplicates += 1;
+                return false;
+            }
+            *count += 1;
+        } else {
+            let file = edge_to_file(&edge);
+            self.edges.insert(edge, (file.hash, 1));
+            self.pending_edge_files.push(file);
+        }
+        self.edge_count += 1;
+        self.insert_vertex(edge.0);
+        self.insert_vertex(edge.1);
+        true
+    }
+
+    /// Writes the pending files to the store at `base_path`, skipping the files that it already
+    /// contains.
+    pub fn flush(&mut self, base_path: PathBuf) -> impl Future<Item=(), Error=Error> {
+        let written_files = self.written_files.clone();
+        write_new_files(base_path.join("vertex"), mem::take(&mut self.pending_vertex_files))
+            .join(write_new_files(base_path.join("edge"), mem::take(&mut self.pending_edge_files)))
+            .map(move |(vertex_files, edge_files)| {
+                written_files.fetch_add(vertex_files + edge_files, Ordering::Relaxed);
+            })
+            .map_err(Into::into)
+    }
+
+    /// Writes the graph to the store at `base_path` under the given name, and its history: a
+    /// single commit that adds the vertices and the edges.
+    ///
+    /// The commit has the given metadata, with the trailers `Imported-vertices`, `Imported-edges`
+    /// and `Skipped-duplicates`, and a `Key` trailer for each of the given keys. The history has
+    /// the given checkpoint policy.
+    /// A history previously saved under the same name is replaced.
+    ///
+    /// The pending files are written first.
+    pub fn write(
+        mut self,
+        base_path: PathBuf,
+        name: &OsStr,
+        info: CommitInfo,
+        checkpoint_policy: CheckpointPolicy,
+        keys: &KeyMap,
+    ) -> impl Future<Item=ImportSummary, Error=Error> {
+        let flushed = self.flush(base_path.clone());
+        let written_files = self.written_files.clone();
+        let vertex_hashs: Vec<Hash> = self.vertices.values().cloned().collect();
+        let edge_hashs: Vec<Hash> = self.edges
+            .values()
+            .flat_map(|&(hash, count)| iter::repeat(hash).take(count))
+            .collect();
+
+        let summary = ImportSummary {
+            commit: NodeHash(0),
+            vertices: self.vertices.len(),
+            edges: self.edge_count,
+            duplicates: self.duplicates,
+            written_files: 0,
+        };
+        let mut info = info
+            .with_trailer("Imported-vertices", &summary.vertices.to_string())
+            .with_trailer("Imported-edges", &summary.edges.to_string())
+            .with_trailer("Skipped-duplicates", &summary.duplicates.to_string());
+        info.trailers.extend(keys.trailers());
+        let commands: Vec<GraphCommand> = self.vertices
+            .keys()
+            .map(|&vertex_id| GraphCommand::AddVertex(vertex_id))
+            .chain(self.edges
+                .iter()
+                .flat_map(|(&Edge(v1, v2), &(_, count))| iter::repeat(GraphCommand::AddEdge(v1, v2)).take(count)))
+            .collect();
+        let mut repository = Repository::new(CommandHasher {});
+        let history = repository.commit_with(commands, info, Timestamp::now())
+            .map(|r| (StoredHistory::from_repository(&repository, self.mode, checkpoint_policy, &KeyMap::new()), r.hashs().head()));
+
+        let simple = self.mode == GraphMode::Simple;
+        let name = name.to_os_string();
+        future::result(history)
+            .map_err(Error::HistoryError)
+            .and_then(move |(history, commit)| {
+                let vectors = write_vertex_hash_vec_file(base_path.clone(), vertex_hashs)
+                    .join(write_edge_hash_vec_file(base_path.clone(), edge_hashs))
+                    .map(move |(vertex_vec_hash, edge_vec_hash)| GraphHash { vertex_vec_hash, edge_vec_hash, simple })
+                    .map_err(Into::<Error>::into);
+                flushed.join(vectors)
+                    .and_then(move |(_, graph_hash)| {
+                        let mut history = history;
+                        // the state after the commit is the written graph
+                        history.add_stored_checkpoint(commit, graph_hash.clone());
+                        save_graph_hash_as(base_path.clone(), &name, &graph_hash)
+                            .and_then(move |_| save_history_as(base_path, &name, history))
+                            .map(move |_| ImportSummary {
+                                commit,
+                                written_files: written_files.load(Ordering::Relaxed),
+                                ..summary
+                            })
+                    })
+            })
+    }
+}
+
+/// Writes a file, unless the directory already contains it. Returns true if it has been written.
+fn write_new_file(dir: PathBuf, file: File) -> impl Future<Item=bool, Error=io::Error> {
+    tokio_fs::metadata(dir.join(file.hash.to_string()))
+        .then(move |metadata| match metadata {
+            Ok(_) => Either::A(future::ok(false)),
+            Err(_) => Either::B(write_file_in_dir(&dir, file).map(|_| true)),
+        })
+}
+
+/// Writes the files that the directory doesn't contain yet. Returns the number of written files.
+fn write_new_files(dir: PathBuf, files: Vec<File>) -> impl Future<Item=usize, Error=io::Error> {
+    tokio_fs::create_dir_all(dir.clone())
+        .and_then(move |_| {
+            stream::iter_ok(files)
+                .map(move |file| write_new_file(dir.clone(), file))
+                .buffer_unordered(WRITE_CONCURRENCY)
+                .fold(0, |count, written| Ok::<_, io::Error>(count + written as usize))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use histo_graph_core::graph::directed_graph::{DirectedGraph, GraphMode};
+    use histo_graph_core::graph::graph::{Edge, VertexId};
+    use histo_graph_core::graph::key_map::KeyMap;
+    use histo_graph_core::historized_graph::CheckpointPolicy;
+    use histo_graph_core::history::history::{Author, Comment, CommitInfo};
+    use super::*;
+    use crate::error::Result;
+    use crate::file_storage::load_graph;
+    use crate::history_storage::load_history;
+    use tokio::runtime::Runtime;
+    use std::ffi::OsString;
+    use std::path::Path;
+
+    #[test]
+    fn test_bulk_import() -> Result<()> {
+        let path: PathBuf = Path::new("../target/test/bulk/").into();
+        let name = OsString::from("bulkgraph");
+        let mut rt = Runtime::new()?;
+
+        let mut loader = BulkLoader::new(GraphMode::Multi);
+        let mut expected = DirectedGraph::new();
+        for i in 0..100 {
+            let edge = Edge(VertexId(1000 + i % 10), VertexId(1000 + i % 7));
+            loader.add_edge(edge);
+            expected.add_edge(edge);
+            if i == 50 {
+                // the files of the vertices and the edges that have been added so far
+                assert_eq!(loader.pending_files(), 10 + 51);
+                rt.block_on(loader.flush(path.clone()))?;
+                assert_eq!(loader.pending_files(), 0);
+            }
+        }
+        loader.add_vertex(VertexId(999));
+        expected.add_vertex(VertexId(999));
+        let mut keys = KeyMap::new();
+        keys.insert("first", VertexId(1000));
+
+        let info = CommitInfo::new(Author("lauren".to_string()), Comment("import".to_string()));
+        let summary = rt.block_on(loader.write(path.clone(), &name, info, CheckpointPolicy::Every(10), &keys))?;
+        assert_eq!((summary.vertices, summary.edges, summary.duplicates), (11, 100, 0));
+
+        assert_eq!(rt.block_on(load_graph(path.clone(), &name))?, expected);
+        let graph = rt.block_on(load_history(path.clone(), &name))?.into_historized_graph()?;
+        assert_eq!(graph.graph(), &expected);
+        assert_eq!(graph.replay_metrics().last_replay_length(), 0);
+        assert_eq!(graph.keys(), keys);
+        assert!(graph.pending_keys().is_empty());
+        assert_eq!(graph.checkpoint_policy(), CheckpointPolicy::Every(10));
+        let commit = graph.repository().head().expect("a commit");
+        assert_eq!(commit.hash(), summary.commit);
+        assert!(commit.trailers().contains(&("Imported-edges".to_string(), "100".to_string())));
+
+        // the files are already in the store
+        let mut loader = BulkLoader::new(GraphMode::Multi);
+        loader.add_edge(Edge(VertexId(1000), VertexId(1000)));
+        let info = CommitInfo::new(Author("lauren".to_string()), Comment("again".to_string()));
+        let summary = rt.block_on(loader.write(path, &name, info, CheckpointPolicy::default(), &KeyMap::new()))?;
+        assert_eq!(summary.written_files, 0);
+
+        Ok(())
+    }
+}
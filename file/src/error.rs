@@ -1,7 +1,8 @@
 #[derive(Debug)]
 pub enum Error {
     IoError(std::io::Error),
-    BinCodeError(bincode::Error)
+    BinCodeError(bincode::Error),
+    HistoryError(String),
 }
 
 use Error::*;
@@ -1,6 +1,6 @@
 use histo_graph_core::graph::{
     graph::{VertexId, Edge},
-    directed_graph::DirectedGraph,
+    directed_graph::{DirectedGraph, GraphMode},
 };
 
 use crate::error::{Error, Result};
@@ -12,18 +12,19 @@ use serde::{Serialize, Deserialize};
 use futures::future::Future;
 use std::{
     borrow::Borrow,
+    fmt,
     io,
     path::{Path, PathBuf},
 };
 use std::ffi::OsStr;
 
 
-#[derive(Clone, Copy, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Hash([u8; 32]);
 
-impl Hash {
-    fn to_string(&self) -> String {
-        HEXLOWER.encode(&self.0)
+impl fmt::Display for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&HEXLOWER.encode(&self.0))
     }
 }
 
@@ -40,9 +41,9 @@ impl<T> From<T> for Hash
     }
 }
 
-struct File {
-    content: Vec<u8>,
-    hash: Hash,
+pub(crate) struct File {
+    pub(crate) content: Vec<u8>,
+    pub(crate) hash: Hash,
 }
 
 /// A HashEdge respresents an edge by the hashes of the vertices it is connected to.
@@ -52,14 +53,34 @@ struct HashEdge {
     to: Hash,
 }
 
-/// The root of a stored graph. It holds the hashes of the vertex vector and the edge vector.
-#[derive(Serialize, Deserialize)]
+/// The root of a stored graph. It holds the hashes of the vertex vector and the edge vector, and
+/// whether the graph is a simple graph or a multigraph.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GraphHash {
+    pub(crate) vertex_vec_hash: Hash,
+    pub(crate) edge_vec_hash: Hash,
+    pub(crate) simple: bool,
+}
+
+/// The layout of a `GraphHash` written before the mode of the graph was stored, when every graph
+/// was a multigraph.
+#[derive(Serialize, Deserialize)]
+struct LegacyGraphHash {
     vertex_vec_hash: Hash,
     edge_vec_hash: Hash,
 }
 
-fn vertex_to_file(vertex_id: &VertexId) -> File {
+/// Deserializes a `GraphHash`, in its current layout or in the layout without the mode of the
+/// graph.
+pub(crate) fn deserialize_graph_hash(content: &[u8]) -> Result<GraphHash> {
+    bincode::deserialize::<GraphHash>(content)
+        .or_else(|e| bincode::deserialize::<LegacyGraphHash>(content)
+            .map(|LegacyGraphHash { vertex_vec_hash, edge_vec_hash }| GraphHash { vertex_vec_hash, edge_vec_hash, simple: false })
+            .map_err(|_| e))
+        .map_err(Into::into)
+}
+
+pub(crate) fn vertex_to_file(vertex_id: &VertexId) -> File {
     // serialize the vertex_id
     let content: Vec<u8> = bincode::serialize(&vertex_id.0).unwrap();
     let hash: Hash = (&content).into();
@@ -70,7 +91,7 @@ fn vertex_to_file(vertex_id: &VertexId) -> File {
     }
 }
 
-fn edge_to_file(edge: &Edge) -> File {
+pub(crate) fn edge_to_file(edge: &Edge) -> File {
     let File { hash: v_hash_0, ..} = vertex_to_file(&edge.0);
     let File { hash: v_hash_1, ..} = vertex_to_file(&edge.1);
 
@@ -111,8 +132,8 @@ fn file_to_hash_vec(file: &File) -> Result<Vec<Hash>> {
     Ok(result)
 }
 
-fn write_file_in_dir(dir_path: &Path, file: File) -> impl Future<Error = io::Error> {
-    let path = dir_path.join(&file.hash.to_string());
+pub(crate) fn write_file_in_dir(dir_path: &Path, file: File) -> impl Future<Error = io::Error> {
+    let path = dir_path.join(file.hash.to_string());
     tokio_fs::write(path, file.content)
 }
 
@@ -147,7 +168,7 @@ fn write_all_vertices_to_files<I>(base_path: PathBuf, i: I) -> impl Future<Item=
 /// First creates a sub-directoy `vertexvec/` in the provided `base_path`, then writes the vector
 /// of hashes into a single file in that sub-directory.
 /// Returns a hash of the written file.
-fn write_vertex_hash_vec_file(base_path: PathBuf, hash_vec: Vec<Hash>) -> impl Future<Item = Hash, Error = io::Error> {
+pub(crate) fn write_vertex_hash_vec_file(base_path: PathBuf, hash_vec: Vec<Hash>) -> impl Future<Item = Hash, Error = io::Error> {
     let path = base_path.join("vertexvec");
     let file = hash_vec_to_file(&hash_vec);
     let hash = file.hash;
@@ -162,7 +183,7 @@ fn write_vertex_hash_vec_file(base_path: PathBuf, hash_vec: Vec<Hash>) -> impl F
 fn write_graph_vertices(base_path: PathBuf, graph: &DirectedGraph) -> impl Future<Item = Hash, Error = io::Error> {
     let vertices: Vec<VertexId> = graph
         .vertices()
-        .map(| v | *v)
+        .copied()
         .collect();
 
     tokio_fs::create_dir_all(base_path.clone())
@@ -215,7 +236,7 @@ fn write_all_edges_to_files<I>(base_path: PathBuf, i: I) -> impl Future<Item=Vec
 /// First creates a sub-directoy `edgevec/` in the provided `base_path`, then writes the vector
 /// of hashes into a single file in that sub-directory.
 /// Returns a hash of the written file.
-fn write_edge_hash_vec_file(base_path: PathBuf, hash_vec: Vec<Hash>) -> impl Future<Item = Hash, Error = io::Error> {
+pub(crate) fn write_edge_hash_vec_file(base_path: PathBuf, hash_vec: Vec<Hash>) -> impl Future<Item = Hash, Error = io::Error> {
     let path = base_path.join("edgevec");
     let file = hash_vec_to_file(&hash_vec);
     let hash = file.hash;
@@ -230,7 +251,7 @@ fn write_edge_hash_vec_file(base_path: PathBuf, hash_vec: Vec<Hash>) -> impl Fut
 fn write_graph_edges(base_path: PathBuf, graph: &DirectedGraph) -> impl Future<Item = Hash, Error = io::Error> {
     let edges: Vec<Edge> = graph
         .edges()
-        .map(| v | *v)
+        .copied()
         .collect();
 
     tokio_fs::create_dir_all(base_path.clone())
@@ -247,9 +268,10 @@ fn write_graph_edges(base_path: PathBuf, graph: &DirectedGraph) -> impl Future<I
 pub fn write_graph(base_path: PathBuf, graph: &DirectedGraph) -> impl Future<Item = GraphHash, Error = io::Error> {
     let vertex_fut = write_graph_vertices(base_path.clone(), graph);
     let edge_fut = write_graph_edges(base_path, graph);
+    let simple = graph.mode() == GraphMode::Simple;
 
     vertex_fut.join(edge_fut)
-        .map(|(vertex_vec_hash, edge_vec_hash)| GraphHash{vertex_vec_hash, edge_vec_hash})
+        .map(move |(vertex_vec_hash, edge_vec_hash)| GraphHash{vertex_vec_hash, edge_vec_hash, simple})
 }
 
 /// Saves a graph under the given name.
@@ -258,15 +280,21 @@ pub fn write_graph(base_path: PathBuf, graph: &DirectedGraph) -> impl Future<Ite
 /// of the provided graph in that directory.
 /// Returns the path to the written file.
 pub fn save_graph_as(base_path: PathBuf, name: &OsStr, graph: &DirectedGraph) -> impl Future<Item=PathBuf, Error=Error> {
+    let name = name.to_os_string();
+    write_graph(base_path.clone(), graph)
+        .map_err(Into::<Error>::into)
+        .and_then(move |graph_hash| save_graph_hash_as(base_path, &name, &graph_hash))
+}
+
+/// Saves the GraphHash of a graph that has already been written under the given name.
+/// Returns the path to the written file.
+pub(crate) fn save_graph_hash_as(base_path: PathBuf, name: &OsStr, graph_hash: &GraphHash) -> impl Future<Item=PathBuf, Error=Error> {
     let dir = base_path.join("graph");
     let path = dir.join(name);
-    write_graph(base_path, graph)
+    let content = bincode::serialize(graph_hash);
+    tokio_fs::create_dir_all(dir)
         .map_err(Into::<Error>::into)
-        .and_then(move |graph_hash| tokio_fs::create_dir_all(dir)
-            .map_err(Into::<Error>::into)
-            .and_then(move | _ | bincode::serialize(&graph_hash)
-                .map_err(Into::<Error>::into))
-        )
+        .and_then(move | _ | content.map_err(Into::<Error>::into))
         .and_then({
             let path = path.clone();
             move |content| tokio_fs::write(path, content)
@@ -338,7 +366,7 @@ fn read_hash_edge(dir_path: PathBuf, hash: Hash) -> impl Future<Item = HashEdge,
         .and_then(|file| file_to_hash_edge(&file))
 }
 
-fn read_edge(base_path: &PathBuf, hash: Hash) -> impl Future<Item = Edge, Error = Error> {
+fn read_edge(base_path: &Path, hash: Hash) -> impl Future<Item = Edge, Error = Error> {
     let edge_path = base_path.join("edge");
     let vertex_path = base_path.join("vertex");
 
@@ -401,7 +429,8 @@ fn read_graph_edges(base_path: PathBuf, hash: Hash, mut graph: DirectedGraph) ->
 
 /// Reads the vertices and edges of a graph, specified by the provided graph_hash.
 pub fn read_graph(base_path: PathBuf, graph_hash: GraphHash) -> impl Future<Item = DirectedGraph, Error = Error> {
-    let graph = DirectedGraph::new();
+    let mode = if graph_hash.simple { GraphMode::Simple } else { GraphMode::Multi };
+    let graph = DirectedGraph::with_mode(mode);
 
     read_graph_vertices(base_path.clone(), graph_hash.vertex_vec_hash, graph)
         .and_then(move |graph| read_graph_edges(base_path, graph_hash.edge_vec_hash, graph))
@@ -411,8 +440,7 @@ pub fn load_graph(base_dir: PathBuf, name: &OsStr) -> impl Future<Item = Directe
     let path = base_dir.join("graph").join(name);
     tokio_fs::read(path)
         .map_err(Into::<Error>::into)
-        .and_then(|content| bincode::deserialize::<GraphHash>(&content)
-            .map_err(Into::<Error>::into))
+        .and_then(|content| deserialize_graph_hash(&content))
         .and_then(|graph_hash| read_graph(base_dir, graph_hash))
 }
 
@@ -424,7 +452,7 @@ mod test {
     use tokio::runtime::Runtime;
     use std::path::{Path, PathBuf};
     use std::ffi::OsString;
-    use histo_graph_core::graph::directed_graph::DirectedGraph;
+    use histo_graph_core::graph::directed_graph::{DirectedGraph, GraphMode};
     use crate::error::Result;
 
     #[test]
@@ -462,7 +490,7 @@ mod test {
 
         let path: PathBuf = Path::new("../target/test/store/").into();
 
-        let f = write_all_vertices_to_files(path, vertices.into_iter());
+        let f = write_all_vertices_to_files(path, vertices);
 
         let mut rt = Runtime::new()?;
         rt.block_on(f)?;
@@ -556,6 +584,30 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_write_and_read_graph_modes() -> Result<()> {
+        let mut simple = DirectedGraph::with_mode(GraphMode::Simple);
+        simple.add_edge(Edge(VertexId(31), VertexId(32)));
+
+        let mut multi = DirectedGraph::new();
+        multi.add_edge(Edge(VertexId(31), VertexId(32)));
+        multi.add_edge(Edge(VertexId(31), VertexId(32)));
+
+        let path: PathBuf = Path::new("../target/test/store/").into();
+
+        let mut rt = Runtime::new()?;
+        for graph in [simple, multi] {
+            let f = write_graph(path.clone(), &graph)
+                .map_err(Into::into)
+                .and_then({ let path = path.clone(); |graph_hash| read_graph(path, graph_hash) });
+
+            let result_graph = rt.block_on(f)?;
+            assert_eq!(graph, result_graph);
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_read_and_write_named_graph() -> Result<()> {
         let mut graph = DirectedGraph::new();
@@ -575,4 +627,26 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_load_legacy_graph_hash() -> Result<()> {
+        let mut graph = DirectedGraph::new();
+        graph.add_edge(Edge(VertexId(33), VertexId(34)));
+        graph.add_edge(Edge(VertexId(33), VertexId(34)));
+
+        let path: PathBuf = Path::new("../target/test/store/").into();
+        let name = OsString::from("legacygraph");
+
+        let mut rt = Runtime::new()?;
+        let GraphHash { vertex_vec_hash, edge_vec_hash, .. } = rt.block_on(write_graph(path.clone(), &graph))?;
+        let content = bincode::serialize(&LegacyGraphHash { vertex_vec_hash, edge_vec_hash })?;
+        std::fs::create_dir_all(path.join("graph"))?;
+        std::fs::write(path.join("graph").join(&name), content)?;
+
+        let result_graph = rt.block_on(load_graph(path, &name))?;
+        assert_eq!(graph, result_graph);
+        assert_eq!(result_graph.mode(), GraphMode::Multi);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,515 @@
+use histo_graph_core::graph::{
+    graph::VertexId,
+    key_map::KeyMap,
+    command::GraphCommand,
+    directed_graph::{DirectedGraph, GraphMode},
+};
+use histo_graph_core::history::{
+    hashlist::{HashList, NodeHash},
+    history::{Author, Comment, Commit, CommitInfo, Ref, ReflogEntry, Repository, Timestamp},
+};
+use histo_graph_core::historized_graph::{CheckpointPolicy, CommandHasher, HistorizedGraph};
+
+use crate::error::{Error, Result};
+use crate::file_storage::{GraphHash, read_graph, write_graph};
+
+use serde::{Serialize, Deserialize};
+
+use futures::future::{self, Either, Future};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    path::PathBuf,
+    rc::Rc,
+};
+use std::ffi::OsStr;
+
+// The start of a stored history.
+const HISTORY_MAGIC: [u8; 4] = *b"HGH\0";
+
+// The version of the layout of `StoredHistory`, stored after `HISTORY_MAGIC`.
+const HISTORY_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+enum CommandFile {
+    AddVertex(u64),
+    RemoveVertex(u64),
+    AddEdge(u64, u64),
+    RemoveEdge(u64, u64),
+}
+
+#[derive(Serialize, Deserialize)]
+struct CommitFile {
+    hash: u64,
+    author: String,
+    author_email: Option<String>,
+    author_timestamp: u64,
+    comment: String,
+    timestamp: u64,
+    parents: Vec<u64>,
+    trailers: Vec<(String, String)>,
+    commands: Vec<CommandFile>,
+}
+
+/// A reference, with the hashes of its commits from the most recent to the oldest.
+#[derive(Serialize, Deserialize)]
+enum RefFile {
+    Detached(Vec<u64>),
+    Tag(Vec<u64>, String),
+    Branch(Vec<u64>, String),
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReflogEntryFile {
+    reference: String,
+    old: Option<u64>,
+    new: Option<u64>,
+    operation: String,
+    timestamp: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+enum CheckpointPolicyFile {
+    OnDemand,
+    Every(u64),
+}
+
+/// The stored form of a `HistorizedGraph`: its commits, its references, its redo buffer, its
+/// checkpoints and the key bindings that no commit records yet. The graph itself is not stored,
+/// it is rebuilt from the nearest checkpoint.
+///
+/// It is stored after a version of its layout. The histories stored before the layout had a
+/// version are read in their own layout.
+#[derive(Serialize, Deserialize)]
+pub struct StoredHistory {
+    simple: bool,
+    checkpoint_policy: CheckpointPolicyFile,
+    current: RefFile,
+    refs: Vec<RefFile>,
+    commits: Vec<CommitFile>,
+    reflog: Vec<ReflogEntryFile>,
+    redo_buffer: Vec<u64>,
+    // the key bindings that no commit records yet, sorted by vertex id. Before the commits recorded
+    // the bindings, all the keys were stored here: the next commit records them.
+    keys: Vec<(String, u64)>,
+    // the checkpoints that are stored as graphs, by commit hash
+    checkpoints: Vec<(u64, GraphHash)>,
+    // the checkpoints that are held in memory, by commit hash
+    #[serde(skip)]
+    checkpoint_graphs: Vec<(u64, DirectedGraph)>,
+}
+
+impl From<CheckpointPolicy> for CheckpointPolicyFile {
+    fn from(policy: CheckpointPolicy) -> CheckpointPolicyFile {
+        match policy {
+            CheckpointPolicy::OnDemand => CheckpointPolicyFile::OnDemand,
+            CheckpointPolicy::Every(interval) => CheckpointPolicyFile::Every(interval as u64),
+        }
+    }
+}
+
+impl From<&CheckpointPolicyFile> for CheckpointPolicy {
+    fn from(policy: &CheckpointPolicyFile) -> CheckpointPolicy {
+        match *policy {
+            CheckpointPolicyFile::OnDemand => CheckpointPolicy::OnDemand,
+            CheckpointPolicyFile::Every(interval) => CheckpointPolicy::Every(interval as usize),
+        }
+    }
+}
+
+impl From<&GraphCommand> for CommandFile {
+    fn from(command: &GraphCommand) -> CommandFile {
+        match *command {
+            GraphCommand::AddVertex(VertexId(v)) => CommandFile::AddVertex(v),
+            GraphCommand::RemoveVertex(VertexId(v)) => CommandFile::RemoveVertex(v),
+            GraphCommand::AddEdge(VertexId(v1), VertexId(v2)) => CommandFile::AddEdge(v1, v2),
+            GraphCommand::RemoveEdge(VertexId(v1), VertexId(v2)) => CommandFile::RemoveEdge(v1, v2),
+        }
+    }
+}
+
+impl From<&CommandFile> for GraphCommand {
+    fn from(command: &CommandFile) -> GraphCommand {
+        match *command {
+            CommandFile::AddVertex(v) => GraphCommand::AddVertex(VertexId(v)),
+            CommandFile::RemoveVertex(v) => GraphCommand::RemoveVertex(VertexId(v)),
+            CommandFile::AddEdge(v1, v2) => GraphCommand::AddEdge(VertexId(v1), VertexId(v2)),
+            CommandFile::RemoveEdge(v1, v2) => GraphCommand::RemoveEdge(VertexId(v1), VertexId(v2)),
+        }
+    }
+}
+
+impl From<&Commit<Vec<GraphCommand>>> for CommitFile {
+    fn from(commit: &Commit<Vec<GraphCommand>>) -> CommitFile {
+        CommitFile {
+            hash: commit.hash().0,
+            author: commit.author().0.clone(),
+            author_email: commit.author_email().map(str::to_string),
+            author_timestamp: commit.author_timestamp().0,
+            comment: commit.comment().0.clone(),
+            timestamp: commit.timestamp().0,
+            parents: commit.parents().iter().map(|NodeHash(h)| *h).collect(),
+            trailers: commit.trailers().to_vec(),
+            commands: commit.item().iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<CommitFile> for Commit<Vec<GraphCommand>> {
+    fn from(commit: CommitFile) -> Commit<Vec<GraphCommand>> {
+        let info = CommitInfo {
+            author: Author(commit.author),
+            author_email: commit.author_email,
+            author_timestamp: Some(Timestamp(commit.author_timestamp)),
+            comment: Comment(commit.comment),
+            trailers: commit.trailers,
+        };
+        Commit::with_info(
+            info,
+            Timestamp(commit.timestamp),
+            commit.parents.into_iter().map(NodeHash).collect(),
+            NodeHash(commit.hash),
+            commit.commands.iter().map(Into::into).collect(),
+        )
+    }
+}
+
+impl From<&ReflogEntry> for ReflogEntryFile {
+    fn from(entry: &ReflogEntry) -> ReflogEntryFile {
+        ReflogEntryFile {
+            reference: entry.reference().to_string(),
+            old: entry.old().map(|NodeHash(h)| h),
+            new: entry.new_hash().map(|NodeHash(h)| h),
+            operation: entry.operation().to_string(),
+            timestamp: entry.timestamp().0,
+        }
+    }
+}
+
+impl From<&ReflogEntryFile> for ReflogEntry {
+    fn from(entry: &ReflogEntryFile) -> ReflogEntry {
+        ReflogEntry::new(
+            &entry.reference,
+            entry.old.map(NodeHash),
+            entry.new.map(NodeHash),
+            &entry.operation,
+            Timestamp(entry.timestamp),
+        )
+    }
+}
+
+fn hashs_to_vec(hashs: Rc<HashList>) -> Vec<u64> {
+    hashs.iter().map(|NodeHash(h)| h).collect()
+}
+
+fn vec_to_hashs(hashs: &[u64]) -> Rc<HashList> {
+    hashs
+        .iter()
+        .rev()
+        .fold(HashList::empty(), |list, &h| HashList::cons(NodeHash(h), list))
+}
+
+impl From<&Ref> for RefFile {
+    fn from(r: &Ref) -> RefFile {
+        match r {
+            Ref::Detached(hashs) => RefFile::Detached(hashs_to_vec(hashs.clone())),
+            Ref::Tag(hashs, name) => RefFile::Tag(hashs_to_vec(hashs.clone()), name.clone()),
+            Ref::Branch(hashs, name) => RefFile::Branch(hashs_to_vec(hashs.clone()), name.clone()),
+        }
+    }
+}
+
+impl From<&RefFile> for Ref {
+    fn from(r: &RefFile) -> Ref {
+        match r {
+            RefFile::Detached(hashs) => Ref::Detached(vec_to_hashs(hashs)),
+            RefFile::Tag(hashs, name) => Ref::Tag(vec_to_hashs(hashs), name.clone()),
+            RefFile::Branch(hashs, name) => Ref::Branch(vec_to_hashs(hashs), name.clone()),
+        }
+    }
+}
+
+impl From<&HistorizedGraph> for StoredHistory {
+    fn from(graph: &HistorizedGraph) -> StoredHistory {
+        let mut history = StoredHistory::from_repository(graph.repository(), graph.mode(), graph.checkpoint_policy(), graph.pending_keys());
+        history.redo_buffer = graph.redo_buffer().iter().map(|NodeHash(h)| *h).collect();
+        history.checkpoint_graphs = graph.checkpoints()
+            .into_iter()
+            .map(|(NodeHash(h), graph)| (h, graph))
+            .collect();
+        history
+    }
+}
+
+impl StoredHistory {
+
+    /// Returns the stored form of a history without redo buffer and checkpoints, with the key
+    /// bindings that no commit records yet.
+    pub(crate) fn from_repository(
+        repository: &Repository<Vec<GraphCommand>, CommandHasher>,
+        mode: GraphMode,
+        checkpoint_policy: CheckpointPolicy,
+        keys: &KeyMap,
+    ) -> StoredHistory {
+        let mut commits: Vec<CommitFile> = repository.all_commits().map(Into::into).collect();
+        // the order of the commits in a repository is arbitrary, sorting makes the file stable
+        commits.sort_by_key(|c| c.hash);
+        let mut keys: Vec<(String, u64)> = keys
+            .iter()
+            .map(|(key, VertexId(id))| (key.to_string(), id))
+            .collect();
+        keys.sort_by_key(|&(_, id)| id);
+
+        StoredHistory {
+            simple: mode == GraphMode::Simple,
+            checkpoint_policy: checkpoint_policy.into(),
+            current: repository.current().into(),
+            refs: repository.list_refs().into_iter().map(Into::into).collect(),
+            commits,
+            reflog: repository.all_reflog().iter().map(Into::into).collect(),
+            redo_buffer: Vec::new(),
+            keys,
+            checkpoints: Vec::new(),
+            checkpoint_graphs: Vec::new(),
+        }
+    }
+
+    /// Adds a checkpoint whose graph is already stored.
+    pub(crate) fn add_stored_checkpoint(&mut self, NodeHash(hash): NodeHash, graph_hash: GraphHash) {
+        self.checkpoints.push((hash, graph_hash));
+    }
+
+    /// Rebuilds the `HistorizedGraph`, replaying the commits of its current reference from the
+    /// checkpoints that have been loaded.
+    pub fn into_historized_graph(self) -> Result<HistorizedGraph> {
+        let mode = if self.simple { GraphMode::Simple } else { GraphMode::Multi };
+        let repository = Repository::restore(
+            CommandHasher {},
+            (&self.current).into(),
+            self.refs.iter().map(Into::into).collect(),
+            self.commits.into_iter().map(Into::into).collect(),
+            self.reflog.iter().map(Into::into).collect(),
+        );
+        let redo_buffer = self.redo_buffer.into_iter().map(NodeHash).collect();
+        let checkpoints = self.checkpoint_graphs
+            .into_iter()
+            .map(|(h, graph)| (NodeHash(h), graph))
+            .collect();
+        let mut keys = KeyMap::new();
+        for (key, id) in self.keys.iter() {
+            if !keys.insert(key, VertexId(*id)) {
+                return Err(Error::HistoryError(format!("Key '{}' or vertex {} is bound twice", key, id)));
+            }
+        }
+
+        HistorizedGraph::restore(repository, mode, redo_buffer, (&self.checkpoint_policy).into(), checkpoints, keys)
+            .map_err(Error::HistoryError)
+    }
+
+    /// Returns the checkpoints that are stored, by commit hash.
+    /// Only the checkpoint nearest to the current commit is loaded with the history, the others
+    /// can be loaded with `load_checkpoint`.
+    pub fn stored_checkpoints(&self) -> Vec<(NodeHash, GraphHash)> {
+        self.checkpoints
+            .iter()
+            .map(|(h, graph_hash)| (NodeHash(*h), graph_hash.clone()))
+            .collect()
+    }
+}
+
+/// Loads the first of the given commits that has a stored checkpoint, given the commits of a
+/// history from the most recent to the oldest.
+/// Returns the hash of that commit with the state of the graph after it, or `None` if none of the
+/// commits has a checkpoint.
+pub fn load_checkpoint(base_path: PathBuf, checkpoints: &[(NodeHash, GraphHash)], hashs: &HashList) -> impl Future<Item=Option<(NodeHash, DirectedGraph)>, Error=Error> {
+    let checkpoints: HashMap<NodeHash, &GraphHash> = checkpoints
+        .iter()
+        .map(|(hash, graph_hash)| (*hash, graph_hash))
+        .collect();
+    let nearest = hashs
+        .iter()
+        .find_map(|hash| checkpoints.get(&hash).map(|&graph_hash| (hash, graph_hash.clone())));
+
+    match nearest {
+        None => Either::A(future::ok(None)),
+        Some((hash, graph_hash)) => Either::B(read_graph(base_path, graph_hash)
+            .map(move |graph| Some((hash, graph)))),
+    }
+}
+
+/// Reads the checkpoints of the history stored at `path`, if any.
+/// A file that can't be deserialized is about to be overwritten, it has no checkpoints to keep.
+fn read_stored_checkpoints(path: PathBuf) -> impl Future<Item=Vec<(u64, GraphHash)>, Error=Error> {
+    tokio_fs::read(path).then(|content| match content {
+        Ok(content) => Ok(deserialize_history(&content)
+            .map(|history| history.checkpoints)
+            .unwrap_or_default()),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    })
+}
+
+/// Saves a history under the given name.
+///
+/// Writes the checkpoints that are not stored yet as graphs, then creates a subdirectory
+/// `history/` of the provided base_path, and saves the serialized history in that directory.
+/// Returns the path to the written file.
+pub fn save_history_as(base_path: PathBuf, name: &OsStr, mut history: StoredHistory) -> impl Future<Item=PathBuf, Error=Error> {
+    let dir = base_path.join("history");
+    let path = dir.join(name);
+    read_stored_checkpoints(path.clone())
+        .and_then(move |stored| {
+            // the state after a commit never changes, a stored checkpoint is kept as it is
+            let commits: HashSet<u64> = history.commits.iter().map(|c| c.hash).collect();
+            let stored: HashMap<u64, GraphHash> = stored
+                .into_iter()
+                .chain(std::mem::take(&mut history.checkpoints))
+                .filter(|(h, _)| commits.contains(h))
+                .collect();
+            let writes: Vec<_> = std::mem::take(&mut history.checkpoint_graphs)
+                .into_iter()
+                .filter(|(h, _)| !stored.contains_key(h))
+                .map(|(h, graph)| write_graph(base_path.clone(), &graph).map(move |graph_hash| (h, graph_hash)))
+                .collect();
+            future::join_all(writes)
+                .map_err(Into::<Error>::into)
+                .map(move |written| {
+                    history.checkpoints = stored.into_iter().chain(written).collect();
+                    history.checkpoints.sort_by_key(|&(h, _)| h);
+                    history
+                })
+        })
+        .and_then(move |history| tokio_fs::create_dir_all(dir)
+            .map(move |_| history)
+            .map_err(Into::<Error>::into))
+        .and_then(|history| bincode::serialize(&(HISTORY_MAGIC, HISTORY_VERSION, &history))
+            .map_err(Into::<Error>::into))
+        .and_then({
+            let path = path.clone();
+            move |content| tokio_fs::write(path, content)
+                .map_err(Into::<Error>::into)
+        })
+        .map(|_| path)
+}
+
+/// Loads the history saved under the given name, with the checkpoint nearest to its current
+/// commit.
+pub fn load_history(base_path: PathBuf, name: &OsStr) -> impl Future<Item=StoredHistory, Error=Error> {
+    let path = base_path.join("history").join(name);
+    tokio_fs::read(path)
+        .map_err(Into::<Error>::into)
+        .and_then(|content| deserialize_history(&content))
+        .and_then(move |mut history| {
+            let hashs = match &history.current {
+                RefFile::Detached(hashs) | RefFile::Tag(hashs, _) | RefFile::Branch(hashs, _) => vec_to_hashs(hashs),
+            };
+            load_checkpoint(base_path, &history.stored_checkpoints(), &hashs)
+                .map(move |checkpoint| {
+                    history.checkpoint_graphs
+                        .extend(checkpoint.map(|(NodeHash(h), graph)| (h, graph)));
+                    history
+                })
+        })
+}
+
+/// Deserializes a stored history, which has to start with `HISTORY_MAGIC` and the current version.
+fn deserialize_history(content: &[u8]) -> Result<StoredHistory> {
+    if !content.starts_with(&HISTORY_MAGIC) {
+        return Err(Error::HistoryError("The content is not a stored history".to_string()));
+    }
+    let (_, version): ([u8; 4], u32) = bincode::deserialize(content)?;
+    if version != HISTORY_VERSION {
+        return Err(Error::HistoryError(format!("The history has the layout version {}, only version {} is supported", version, HISTORY_VERSION)));
+    }
+    let (_, _, history): ([u8; 4], u32, StoredHistory) = bincode::deserialize(content)?;
+    Ok(history)
+}
+
+#[cfg(test)]
+mod test {
+    use histo_graph_core::graph::graph::{Edge, VertexId};
+    use histo_graph_core::graph::directed_graph::GraphMode;
+    use histo_graph_core::historized_graph::{CheckpointPolicy, HistorizedGraph};
+    use super::*;
+    use futures::future::Future;
+    use tokio::runtime::Runtime;
+    use std::path::{Path, PathBuf};
+    use std::ffi::OsString;
+    use crate::error::Result;
+
+    #[test]
+    fn test_save_and_load_history() -> Result<()> {
+        let mut graph = HistorizedGraph::with_mode(GraphMode::Simple);
+        graph.set_commit_info(CommitInfo::new(Author("lauren".to_string()), Comment("test".to_string()))
+            .with_author_email("lauren@example.com")
+            .with_trailer("Ticket", "GRAPH-12"));
+        // a binding recorded by a commit, and a pending one below
+        let rand = graph.intern("rand");
+        graph.add_vertex(rand);
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+        graph.add_edge(Edge(VertexId(2), VertexId(3)));
+        graph.remove_vertex(VertexId(2));
+        graph.undo();
+        let serde = graph.intern("serde");
+
+        let path: PathBuf = Path::new("../target/test/store/").into();
+
+        let f = save_history_as(path.clone(), &OsString::from("laurenhistory"), (&graph).into())
+            .and_then(move | _ | load_history(path, &OsString::from("laurenhistory")));
+
+        let mut rt = Runtime::new()?;
+        let mut result = rt.block_on(f)?.into_historized_graph()?;
+
+        assert_eq!(result.graph(), graph.graph());
+        assert_eq!(result.redo_buffer(), graph.redo_buffer());
+        assert_eq!(result.repository().head(), graph.repository().head());
+        assert_eq!(result.repository().all_reflog(), graph.repository().all_reflog());
+        assert_eq!(result.keys(), graph.keys());
+        assert_eq!(result.pending_keys(), graph.pending_keys());
+        assert_eq!(result.intern("serde"), serde);
+        assert_eq!(result.keys().vertex_id("rand"), Some(rand));
+
+        assert!(result.redo());
+        assert!(result.undo());
+        assert!(result.undo());
+        assert_eq!(result.edge_count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_and_load_checkpoints() -> Result<()> {
+        let mut graph = HistorizedGraph::new();
+        graph.set_checkpoint_policy(CheckpointPolicy::Every(10));
+        for i in 0..25 {
+            graph.add_edge(Edge(VertexId(i), VertexId(i + 1)));
+        }
+        let hashs: Vec<NodeHash> = graph.repository().current().hashs().iter().collect();
+        graph.at("HEAD").map_err(Error::HistoryError)?;
+        assert_eq!(graph.checkpoints().len(), 2);
+
+        let path: PathBuf = Path::new("../target/test/store/").into();
+        let name = OsString::from("checkpointhistory");
+
+        let mut rt = Runtime::new()?;
+        rt.block_on(save_history_as(path.clone(), &name, (&graph).into()))?;
+        let history = rt.block_on(load_history(path.clone(), &name))?;
+
+        // only the checkpoint nearest to the head is loaded
+        let checkpoints = history.stored_checkpoints();
+        assert_eq!(checkpoints.len(), 2);
+        let result = history.into_historized_graph()?;
+        assert_eq!(result.graph(), graph.graph());
+        assert_eq!(result.checkpoints().len(), 1);
+        assert_eq!(result.replay_metrics().last_replay_length(), 5);
+        assert_eq!(result.checkpoint_policy(), CheckpointPolicy::Every(10));
+
+        let past = graph.repository().resolve(&hashs[10].to_string()).map_err(Error::HistoryError)?;
+        let (hash, checkpoint) = rt.block_on(load_checkpoint(path, &checkpoints, &past))?
+            .expect("a checkpoint");
+        assert_eq!(hash, hashs[15]);
+        assert_eq!(checkpoint.edge_count(), 10);
+
+        Ok(())
+    }
+}
@@ -1,2 +1,4 @@
+pub mod bulk_import;
 pub mod error;
 pub mod file_storage;
+pub mod history_storage;
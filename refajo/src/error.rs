@@ -1,13 +1,35 @@
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     FileError(histo_graph_file::error::Error),
     SerdeJsonError(serde_json::error::Error),
     IoError(std::io::Error),
     ParseIntError(std::num::ParseIntError),
+    ParseFloatError(std::num::ParseFloatError),
+    HistoryError(String),
+    GraphMlError(histo_graph_serde::graphml::GraphMlError),
+    DelimitedError(histo_graph_serde::delimited::DelimitedError),
+    Usage(String),
 }
 
 use Error::*;
 
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FileError(e) => write!(f, "storage error: {:?}", e),
+            SerdeJsonError(e) => write!(f, "json error: {}", e),
+            IoError(e) => write!(f, "io error: {}", e),
+            ParseIntError(e) => write!(f, "invalid number: {}", e),
+            ParseFloatError(e) => write!(f, "invalid number: {}", e),
+            HistoryError(e) => write!(f, "history error: {}", e),
+            GraphMlError(e) => write!(f, "graphml error: {}", e),
+            DelimitedError(e) => write!(f, "delimited text error: {}", e),
+            Usage(e) => write!(f, "usage error: {}", e),
+        }
+    }
+}
+
 impl From<histo_graph_file::error::Error> for Error {
     fn from(e: histo_graph_file::error::Error) -> Error {
         FileError(e)
@@ -32,4 +54,34 @@ impl From<std::num::ParseIntError> for Error {
     }
 }
 
+impl From<std::num::ParseFloatError> for Error {
+    fn from(e: std::num::ParseFloatError) -> Error {
+        ParseFloatError(e)
+    }
+}
+
+impl From<histo_graph_serde::graphml::GraphMlError> for Error {
+    fn from(e: histo_graph_serde::graphml::GraphMlError) -> Error {
+        GraphMlError(e)
+    }
+}
+
+impl From<histo_graph_serde::delimited::DelimitedError> for Error {
+    fn from(e: histo_graph_serde::delimited::DelimitedError) -> Error {
+        DelimitedError(e)
+    }
+}
+
+impl From<String> for Error {
+    fn from(e: String) -> Error {
+        HistoryError(e)
+    }
+}
+
+impl From<histo_graph_core::historized_graph::PickError> for Error {
+    fn from(e: histo_graph_core::historized_graph::PickError) -> Error {
+        HistoryError(e.to_string())
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,13 +1,24 @@
-use clap::{App, SubCommand, Arg};
+use clap::{App, SubCommand, Arg, ArgMatches};
 use histo_graph_file::file_storage::*;
+use histo_graph_file::history_storage::*;
+use histo_graph_file::bulk_import::BulkLoader;
 use std::path::{PathBuf, Path};
-use histo_graph_serde::directed_graph_serde::DirectedGraphSer;
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
 use tokio::runtime::Runtime;
-use error::Result;
-use histo_graph_core::graph::directed_graph::DirectedGraph;
-use histo_graph_core::graph::graph::{VertexId, Edge};
-use futures::future::Future;
+use histo_graph_serde::delimited::{read_delimited_with, write_delimited, Delimited, DelimitedReader, Layout};
+use histo_graph_serde::dot::{write_dot, write_dot_diff};
+use histo_graph_serde::graphml::{read_graphml, write_graphml, GraphMl};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use error::{Error, Result};
+use histo_graph_core::graph::centrality::ranking;
+use histo_graph_core::graph::diff::diff;
+use histo_graph_core::graph::directed_graph::{DirectedGraph, GraphMode};
+use histo_graph_core::graph::graph::{Direction, VertexId, Edge};
+use histo_graph_core::graph::command::GraphCommand;
+use histo_graph_core::historized_graph::{CheckpointPolicy, Commands, HistorizedGraph};
+use histo_graph_core::history::history::{Author, Comment, Commit, CommitInfo, Hashs, Ref, Timestamp};
 
 mod error;
 
@@ -16,10 +27,134 @@ fn main() -> Result<()> {
         .version("0.1.0")
         .about("Historizes graphs")
         .subcommand(SubCommand::with_name("init")
-            .about("initializes a new graph"))
+            .about("initializes a new graph")
+            .arg(Arg::with_name("simple")
+                .long("simple")
+                .help("creates a simple graph, that rejects parallel edges"))
+            .arg(Arg::with_name("checkpoint-interval")
+                .long("checkpoint-interval")
+                .value_name("commits")
+                .help("keeps a checkpoint of the graph every given number of commits, 0 keeps them on demand only (default: 64)"))
+        )
         .subcommand(SubCommand::with_name("show")
             .about("shows a graph")
+            .arg(Arg::with_name("at")
+                .long("at")
+                .value_name("rev")
+                .conflicts_with("at-time")
+                .help("shows the graph as of a revision: HEAD, a branch, a tag or a commit hash"))
+            .arg(Arg::with_name("at-time")
+                .long("at-time")
+                .value_name("seconds")
+                .help("shows the graph as of a time, in seconds since the UNIX epoch"))
+            .arg(Arg::with_name("format")
+                .long("format")
+                .possible_values(&["json", "pretty", "dot", "edgelist"])
+                .default_value("json")
+                .help("writes the graph as one line of JSON, as indented JSON, in the DOT language, or as an edge list"))
+            .arg(Arg::with_name("vertex")
+                .long("vertex")
+                .value_name("id")
+                .help("shows the neighbourhood of a vertex, given by its id or its key: the vertices connected to it and their edges"))
+            .arg(Arg::with_name("depth")
+                .long("depth")
+                .value_name("steps")
+                .requires("vertex")
+                .help("the number of steps from the vertex to its neighbours (default: 1)"))
+            .arg(Arg::with_name("limit")
+                .long("limit")
+                .value_name("count")
+                .help("shows the subgraph of the given number of vertices with the lowest ids"))
+            .arg(Arg::with_name("stats")
+                .long("stats")
+                .help("shows the vertex and edge counts and the degree distributions instead of the graph, in JSON"))
+            .arg(Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .help("doesn't write the name of the sub-command to the standard error"))
             )
+        .subcommand(SubCommand::with_name("export")
+            .about("exports the graph")
+            .arg(Arg::with_name("format")
+                .long("format")
+                .possible_values(&["json", "graphml", "csv", "tsv"])
+                .default_value("json"))
+            .arg(Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("file")
+                .help("writes the graph to a file (default: the standard output)"))
+            .args(&delimited_args())
+        )
+        .subcommand(SubCommand::with_name("dot")
+            .about("writes the graph, or the changes between two revisions, in the DOT language of Graphviz")
+            .arg(Arg::with_name("diff")
+                .long("diff")
+                .value_names(&["a", "b"])
+                .help("shows the changes from revision <a> to revision <b>: added elements in green, removed ones in red"))
+        )
+        .subcommand(SubCommand::with_name("import")
+            .about("replaces the graph by the content of a file, in a single commit")
+            .arg(Arg::with_name("file")
+                .required(true)
+                .index(1))
+            .arg(Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["json", "graphml", "csv", "tsv"])
+                .help("the format of the file (default: given by the extension .graphml, .csv or .tsv, json otherwise)"))
+            .arg(Arg::with_name("bulk")
+                .long("bulk")
+                .help("writes the graph straight to the store, as the single commit of a new history: the graph must not have commits yet"))
+            .args(&delimited_args())
+        )
+        .subcommand(SubCommand::with_name("stats")
+            .about("shows the metrics of the graph: degree distributions, density, self-loops, parallel edges, sources, sinks, diameter and clustering coefficient")
+            .arg(Arg::with_name("history")
+                .long("history")
+                .help("shows the metrics after each commit of the current branch, one line of JSON per commit, from the first commit"))
+            .arg(Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .help("doesn't write the name of the sub-command to the standard error"))
+        )
+        .subcommand(SubCommand::with_name("rank")
+            .about("shows the most central vertices, from the highest score: one line per vertex, with its rank, its key or id, and its score")
+            .arg(Arg::with_name("by")
+                .long("by")
+                .possible_values(&["pagerank", "betweenness", "degree-in", "degree-out"])
+                .default_value("pagerank")
+                .help("the centrality measure"))
+            .arg(Arg::with_name("top")
+                .long("top")
+                .value_name("count")
+                .default_value("10")
+                .help("the number of vertices to show"))
+            .arg(Arg::with_name("damping")
+                .long("damping")
+                .value_name("factor")
+                .default_value("0.85")
+                .help("the damping factor of the PageRank"))
+            .arg(Arg::with_name("iterations")
+                .long("iterations")
+                .value_name("count")
+                .default_value("100")
+                .help("the number of iterations of the PageRank"))
+            .arg(Arg::with_name("threads")
+                .long("threads")
+                .value_name("count")
+                .help("the number of threads of the betweenness centrality (default: the available parallelism)"))
+            .arg(Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .help("doesn't write the name of the sub-command to the standard error"))
+        )
+        .subcommand(SubCommand::with_name("components")
+            .about("shows the connected components of a graph")
+            .arg(Arg::with_name("weak")
+                .long("weak")
+                .help("shows the weakly connected components instead of the strongly connected ones"))
+        )
         .subcommand(SubCommand::with_name("add-vertex")
             .about("adds a vertex")
             .arg(Arg::with_name("vertexId")
@@ -35,34 +170,420 @@ fn main() -> Result<()> {
                 .required(true)
                 .index(2))
         )
+        .subcommand(SubCommand::with_name("log")
+            .about("shows the commits of a revision range, from the most recent to the oldest")
+            .arg(Arg::with_name("range")
+                .index(1)
+                .help("a revision, or a range a..b of the commits of b that are not in a (default: HEAD)"))
+            .arg(Arg::with_name("author")
+                .long("author")
+                .takes_value(true)
+                .help("shows the commits whose author name or email contains the given text"))
+            .arg(Arg::with_name("grep")
+                .long("grep")
+                .takes_value(true)
+                .help("shows the commits whose comment contains the given text"))
+            .arg(Arg::with_name("since")
+                .long("since")
+                .value_name("seconds")
+                .help("shows the commits created at or after a time, in seconds since the UNIX epoch"))
+            .arg(Arg::with_name("until")
+                .long("until")
+                .value_name("seconds")
+                .help("shows the commits created at or before a time, in seconds since the UNIX epoch"))
+            .arg(Arg::with_name("max-count")
+                .short("n")
+                .long("max-count")
+                .takes_value(true)
+                .help("shows at most the given number of commits"))
+            .arg(Arg::with_name("commands")
+                .short("p")
+                .long("commands")
+                .help("shows the commands of the commits"))
+        )
+        .subcommand(SubCommand::with_name("blame-vertex")
+            .about("shows the commits that added or removed a vertex, or an edge from or to it")
+            .arg(Arg::with_name("vertexId")
+                .required(true)
+                .index(1))
+        )
+        .subcommand(SubCommand::with_name("blame-edge")
+            .about("shows the commits that added or removed an edge")
+            .arg(Arg::with_name("vertexId_from")
+                .required(true)
+                .index(1))
+            .arg(Arg::with_name("vertexId_to")
+                .required(true)
+                .index(2))
+        )
+        .subcommand(SubCommand::with_name("refs")
+            .about("lists the branches and the tags"))
+        .subcommand(SubCommand::with_name("branch")
+            .about("creates a branch on the current commit")
+            .arg(Arg::with_name("name")
+                .required(true)
+                .index(1))
+            .arg(Arg::with_name("delete")
+                .short("d")
+                .long("delete")
+                .conflicts_with("rename")
+                .help("deletes the branch instead"))
+            .arg(Arg::with_name("rename")
+                .short("m")
+                .long("rename")
+                .value_name("new_name")
+                .help("renames the branch instead"))
+        )
+        .subcommand(SubCommand::with_name("tag")
+            .about("tags the current commit")
+            .arg(Arg::with_name("name")
+                .required(true)
+                .index(1))
+            .arg(Arg::with_name("delete")
+                .short("d")
+                .long("delete")
+                .help("deletes the tag instead"))
+        )
+        .subcommand(SubCommand::with_name("reflog")
+            .about("shows the moves of HEAD or of a branch, the most recent first")
+            .arg(Arg::with_name("ref")
+                .index(1)
+                .help("HEAD or the name of a branch (default: HEAD)"))
+        )
+        .subcommand(SubCommand::with_name("checkout")
+            .about("checks out a revision: HEAD, a branch, a tag, <ref>@{n} or a commit hash")
+            .arg(Arg::with_name("rev")
+                .required(true)
+                .index(1))
+        )
+        .subcommand(SubCommand::with_name("cherry-pick")
+            .about("applies the changes of a commit on top of the current branch")
+            .arg(Arg::with_name("rev")
+                .required(true)
+                .index(1))
+        )
+        .subcommand(SubCommand::with_name("revert")
+            .about("commits the revert of the changes of a commit")
+            .arg(Arg::with_name("rev")
+                .required(true)
+                .index(1))
+        )
+        .subcommand(SubCommand::with_name("squash")
+            .about("replaces the commits of the range <from>..<to> of the current branch by a single commit")
+            .arg(Arg::with_name("from")
+                .required(true)
+                .index(1))
+            .arg(Arg::with_name("to")
+                .required(true)
+                .index(2))
+        )
+        .subcommand(SubCommand::with_name("checkpoint")
+            .about("keeps the current state of the graph as a checkpoint"))
+        .subcommand(SubCommand::with_name("undo")
+            .about("undoes the most recent change of the graph"))
+        .subcommand(SubCommand::with_name("redo")
+            .about("redoes the most recently undone change of the graph"))
         .get_matches();
 
     let base_dir: PathBuf = Path::new(".store/").into();
     let name = &OsString::from("current");
 
-    if let Some(_) = matches.subcommand_matches("show") {
-        println!("Running sub-command 'show' ");
+    if let Some(matches) = matches.subcommand_matches("show") {
+        // the graph is written to the standard output
+        if !matches.is_present("quiet") {
+            eprintln!("Running sub-command 'show' ");
+        }
+
+        let mut rt = Runtime::new()?;
+        let (history, graph) = if let Some(rev) = matches.value_of("at") {
+            let history = load_historized_graph_for(&mut rt, base_dir.clone(), name, |graph| graph.repository().resolve(rev))?;
+            let graph = history.at(rev)?;
+            (Some(history), graph)
+        } else if let Some(seconds) = matches.value_of("at-time") {
+            let timestamp = Timestamp(std::str::FromStr::from_str(seconds)?);
+            let history = load_historized_graph_for(&mut rt, base_dir.clone(), name, |graph| Ok(graph.repository().resolve_time(timestamp)))?;
+            let graph = history.at_time(timestamp)?;
+            (Some(history), graph)
+        } else {
+            (None, rt.block_on(load_graph(base_dir.clone(), name))?)
+        };
+
+        let graph = if let Some(vertex) = matches.value_of("vertex") {
+            // the keys of the vertices are stored with the history
+            let history = match history {
+                Some(history) => history,
+                None => load_historized_graph(&mut rt, base_dir, name)?,
+            };
+            let vertex_id = find_vertex(&history, vertex)?;
+            if !graph.contains_vertex(vertex_id) {
+                return Err(Error::Usage(format!("The graph doesn't contain the vertex '{}'", vertex)));
+            }
+            let depth = matches.value_of("depth").map_or(Ok(1), str::parse)?;
+            graph.ego_graph(vertex_id, depth, Direction::Both)
+        } else {
+            graph
+        };
+        let graph = if let Some(limit) = matches.value_of("limit") {
+            let mut vertices: Vec<VertexId> = graph.vertices().copied().collect();
+            vertices.sort();
+            vertices.truncate(limit.parse()?);
+            graph.subgraph(vertices)
+        } else {
+            graph
+        };
+
+        let stdout = std::io::stdout();
+        let mut out = BufWriter::new(stdout.lock());
+        let pretty = matches.value_of("format") == Some("pretty");
+        if matches.is_present("stats") {
+            let stats = graph_stats(&graph);
+            if pretty {
+                serde_json::to_writer_pretty(&mut out, &stats)?;
+            } else {
+                serde_json::to_writer(&mut out, &stats)?;
+            }
+            writeln!(out)?;
+        } else {
+            match matches.value_of("format") {
+                Some("dot") => write_dot(&graph, &mut out)?,
+                Some("edgelist") => write_delimited(&graph, &Delimited { delimiter: ' ', ..Delimited::csv() }, &mut out)?,
+                _ => {
+                    if pretty {
+                        serde_json::to_writer_pretty(&mut out, &graph)?;
+                    } else {
+                        serde_json::to_writer(&mut out, &graph)?;
+                    }
+                    writeln!(out)?;
+                }
+            }
+        }
+        out.flush()?;
+
+        return Ok(());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("export") {
+        // the graph may be written to the standard output
+        eprintln!("Running sub-command 'export' ");
+
+        let mut rt = Runtime::new()?;
+        let graph = rt.block_on(load_graph(base_dir, name))?;
+        let mut out: Box<dyn Write> = match matches.value_of("output") {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(BufWriter::new(std::io::stdout())),
+        };
+        match matches.value_of("format") {
+            Some("graphml") => write_graphml(&GraphMl::from(graph), &mut out)?,
+            Some(format @ "csv") | Some(format @ "tsv") => write_delimited(&graph, &delimited_format(format, matches)?, &mut out)?,
+            _ => {
+                serde_json::to_writer(&mut out, &graph)?;
+                writeln!(out)?;
+            }
+        }
+        out.flush()?;
+
+        return Ok(());
+    }
 
-        let f = load_graph(base_dir, &name);
+    if let Some(matches) = matches.subcommand_matches("dot") {
+        // the graph is written to the standard output
+        eprintln!("Running sub-command 'dot' ");
+
+        let mut rt = Runtime::new()?;
+        let stdout = std::io::stdout();
+        let mut out = BufWriter::new(stdout.lock());
+        if let Some(mut revs) = matches.values_of("diff") {
+            let (a, b) = (revs.next().unwrap_or("HEAD"), revs.next().unwrap_or("HEAD"));
+            let graph = load_historized_graph_for(&mut rt, base_dir, name, |graph| graph.repository().resolve(a))?;
+            let (g1, g2) = (graph.at(a)?, graph.at(b)?);
+            write_dot_diff(&g1, &diff(&g1, &g2), &mut out)?;
+        } else {
+            let graph = rt.block_on(load_graph(base_dir, name))?;
+            write_dot(&graph, &mut out)?;
+        }
+        out.flush()?;
+
+        return Ok(());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("import") {
+        println!("Running sub-command 'import' ");
+        if let Some(path) = matches.value_of("file") {
+            let format = match matches.value_of("format") {
+                Some(format) => format,
+                None => match Path::new(path).extension().and_then(OsStr::to_str) {
+                    Some("graphml") => "graphml",
+                    Some("csv") => "csv",
+                    Some("tsv") => "tsv",
+                    _ => "json",
+                },
+            };
+
+            let mut rt = Runtime::new()?;
+            let mut graph = load_historized_graph(&mut rt, base_dir.clone(), name)?;
+            let input = BufReader::new(File::open(path)?);
+
+            if matches.is_present("bulk") {
+                if graph.repository().all_commits().next().is_some() {
+                    return Err(Error::Usage(format!("The bulk import writes a new history, {:?} already has commits", name)));
+                }
+                let mut loader = BulkLoader::new(graph.mode());
+                if format == "csv" || format == "tsv" {
+                    // the lines are loaded as they are read
+                    let format = delimited_format(format, matches)?;
+                    for command in DelimitedReader::with_resolver(input, format, |vertex| Some(intern_vertex(&mut graph, vertex))) {
+                        match command? {
+                            GraphCommand::AddVertex(vertex_id) => loader.add_vertex(vertex_id),
+                            GraphCommand::AddEdge(from, to) => loader.add_edge(Edge(from, to)),
+                            _ => false,
+                        };
+                        if loader.needs_flush() {
+                            rt.block_on(loader.flush(base_dir.clone()))?;
+                        }
+                    }
+                } else {
+                    let imported = read_import(&mut graph, input, format, matches)?;
+                    for &vertex_id in imported.vertices() {
+                        loader.add_vertex(vertex_id);
+                        if loader.needs_flush() {
+                            rt.block_on(loader.flush(base_dir.clone()))?;
+                        }
+                    }
+                    for &edge in imported.edges() {
+                        loader.add_edge(edge);
+                        if loader.needs_flush() {
+                            rt.block_on(loader.flush(base_dir.clone()))?;
+                        }
+                    }
+                }
+
+                let info = CommitInfo::new(Author("auto".to_string()), Comment(format!("Import {}", path)));
+                let summary = rt.block_on(loader.write(base_dir, name, info, graph.checkpoint_policy(), graph.pending_keys()))?;
+                println!("Imported {} vertices and {} edges in commit {}", summary.vertices, summary.edges, summary.commit);
+                println!("Skipped {} duplicates, wrote {} new files", summary.duplicates, summary.written_files);
+                return Ok(());
+            }
+
+            let imported = read_import(&mut graph, input, format, matches)?;
+            let commands = diff(graph.graph(), &imported).as_commands();
+            graph.try_execute(&commands)?;
+            println!("Imported {} vertices and {} edges", graph.vertex_count(), graph.edge_count());
+            save_historized_graph(&mut rt, base_dir, name, &graph)?;
+        }
+
+        return Ok(());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("stats") {
+        // the metrics are written to the standard output
+        if !matches.is_present("quiet") {
+            eprintln!("Running sub-command 'stats' ");
+        }
+
+        let mut rt = Runtime::new()?;
+        let stdout = std::io::stdout();
+        let mut out = BufWriter::new(stdout.lock());
+        if matches.is_present("history") {
+            let graph = load_historized_graph(&mut rt, base_dir, name)?;
+            for (hash, metrics) in graph.metrics_history() {
+                let mut line = serde_json::to_value(metrics)?;
+                if let Some(fields) = line.as_object_mut() {
+                    let timestamp = graph.repository().find_commit(hash).map_or(0, |commit| commit.timestamp().0);
+                    fields.insert("commit".to_string(), hash.to_string().into());
+                    fields.insert("timestamp".to_string(), timestamp.into());
+                }
+                serde_json::to_writer(&mut out, &line)?;
+                writeln!(out)?;
+            }
+        } else {
+            let graph = rt.block_on(load_graph(base_dir, name))?;
+            serde_json::to_writer(&mut out, &graph.metrics())?;
+            writeln!(out)?;
+        }
+        out.flush()?;
+
+        return Ok(());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("rank") {
+        // the ranking is written to the standard output
+        if !matches.is_present("quiet") {
+            eprintln!("Running sub-command 'rank' ");
+        }
+
+        let mut rt = Runtime::new()?;
+        // the keys of the vertices are stored with the history
+        let graph = load_historized_graph(&mut rt, base_dir, name)?;
+        let scores = match matches.value_of("by") {
+            Some("betweenness") => {
+                let threads = match matches.value_of("threads") {
+                    Some(threads) => threads.parse()?,
+                    None => std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+                };
+                graph.graph().betweenness_centrality(threads)
+            }
+            Some("degree-in") => graph.graph().degree_centrality(Direction::Inbound),
+            Some("degree-out") => graph.graph().degree_centrality(Direction::Outbound),
+            _ => {
+                let damping = matches.value_of("damping").unwrap_or("0.85").parse()?;
+                let iterations = matches.value_of("iterations").unwrap_or("100").parse()?;
+                graph.graph().pagerank(damping, iterations)?
+            }
+        };
+        let top: usize = matches.value_of("top").unwrap_or("10").parse()?;
+
+        let keys = graph.keys();
+        let stdout = std::io::stdout();
+        let mut out = BufWriter::new(stdout.lock());
+        for (rank, (vertex_id, score)) in ranking(scores).into_iter().take(top).enumerate() {
+            match keys.key(vertex_id) {
+                Some(key) => writeln!(out, "{}\t{}\t{}", rank + 1, key, score)?,
+                None => writeln!(out, "{}\t{}\t{}", rank + 1, vertex_id.0, score)?,
+            }
+        }
+        out.flush()?;
+
+        return Ok(());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("components") {
+        println!("Running sub-command 'components' ");
+
+        let f = load_graph(base_dir, name);
 
         let mut rt = Runtime::new()?;
         let graph = rt.block_on(f)?;
-        let ser: DirectedGraphSer = (&graph).into();
-        let str = serde_json::to_string(&ser)?;
+        let components = if matches.is_present("weak") {
+            graph.weakly_connected_components()
+        } else {
+            graph.strongly_connected_components()
+        };
+        let components: Vec<Vec<u64>> = components
+            .into_iter()
+            .map(|component| component.into_iter().map(|VertexId(id)| id).collect())
+            .collect();
+        let str = serde_json::to_string(&components)?;
         println!("{}", str);
 
         return Ok(());
     }
 
-    if let Some(_) = matches.subcommand_matches("init") {
+    if let Some(matches) = matches.subcommand_matches("init") {
         println!("Running sub-command 'init' ");
 
-        let graph = DirectedGraph::new();
-
-        let f = save_graph_as(base_dir, &name, &graph);
+        let mode = if matches.is_present("simple") { GraphMode::Simple } else { GraphMode::Multi };
+        let mut graph = HistorizedGraph::with_mode(mode);
+        if let Some(interval) = matches.value_of("checkpoint-interval") {
+            let interval: usize = std::str::FromStr::from_str(interval)?;
+            graph.set_checkpoint_policy(if interval == 0 {
+                CheckpointPolicy::OnDemand
+            } else {
+                CheckpointPolicy::Every(interval)
+            });
+        }
 
         let mut rt = Runtime::new()?;
-        rt.block_on(f)?;
+        save_historized_graph(&mut rt, base_dir, name, &graph)?;
 
         return Ok(());
     }
@@ -72,18 +593,13 @@ fn main() -> Result<()> {
         if let Some(vertex_id) = matches.value_of("vertexId") {
             println!("Adding vertex '{}'", vertex_id);
 
-            let vertex_id: u64 = std::str::FromStr::from_str(vertex_id)?;
-            let vertex_id = VertexId(vertex_id);
-
-            let f = load_graph(base_dir.clone(), &name)
-                .and_then(move |mut graph| {
-                    graph.add_vertex(vertex_id);
-                    Ok(graph)
-                })
-                .and_then({ let name = name.clone(); move |graph| save_graph_as(base_dir, &name, &graph)});
-
             let mut rt = Runtime::new()?;
-            rt.block_on(f)?;
+            let mut graph = load_historized_graph(&mut rt, base_dir.clone(), name)?;
+            let vertex = intern_vertex(&mut graph, vertex_id);
+            if !graph.try_execute(&[GraphCommand::AddVertex(vertex)])? {
+                println!("Vertex '{}' already exists", vertex_id);
+            }
+            save_historized_graph(&mut rt, base_dir, name, &graph)?;
         }
 
         return Ok(());
@@ -94,20 +610,225 @@ fn main() -> Result<()> {
         if let (Some(vertex_id_from), Some(vertex_id_to)) = (matches.value_of("vertexId_from"), matches.value_of("vertexId_to")) {
             println!("Adding edge '{}' -> '{}'", vertex_id_from, vertex_id_to);
 
-            let vertex_id_from: u64 = std::str::FromStr::from_str(vertex_id_from)?;
-            let vertex_id_to: u64 = std::str::FromStr::from_str(vertex_id_to)?;
+            let mut rt = Runtime::new()?;
+            let mut graph = load_historized_graph(&mut rt, base_dir.clone(), name)?;
+            let (from, to) = (intern_vertex(&mut graph, vertex_id_from), intern_vertex(&mut graph, vertex_id_to));
+            if !graph.try_execute(&[GraphCommand::AddEdge(from, to)])? {
+                println!("Edge '{}' -> '{}' already exists in the simple graph", vertex_id_from, vertex_id_to);
+            }
+            save_historized_graph(&mut rt, base_dir, name, &graph)?;
+        }
+
+        return Ok(());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("log") {
+        println!("Running sub-command 'log' ");
 
-            let edge = Edge(VertexId(vertex_id_from),  VertexId(vertex_id_to));
+        let mut rt = Runtime::new()?;
+        let graph = load_historized_graph(&mut rt, base_dir, name)?;
+        let mut log = graph.repository().log(matches.value_of("range").unwrap_or("HEAD"))?;
+        if let Some(author) = matches.value_of("author") {
+            log = log.author(author);
+        }
+        if let Some(comment) = matches.value_of("grep") {
+            log = log.comment(comment);
+        }
+        if let Some(seconds) = matches.value_of("since") {
+            log = log.since(Timestamp(std::str::FromStr::from_str(seconds)?));
+        }
+        if let Some(seconds) = matches.value_of("until") {
+            log = log.until(Timestamp(std::str::FromStr::from_str(seconds)?));
+        }
+        if let Some(max_count) = matches.value_of("max-count") {
+            log = log.max_count(std::str::FromStr::from_str(max_count)?);
+        }
+        let show_commands = matches.is_present("commands");
+        for commit in log {
+            print_commit(commit?, |_| show_commands);
+        }
 
-            let f = load_graph(base_dir.clone(), &name)
-                .and_then(move |mut graph| {
-                    graph.add_edge(edge);
-                    Ok(graph)
-                })
-                .and_then({ let name = name.clone(); move |graph| save_graph_as(base_dir, &name, &graph)});
+        return Ok(());
+    }
 
+    if let Some(matches) = matches.subcommand_matches("blame-vertex") {
+        println!("Running sub-command 'blame-vertex' ");
+        if let Some(vertex_id) = matches.value_of("vertexId") {
             let mut rt = Runtime::new()?;
-            rt.block_on(f)?;
+            let graph = load_historized_graph(&mut rt, base_dir, name)?;
+            let vertex_id = find_vertex(&graph, vertex_id)?;
+            for commit in graph.vertex_history(vertex_id) {
+                print_commit(commit, |command| command.touches_vertex(vertex_id));
+            }
+        }
+
+        return Ok(());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("blame-edge") {
+        println!("Running sub-command 'blame-edge' ");
+        if let (Some(vertex_id_from), Some(vertex_id_to)) = (matches.value_of("vertexId_from"), matches.value_of("vertexId_to")) {
+            let mut rt = Runtime::new()?;
+            let graph = load_historized_graph(&mut rt, base_dir, name)?;
+            let edge = Edge(find_vertex(&graph, vertex_id_from)?, find_vertex(&graph, vertex_id_to)?);
+            for commit in graph.edge_history(edge) {
+                print_commit(commit, |command| command.touches_edge(edge));
+            }
+        }
+
+        return Ok(());
+    }
+
+    if matches.subcommand_matches("refs").is_some() {
+        println!("Running sub-command 'refs' ");
+
+        let mut rt = Runtime::new()?;
+        let graph = load_historized_graph(&mut rt, base_dir, name)?;
+        let current = graph.repository().current();
+        for r in graph.repository().list_refs() {
+            let kind = if let Ref::Tag(_, _) = r { "tag" } else { "branch" };
+            let marker = if r == current { "*" } else { " " };
+            let head = r.hashs().head_option().map(|hash| hash.to_string()).unwrap_or_default();
+            println!("{} {} {} {}", marker, kind, r.name(), head);
+        }
+
+        return Ok(());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("branch") {
+        println!("Running sub-command 'branch' ");
+        if let Some(branch) = matches.value_of("name") {
+            let mut rt = Runtime::new()?;
+            let mut graph = load_historized_graph(&mut rt, base_dir.clone(), name)?;
+            if matches.is_present("delete") {
+                graph.delete_branch(branch)?;
+            } else if let Some(new_name) = matches.value_of("rename") {
+                graph.rename_branch(branch, new_name)?;
+            } else {
+                graph.branch(branch)?;
+            }
+            save_historized_graph(&mut rt, base_dir, name, &graph)?;
+        }
+
+        return Ok(());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("tag") {
+        println!("Running sub-command 'tag' ");
+        if let Some(tag) = matches.value_of("name") {
+            let mut rt = Runtime::new()?;
+            let mut graph = load_historized_graph(&mut rt, base_dir.clone(), name)?;
+            if matches.is_present("delete") {
+                graph.delete_tag(tag)?;
+            } else {
+                graph.tag(tag)?;
+            }
+            save_historized_graph(&mut rt, base_dir, name, &graph)?;
+        }
+
+        return Ok(());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("reflog") {
+        println!("Running sub-command 'reflog' ");
+
+        let reference = matches.value_of("ref").unwrap_or("HEAD");
+        let mut rt = Runtime::new()?;
+        let graph = load_historized_graph(&mut rt, base_dir, name)?;
+        for (n, entry) in graph.repository().reflog(reference).iter().enumerate() {
+            let hash = entry.new_hash().map(|hash| hash.to_string()).unwrap_or_else(|| "-".repeat(16));
+            println!("{} {}@{{{}}} {} {}", hash, reference, n, entry.timestamp().0, entry.operation());
+        }
+
+        return Ok(());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("checkout") {
+        println!("Running sub-command 'checkout' ");
+        if let Some(rev) = matches.value_of("rev") {
+            let mut rt = Runtime::new()?;
+            let mut graph = load_historized_graph_for(&mut rt, base_dir.clone(), name, |graph| graph.repository().resolve(rev))?;
+            graph.checkout(rev)?;
+            println!("Checked out '{}', replayed {} commits", rev, graph.replay_metrics().last_replay_length());
+            save_historized_graph(&mut rt, base_dir, name, &graph)?;
+        }
+
+        return Ok(());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("cherry-pick") {
+        println!("Running sub-command 'cherry-pick' ");
+        if let Some(rev) = matches.value_of("rev") {
+            let mut rt = Runtime::new()?;
+            let mut graph = load_historized_graph(&mut rt, base_dir.clone(), name)?;
+            graph.cherry_pick(rev)?;
+            save_historized_graph(&mut rt, base_dir, name, &graph)?;
+        }
+
+        return Ok(());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("revert") {
+        println!("Running sub-command 'revert' ");
+        if let Some(rev) = matches.value_of("rev") {
+            let mut rt = Runtime::new()?;
+            let mut graph = load_historized_graph(&mut rt, base_dir.clone(), name)?;
+            graph.revert(rev)?;
+            save_historized_graph(&mut rt, base_dir, name, &graph)?;
+        }
+
+        return Ok(());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("squash") {
+        println!("Running sub-command 'squash' ");
+        if let (Some(from), Some(to)) = (matches.value_of("from"), matches.value_of("to")) {
+            let mut rt = Runtime::new()?;
+            let mut graph = load_historized_graph(&mut rt, base_dir.clone(), name)?;
+            graph.squash(from, to)?;
+            save_historized_graph(&mut rt, base_dir, name, &graph)?;
+        }
+
+        return Ok(());
+    }
+
+    if matches.subcommand_matches("checkpoint").is_some() {
+        println!("Running sub-command 'checkpoint' ");
+
+        let mut rt = Runtime::new()?;
+        let graph = load_historized_graph(&mut rt, base_dir.clone(), name)?;
+        if graph.checkpoint() {
+            save_historized_graph(&mut rt, base_dir, name, &graph)?;
+        } else {
+            println!("Nothing to checkpoint");
+        }
+
+        return Ok(());
+    }
+
+    if matches.subcommand_matches("undo").is_some() {
+        println!("Running sub-command 'undo' ");
+
+        let mut rt = Runtime::new()?;
+        let mut graph = load_historized_graph(&mut rt, base_dir.clone(), name)?;
+        if graph.undo() {
+            save_historized_graph(&mut rt, base_dir, name, &graph)?;
+        } else {
+            println!("Nothing to undo");
+        }
+
+        return Ok(());
+    }
+
+    if matches.subcommand_matches("redo").is_some() {
+        println!("Running sub-command 'redo' ");
+
+        let mut rt = Runtime::new()?;
+        let mut graph = load_historized_graph(&mut rt, base_dir.clone(), name)?;
+        if graph.redo() {
+            save_historized_graph(&mut rt, base_dir, name, &graph)?;
+        } else {
+            println!("Nothing to redo");
         }
 
         return Ok(());
@@ -116,3 +837,169 @@ fn main() -> Result<()> {
     Ok(())
 
 }
+
+/// Returns the vertex and edge counts of a graph, and the distributions of the in-degrees and the
+/// out-degrees of its vertices, as `[degree, number of vertices]` pairs sorted by degree.
+fn graph_stats(graph: &DirectedGraph) -> serde_json::Value {
+    let distribution = |direction: Direction| {
+        graph.degree_distribution(direction).into_iter().collect::<Vec<(usize, usize)>>()
+    };
+    serde_json::json!({
+        "vertices": graph.vertex_count(),
+        "edges": graph.edge_count(),
+        "degree_in": distribution(Direction::Inbound),
+        "degree_out": distribution(Direction::Outbound),
+    })
+}
+
+/// Reads the graph of a file to import, in the given format.
+fn read_import(graph: &mut HistorizedGraph, input: BufReader<File>, format: &str, matches: &ArgMatches) -> Result<DirectedGraph> {
+    Ok(match format {
+        "graphml" => {
+            let graph_ml = read_graphml(input, graph.mode())?;
+            if !graph_ml.vertex_attributes.is_empty() || !graph_ml.edge_attributes.is_empty() {
+                println!("The attributes of the vertices and edges are not stored");
+            }
+            intern_graphml_keys(graph, &graph_ml)
+        }
+        "csv" | "tsv" => {
+            let mode = graph.mode();
+            read_delimited_with(input, &delimited_format(format, matches)?, mode, |vertex| {
+                Some(intern_vertex(graph, vertex))
+            })?
+        }
+        _ => serde_json::from_reader(input)?,
+    })
+}
+
+/// Returns the vertex id of a command-line argument: a number is a vertex id, any other text is
+/// the key of a vertex, which is bound to a fresh vertex id if it has none yet.
+fn intern_vertex(graph: &mut HistorizedGraph, vertex: &str) -> VertexId {
+    match vertex.parse() {
+        Ok(id) => {
+            // a key must not get the id of a vertex that is about to be added
+            graph.reserve_vertex_id(VertexId(id));
+            VertexId(id)
+        }
+        Err(_) => graph.intern(vertex),
+    }
+}
+
+/// Returns the vertex id of a command-line argument: a vertex id, or the key of a vertex.
+fn find_vertex(graph: &HistorizedGraph, vertex: &str) -> Result<VertexId> {
+    match vertex.parse() {
+        Ok(id) => Ok(VertexId(id)),
+        Err(_) => graph.keys()
+            .vertex_id(vertex)
+            .ok_or_else(|| Error::Usage(format!("Unknown vertex key '{}'", vertex))),
+    }
+}
+
+/// Returns the graph of a GraphML document, with the vertices that haven't kept their node id in
+/// the document designated by this id as their key.
+fn intern_graphml_keys(graph: &mut HistorizedGraph, graph_ml: &GraphMl) -> DirectedGraph {
+    let keys = &graph_ml.original_ids;
+    for &vertex_id in graph_ml.graph.vertices().filter(|vertex_id| !keys.contains_key(vertex_id)) {
+        graph.reserve_vertex_id(vertex_id);
+    }
+    let ids: HashMap<VertexId, VertexId> = keys
+        .iter()
+        .map(|(&vertex_id, key)| (vertex_id, graph.intern(key)))
+        .collect();
+    let id = |vertex_id: VertexId| ids.get(&vertex_id).copied().unwrap_or(vertex_id);
+
+    let mut imported = DirectedGraph::with_mode(graph_ml.graph.mode());
+    for &vertex_id in graph_ml.graph.vertices() {
+        imported.add_vertex(id(vertex_id));
+    }
+    for &Edge(from, to) in graph_ml.graph.edges() {
+        imported.add_edge(Edge(id(from), id(to)));
+    }
+    imported
+}
+
+/// The arguments of the csv and tsv formats of `export` and `import`.
+fn delimited_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("adjacency")
+            .long("adjacency")
+            .help("csv, tsv: one vertex per line followed by its successors, instead of one edge per line"),
+        Arg::with_name("header")
+            .long("header")
+            .help("csv, tsv: the first line is a header"),
+        Arg::with_name("delimiter")
+            .long("delimiter")
+            .takes_value(true)
+            .help("csv, tsv: the delimiter of the fields (default: ',' for csv, tab for tsv)"),
+        Arg::with_name("id-column")
+            .long("id-column")
+            .takes_value(true)
+            .default_value("0")
+            .help("csv, tsv: the column of the source vertex of an edge, or of the vertex of an adjacency list"),
+        Arg::with_name("target-column")
+            .long("target-column")
+            .takes_value(true)
+            .default_value("1")
+            .help("csv, tsv: the column of the target vertex of an edge"),
+    ]
+}
+
+fn delimited_format(format: &str, matches: &ArgMatches) -> Result<Delimited> {
+    let mut delimited = if format == "tsv" { Delimited::tsv() } else { Delimited::csv() };
+    if matches.is_present("adjacency") {
+        delimited.layout = Layout::AdjacencyList;
+    }
+    delimited.header = matches.is_present("header");
+    if let Some(delimiter) = matches.value_of("delimiter") {
+        let mut chars = delimiter.chars();
+        delimited.delimiter = match (chars.next(), chars.next()) {
+            (Some(c), None) => c,
+            _ => return Err(Error::Usage(format!("The delimiter must be a single character: '{}'", delimiter))),
+        };
+    }
+    if let Some(id_column) = matches.value_of("id-column") {
+        delimited.id_column = id_column.parse()?;
+    }
+    if let Some(target_column) = matches.value_of("target-column") {
+        delimited.target_column = target_column.parse()?;
+    }
+    Ok(delimited)
+}
+
+/// Prints the hash, the author, the time and the comment of a commit, followed by those of its
+/// commands that satisfy `filter`.
+fn print_commit<F>(commit: &Commit<Commands>, filter: F)
+    where F: Fn(&GraphCommand) -> bool {
+    println!("{} {} {} {}", commit.hash(), commit.author().0, commit.timestamp().0, commit.comment().0);
+    for command in commit.item().iter().filter(|&command| filter(command)) {
+        println!("    {}", command);
+    }
+}
+
+/// Loads the history saved under `name`, and rebuilds the graph from it.
+fn load_historized_graph(rt: &mut Runtime, base_dir: PathBuf, name: &OsStr) -> Result<HistorizedGraph> {
+    let history = rt.block_on(load_history(base_dir, name))?;
+    Ok(history.into_historized_graph()?)
+}
+
+/// Loads the history saved under `name`, and rebuilds the graph from it, along with the stored
+/// checkpoint nearest to the commits selected by `select`.
+fn load_historized_graph_for<F>(rt: &mut Runtime, base_dir: PathBuf, name: &OsStr, select: F) -> Result<HistorizedGraph>
+    where F: FnOnce(&HistorizedGraph) -> std::result::Result<Hashs, String> {
+    let history = rt.block_on(load_history(base_dir.clone(), name))?;
+    let checkpoints = history.stored_checkpoints();
+    let graph = history.into_historized_graph()?;
+    let hashs = select(&graph)?;
+    if let Some((hash, checkpoint)) = rt.block_on(load_checkpoint(base_dir, &checkpoints, &hashs))? {
+        graph.add_checkpoint(hash, checkpoint);
+    }
+    Ok(graph)
+}
+
+/// Saves the history of the graph under `name`, along with the current state of the graph, which
+/// is read by the sub-commands that don't need the history.
+fn save_historized_graph(rt: &mut Runtime, base_dir: PathBuf, name: &OsStr, graph: &HistorizedGraph) -> Result<()> {
+    rt.block_on(save_history_as(base_dir.clone(), name, graph.into()))?;
+    rt.block_on(save_graph_as(base_dir, name, graph.graph()))?;
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use serde::{Serialize, Deserialize};
+use histo_graph_core::graph::command::GraphCommand;
+use histo_graph_core::graph::graph::VertexId;
+
+/// Serialized form of a `GraphCommand`, tagged with its operation, e.g.
+/// `{"op":"add_edge","from":1,"to":2}` or `{"op":"remove_vertex","id":1}`.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum GraphCommandSer {
+    AddVertex { id: u64 },
+    RemoveVertex { id: u64 },
+    AddEdge { from: u64, to: u64 },
+    RemoveEdge { from: u64, to: u64 },
+}
+
+impl From<&GraphCommand> for GraphCommandSer {
+    fn from(command: &GraphCommand) -> GraphCommandSer {
+        match *command {
+            GraphCommand::AddVertex(VertexId(id)) => GraphCommandSer::AddVertex { id },
+            GraphCommand::RemoveVertex(VertexId(id)) => GraphCommandSer::RemoveVertex { id },
+            GraphCommand::AddEdge(VertexId(from), VertexId(to)) => GraphCommandSer::AddEdge { from, to },
+            GraphCommand::RemoveEdge(VertexId(from), VertexId(to)) => GraphCommandSer::RemoveEdge { from, to },
+        }
+    }
+}
+
+impl From<&GraphCommandSer> for GraphCommand {
+    fn from(command: &GraphCommandSer) -> GraphCommand {
+        match *command {
+            GraphCommandSer::AddVertex { id } => GraphCommand::AddVertex(VertexId(id)),
+            GraphCommandSer::RemoveVertex { id } => GraphCommand::RemoveVertex(VertexId(id)),
+            GraphCommandSer::AddEdge { from, to } => GraphCommand::AddEdge(VertexId(from), VertexId(to)),
+            GraphCommandSer::RemoveEdge { from, to } => GraphCommand::RemoveEdge(VertexId(from), VertexId(to)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use histo_graph_core::graph::command::GraphCommand::{self, *};
+    use histo_graph_core::graph::graph::VertexId;
+    use crate::command_serde::GraphCommandSer;
+
+    #[test]
+    fn test_tagged_commands() -> Result<(), serde_json::error::Error> {
+        let commands = vec![
+            AddVertex(VertexId(1)),
+            RemoveVertex(VertexId(2)),
+            AddEdge(VertexId(1), VertexId(2)),
+            RemoveEdge(VertexId(3), VertexId(4)),
+        ];
+        let commands_ser: Vec<GraphCommandSer> = commands.iter().map(Into::into).collect();
+
+        let str = serde_json::to_string(&commands_ser)?;
+        assert_eq!(str, r#"[{"op":"add_vertex","id":1},{"op":"remove_vertex","id":2},{"op":"add_edge","from":1,"to":2},{"op":"remove_edge","from":3,"to":4}]"#);
+
+        let commands_ser: Vec<GraphCommandSer> = serde_json::from_str(&str)?;
+        let ser_de_commands: Vec<GraphCommand> = commands_ser.iter().map(Into::into).collect();
+        assert_eq!(commands, ser_de_commands);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_op() {
+        assert!(serde_json::from_str::<GraphCommandSer>(r#"{"op":"move_vertex","id":1}"#).is_err());
+        assert!(serde_json::from_str::<GraphCommandSer>(r#"{"op":"add_edge","from":1}"#).is_err());
+    }
+}
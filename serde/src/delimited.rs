@@ -0,0 +1,440 @@
+//! This module implements the reading and writing of graphs as delimited text, e.g. CSV or TSV,
+//! in two layouts:
+//!
+//! * an edge list has one edge per line, `from,to[,attributes...]`. A line without target is a
+//!   vertex without edges.
+//! * an adjacency list has one vertex per line, followed by its successors,
+//!   `vertex[,successor...]`. A successor is repeated for parallel edges.
+//!
+//! The vertex ids are numbers, unless the reader is given a resolver, e.g. to map string keys to
+//! vertex ids (see `HistorizedGraph::intern`). Fields may be quoted with `"`, a quote inside a
+//! quoted field being written `""`. Empty lines and lines starting with `#` are skipped, and the
+//! columns that are not used, e.g. attributes, are ignored.
+//!
+//! The text is read one line at a time, so that large files can be read without holding their
+//! content in memory.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use histo_graph_core::graph::command::GraphCommand;
+use histo_graph_core::graph::directed_graph::{DirectedGraph, GraphMode};
+use histo_graph_core::graph::graph::{Edge, VertexId};
+
+/// The layout of the lines of a delimited text.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Layout {
+    EdgeList,
+    AdjacencyList,
+}
+
+/// The format of a delimited text.
+///
+/// # Examples
+///
+/// ```
+/// use histo_graph_serde::delimited::{Delimited, Layout};
+///
+/// // a TSV adjacency list with a header line
+/// let format = Delimited { layout: Layout::AdjacencyList, header: true, ..Delimited::tsv() };
+/// assert_eq!(format.delimiter, '\t');
+/// ```
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Delimited {
+    pub layout: Layout,
+    pub delimiter: char,
+    /// Whether the first line is a header, which is skipped on reading.
+    pub header: bool,
+    /// The column of the source vertex of an edge list, or of the vertex of an adjacency list.
+    pub id_column: usize,
+    /// The column of the target vertex of an edge list.
+    pub target_column: usize,
+}
+
+impl Delimited {
+    /// A comma separated edge list without header, `from,to`.
+    pub fn csv() -> Delimited {
+        Delimited {
+            layout: Layout::EdgeList,
+            delimiter: ',',
+            header: false,
+            id_column: 0,
+            target_column: 1,
+        }
+    }
+
+    /// A tab separated edge list without header, `from\tto`.
+    pub fn tsv() -> Delimited {
+        Delimited { delimiter: '\t', ..Delimited::csv() }
+    }
+}
+
+#[derive(Debug)]
+pub enum DelimitedError {
+    Io(io::Error),
+    Invalid { line: usize, message: String },
+}
+
+impl fmt::Display for DelimitedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DelimitedError::Io(e) => write!(f, "io error: {}", e),
+            DelimitedError::Invalid { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl From<io::Error> for DelimitedError {
+    fn from(e: io::Error) -> DelimitedError {
+        DelimitedError::Io(e)
+    }
+}
+
+/// Splits a line into its fields, removing the quotes.
+fn split_fields(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            if c != '"' {
+                field.push(c);
+            } else if chars.peek() == Some(&'"') {
+                chars.next();
+                field.push('"');
+            } else {
+                quoted = false;
+            }
+        } else if c == '"' {
+            quoted = true;
+        } else if c == delimiter {
+            fields.push(std::mem::take(&mut field));
+        } else {
+            field.push(c);
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Reads the commands that build a graph from a delimited text: an `AddVertex` for each vertex
+/// without edges of an edge list and for each vertex of an adjacency list, and an `AddEdge` for
+/// each edge, in the order of the lines.
+///
+/// # Examples
+///
+/// ```
+/// use histo_graph_core::graph::command::GraphCommand;
+/// use histo_graph_core::graph::graph::VertexId;
+/// use histo_graph_serde::delimited::{Delimited, DelimitedReader};
+///
+/// let input = "from,to,weight\n1,2,0.5\n3\n".as_bytes();
+/// let commands: Vec<GraphCommand> = DelimitedReader::new(input, Delimited { header: true, ..Delimited::csv() })
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(commands, vec![GraphCommand::AddEdge(VertexId(1), VertexId(2)), GraphCommand::AddVertex(VertexId(3))]);
+/// ```
+pub struct DelimitedReader<R, F = fn(&str) -> Option<VertexId>> {
+    input: R,
+    format: Delimited,
+    resolve: F,
+    line: String,
+    line_number: usize,
+    pending: VecDeque<GraphCommand>,
+}
+
+fn numeric_id(field: &str) -> Option<VertexId> {
+    field.parse().ok().map(VertexId)
+}
+
+impl<R: BufRead> DelimitedReader<R> {
+    /// Creates a reader of numeric vertex ids.
+    pub fn new(input: R, format: Delimited) -> DelimitedReader<R> {
+        DelimitedReader::with_resolver(input, format, numeric_id)
+    }
+}
+
+impl<R: BufRead, F: FnMut(&str) -> Option<VertexId>> DelimitedReader<R, F> {
+    /// Creates a reader that gets the vertex ids of the fields, without surrounding whitespace,
+    /// from `resolve`. A field without vertex id is an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::command::GraphCommand;
+    /// use histo_graph_core::historized_graph::HistorizedGraph;
+    /// use histo_graph_serde::delimited::{Delimited, DelimitedReader};
+    ///
+    /// let mut graph = HistorizedGraph::new();
+    /// let input = "serde\ttokio\n".as_bytes();
+    /// let commands: Vec<GraphCommand> = DelimitedReader::with_resolver(input, Delimited::tsv(), |key| Some(graph.intern(key)))
+    ///     .collect::<Result<_, _>>()
+    ///     .unwrap();
+    /// assert!(graph.execute(&commands));
+    /// assert_eq!(graph.keys().len(), 2);
+    /// ```
+    pub fn with_resolver(input: R, format: Delimited, resolve: F) -> DelimitedReader<R, F> {
+        DelimitedReader {
+            input,
+            format,
+            resolve,
+            line: String::new(),
+            line_number: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn vertex_id(&mut self, field: &str) -> Result<VertexId, DelimitedError> {
+        let line = self.line_number;
+        (self.resolve)(field.trim())
+            .ok_or_else(|| DelimitedError::Invalid {
+                line,
+                message: format!("invalid vertex id '{}'", field),
+            })
+    }
+
+    /// Reads the next line, and queues its commands. Returns `false` at the end of the input.
+    fn read_line(&mut self) -> Result<bool, DelimitedError> {
+        self.line.clear();
+        if self.input.read_line(&mut self.line)? == 0 {
+            return Ok(false);
+        }
+        self.line_number += 1;
+
+        let line = self.line.trim_end_matches(['\n', '\r']);
+        if (self.format.header && self.line_number == 1) || line.trim().is_empty() || line.starts_with('#') {
+            return Ok(true);
+        }
+
+        let fields = split_fields(line, self.format.delimiter);
+        let id_field = fields.get(self.format.id_column)
+            .filter(|field| !field.trim().is_empty())
+            .ok_or_else(|| DelimitedError::Invalid {
+                line: self.line_number,
+                message: format!("missing vertex id in column {}", self.format.id_column),
+            })?;
+        let vertex_id = self.vertex_id(id_field)?;
+
+        match self.format.layout {
+            Layout::EdgeList => {
+                match fields.get(self.format.target_column).filter(|field| !field.trim().is_empty()) {
+                    Some(field) => {
+                        let target = self.vertex_id(field)?;
+                        self.pending.push_back(GraphCommand::AddEdge(vertex_id, target));
+                    }
+                    None => self.pending.push_back(GraphCommand::AddVertex(vertex_id)),
+                }
+            }
+            Layout::AdjacencyList => {
+                self.pending.push_back(GraphCommand::AddVertex(vertex_id));
+                for (column, field) in fields.iter().enumerate() {
+                    if column != self.format.id_column && !field.trim().is_empty() {
+                        let successor = self.vertex_id(field)?;
+                        self.pending.push_back(GraphCommand::AddEdge(vertex_id, successor));
+                    }
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl<R: BufRead, F: FnMut(&str) -> Option<VertexId>> Iterator for DelimitedReader<R, F> {
+    type Item = Result<GraphCommand, DelimitedError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(command) = self.pending.pop_front() {
+                return Some(Ok(command));
+            }
+            match self.read_line() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Reads a graph with the given mode from a delimited text.
+///
+/// # Examples
+///
+/// ```
+/// use histo_graph_core::graph::directed_graph::GraphMode;
+/// use histo_graph_core::graph::graph::{VertexId, Edge};
+/// use histo_graph_serde::delimited::{read_delimited, Delimited, Layout};
+///
+/// let input = "1\t2\t2\n3\n".as_bytes();
+/// let format = Delimited { layout: Layout::AdjacencyList, ..Delimited::tsv() };
+/// let graph = read_delimited(input, &format, GraphMode::Multi).unwrap();
+/// assert_eq!(graph.vertex_count(), 3);
+/// assert_eq!(graph.edge_multiplicity(Edge(VertexId(1), VertexId(2))), 2);
+/// ```
+pub fn read_delimited<R: BufRead>(input: R, format: &Delimited, mode: GraphMode) -> Result<DirectedGraph, DelimitedError> {
+    read_delimited_with(input, format, mode, numeric_id)
+}
+
+/// Reads a graph with the given mode from a delimited text, getting the vertex ids of the fields
+/// from `resolve`. See `DelimitedReader::with_resolver`.
+pub fn read_delimited_with<R, F>(input: R, format: &Delimited, mode: GraphMode, resolve: F) -> Result<DirectedGraph, DelimitedError>
+where
+    R: BufRead,
+    F: FnMut(&str) -> Option<VertexId>,
+{
+    let mut graph = DirectedGraph::with_mode(mode);
+    for command in DelimitedReader::with_resolver(input, *format, resolve) {
+        command?.apply_to(&mut graph);
+    }
+    Ok(graph)
+}
+
+/// Puts `value` in the column `column`, adding empty columns before it if needed.
+fn put(fields: &mut Vec<String>, column: usize, value: String) {
+    if fields.len() <= column {
+        fields.resize(column + 1, String::new());
+    }
+    fields[column] = value;
+}
+
+fn write_fields<W: Write>(out: &mut W, fields: &[String], delimiter: char) -> io::Result<()> {
+    let mut delimiter_buf = [0; 4];
+    let delimiter = delimiter.encode_utf8(&mut delimiter_buf);
+    writeln!(out, "{}", fields.join(delimiter))
+}
+
+/// Writes the graph as a delimited text. The vertices are sorted, and the edges are sorted by
+/// source and target.
+///
+/// # Examples
+///
+/// ```
+/// use histo_graph_core::graph::directed_graph::DirectedGraph;
+/// use histo_graph_core::graph::graph::{VertexId, Edge};
+/// use histo_graph_serde::delimited::{write_delimited, Delimited};
+///
+/// let mut graph = DirectedGraph::new();
+/// graph.add_edge(Edge(VertexId(2), VertexId(1)));
+/// graph.add_vertex(VertexId(3));
+///
+/// let mut out: Vec<u8> = Vec::new();
+/// write_delimited(&graph, &Delimited { header: true, ..Delimited::csv() }, &mut out).unwrap();
+/// assert_eq!(String::from_utf8(out).unwrap(), "from,to\n2,1\n3\n");
+/// ```
+pub fn write_delimited<W: Write>(graph: &DirectedGraph, format: &Delimited, out: &mut W) -> io::Result<()> {
+    let mut vertices: Vec<VertexId> = graph.vertices().copied().collect();
+    vertices.sort();
+
+    match format.layout {
+        Layout::EdgeList => {
+            if format.header {
+                let mut fields = Vec::new();
+                put(&mut fields, format.id_column, "from".to_string());
+                put(&mut fields, format.target_column, "to".to_string());
+                write_fields(out, &fields, format.delimiter)?;
+            }
+            for vertex_id in vertices {
+                let mut fields = Vec::new();
+                put(&mut fields, format.id_column, vertex_id.0.to_string());
+                if graph.degree_out(vertex_id) == 0 {
+                    // vertices with outbound edges are written with their edges
+                    if graph.degree_in(vertex_id) == 0 {
+                        write_fields(out, &fields, format.delimiter)?;
+                    }
+                    continue;
+                }
+                for &Edge(_, VertexId(to)) in graph.outbound_edges(vertex_id) {
+                    put(&mut fields, format.target_column, to.to_string());
+                    write_fields(out, &fields, format.delimiter)?;
+                }
+            }
+        }
+        Layout::AdjacencyList => {
+            if format.header {
+                let mut fields = vec!["successors".to_string(); format.id_column];
+                fields.insert(format.id_column, "vertex".to_string());
+                fields.push("successors".to_string());
+                write_fields(out, &fields, format.delimiter)?;
+            }
+            for vertex_id in vertices {
+                let mut fields: Vec<String> = graph.outbound_edges(vertex_id)
+                    .map(|&Edge(_, VertexId(to))| to.to_string())
+                    .collect();
+                if fields.len() < format.id_column {
+                    fields.resize(format.id_column, String::new());
+                }
+                fields.insert(format.id_column, vertex_id.0.to_string());
+                write_fields(out, &fields, format.delimiter)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use histo_graph_core::graph::directed_graph::{DirectedGraph, GraphMode};
+    use histo_graph_core::graph::graph::{Edge, VertexId};
+    use crate::delimited::{read_delimited, split_fields, write_delimited, Delimited, DelimitedError, Layout};
+
+    fn graph() -> DirectedGraph {
+        let mut graph = DirectedGraph::new();
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+        graph.add_edge(Edge(VertexId(2), VertexId(3)));
+        graph.add_edge(Edge(VertexId(4), VertexId(4)));
+        graph.add_vertex(VertexId(5));
+        graph
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), DelimitedError> {
+        let graph = graph();
+        for &layout in [Layout::EdgeList, Layout::AdjacencyList].iter() {
+            for &(header, id_column, target_column) in [(false, 0, 1), (true, 2, 0)].iter() {
+                let format = Delimited { layout, header, id_column, target_column, ..Delimited::tsv() };
+                let mut out: Vec<u8> = Vec::new();
+                write_delimited(&graph, &format, &mut out)?;
+
+                assert_eq!(read_delimited(out.as_slice(), &format, GraphMode::Multi)?, graph);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_format() -> Result<(), DelimitedError> {
+        let graph = graph();
+        let mut out: Vec<u8> = Vec::new();
+        write_delimited(&graph, &Delimited::csv(), &mut out)?;
+        assert_eq!(String::from_utf8(out).unwrap(), "1,2\n1,2\n2,3\n4,4\n5\n");
+
+        let mut out: Vec<u8> = Vec::new();
+        let format = Delimited { layout: Layout::AdjacencyList, header: true, id_column: 1, ..Delimited::csv() };
+        write_delimited(&graph, &format, &mut out)?;
+        assert_eq!(String::from_utf8(out).unwrap(), "successors,vertex,successors\n2,1,2\n3,2\n,3\n4,4\n,5\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_read() -> Result<(), DelimitedError> {
+        let input = "# a comment\n\"1\",\" 2 \",\"a, \"\"quoted\"\" label\"\n\n2,1\r\n1,2,\n";
+        let graph = read_delimited(input.as_bytes(), &Delimited::csv(), GraphMode::Simple)?;
+        assert_eq!(graph.vertex_count(), 2);
+        assert_eq!(graph.edge_count(), 2);
+
+        assert_eq!(split_fields(r#""a, ""b""",c"#, ','), vec![r#"a, "b""#.to_string(), "c".to_string()]);
+
+        match read_delimited("1,2\n2,x\n".as_bytes(), &Delimited::csv(), GraphMode::Multi) {
+            Err(DelimitedError::Invalid { line: 2, .. }) => {}
+            other => panic!("expected an invalid line 2, got {:?}", other),
+        }
+        match read_delimited("1,2\n,3\n".as_bytes(), &Delimited::csv(), GraphMode::Multi) {
+            Err(DelimitedError::Invalid { line: 2, .. }) => {}
+            other => panic!("expected an invalid line 2, got {:?}", other),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use serde::{Serialize, Deserialize};
+use histo_graph_core::graph::diff::StructureDiff;
+use histo_graph_core::graph::graph::{VertexId, Edge};
+
+/// Serialized form of a `StructureDiff`, from the point of view of the first graph.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct StructureDiffSer {
+    extra_vertices: Vec<u64>,
+    missing_vertices: Vec<u64>,
+    extra_edges: Vec<(u64, u64)>,
+    missing_edges: Vec<(u64, u64)>,
+}
+
+fn vertices_ser(vertices: &[VertexId]) -> Vec<u64> {
+    vertices.iter().map(|&VertexId(id)| id).collect()
+}
+
+fn edges_ser(edges: &[Edge]) -> Vec<(u64, u64)> {
+    edges.iter().map(|&Edge(VertexId(id_1), VertexId(id_2))| (id_1, id_2)).collect()
+}
+
+fn vertices_de(vertices: &[u64]) -> Vec<VertexId> {
+    vertices.iter().map(|&id| VertexId(id)).collect()
+}
+
+fn edges_de(edges: &[(u64, u64)]) -> Vec<Edge> {
+    edges.iter().map(|&(id_1, id_2)| Edge(VertexId(id_1), VertexId(id_2))).collect()
+}
+
+impl From<&StructureDiff> for StructureDiffSer {
+    fn from(diff: &StructureDiff) -> StructureDiffSer {
+        StructureDiffSer {
+            extra_vertices: vertices_ser(diff.extra_vertices()),
+            missing_vertices: vertices_ser(diff.missing_vertices()),
+            extra_edges: edges_ser(diff.extra_edges()),
+            missing_edges: edges_ser(diff.missing_edges()),
+        }
+    }
+}
+
+impl From<&StructureDiffSer> for StructureDiff {
+    fn from(diff_ser: &StructureDiffSer) -> StructureDiff {
+        StructureDiff::new(
+            vertices_de(&diff_ser.extra_vertices),
+            vertices_de(&diff_ser.missing_vertices),
+            edges_de(&diff_ser.extra_edges),
+            edges_de(&diff_ser.missing_edges),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use histo_graph_core::graph::directed_graph::DirectedGraph;
+    use histo_graph_core::graph::diff::{diff, StructureDiff};
+    use histo_graph_core::graph::graph::{Edge, VertexId};
+    use crate::diff_serde::StructureDiffSer;
+
+    #[test]
+    fn test_diff() -> Result<(), serde_json::error::Error> {
+        let mut g1 = DirectedGraph::new();
+        g1.add_edge(Edge(VertexId(1), VertexId(2)));
+        let mut g2 = DirectedGraph::new();
+        g2.add_edge(Edge(VertexId(1), VertexId(3)));
+        let d = diff(&g1, &g2);
+
+        let str = serde_json::to_string(&StructureDiffSer::from(&d))?;
+        assert_eq!(str, r#"{"extra_vertices":[2],"missing_vertices":[3],"extra_edges":[[1,2]],"missing_edges":[[1,3]]}"#);
+
+        let diff_ser: StructureDiffSer = serde_json::from_str(&str)?;
+        assert_eq!(d, StructureDiff::from(&diff_ser));
+        Ok(())
+    }
+}
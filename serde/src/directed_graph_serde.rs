@@ -1,9 +1,44 @@
 use serde::{Serialize, Deserialize};
-use histo_graph_core::graph::directed_graph::DirectedGraph;
+use histo_graph_core::graph::directed_graph::{DirectedGraph, GraphMode};
 use histo_graph_core::graph::graph::{VertexId, Edge};
 
+/// Serialized form of a `GraphMode`. A missing mode is read as `multi`, the mode of graphs that
+/// have been serialized before the mode existed.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphModeSer {
+    Simple,
+    Multi,
+}
+
+impl Default for GraphModeSer {
+    fn default() -> GraphModeSer {
+        GraphModeSer::Multi
+    }
+}
+
+impl From<GraphMode> for GraphModeSer {
+    fn from(mode: GraphMode) -> GraphModeSer {
+        match mode {
+            GraphMode::Simple => GraphModeSer::Simple,
+            GraphMode::Multi => GraphModeSer::Multi,
+        }
+    }
+}
+
+impl From<GraphModeSer> for GraphMode {
+    fn from(mode: GraphModeSer) -> GraphMode {
+        match mode {
+            GraphModeSer::Simple => GraphMode::Simple,
+            GraphModeSer::Multi => GraphMode::Multi,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct DirectedGraphSer {
+    #[serde(default)]
+    mode: GraphModeSer,
     vertices: Vec<u64>,
     edges: Vec<(u64, u64)>,
 }
@@ -11,6 +46,7 @@ pub struct DirectedGraphSer {
 impl From<&DirectedGraph> for DirectedGraphSer {
     fn from(graph: &DirectedGraph) -> DirectedGraphSer {
         DirectedGraphSer {
+            mode: graph.mode().into(),
             vertices: graph
                 .vertices()
                 .map(|&VertexId(id)| id)
@@ -25,7 +61,7 @@ impl From<&DirectedGraph> for DirectedGraphSer {
 
 impl From<&DirectedGraphSer> for DirectedGraph {
     fn from(graph_ser: &DirectedGraphSer) -> DirectedGraph {
-        let mut graph = DirectedGraph::new();
+        let mut graph = DirectedGraph::with_mode(graph_ser.mode.into());
 
         for &v_id in &graph_ser.vertices {
             graph.add_vertex(VertexId(v_id));
@@ -41,7 +77,7 @@ impl From<&DirectedGraphSer> for DirectedGraph {
 
 #[cfg(test)]
 mod test {
-    use histo_graph_core::graph::directed_graph::DirectedGraph;
+    use histo_graph_core::graph::directed_graph::{DirectedGraph, GraphMode};
     use histo_graph_core::graph::graph::{Edge, VertexId};
     use crate::directed_graph_serde::DirectedGraphSer;
 
@@ -63,4 +99,43 @@ mod test {
         assert_eq!(graph, ser_de_graph);
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_simple_mode() -> Result<(), serde_json::error::Error> {
+        let mut graph = DirectedGraph::with_mode(GraphMode::Simple);
+        graph.add_edge(Edge(VertexId(0), VertexId(1)));
+
+        let str = serde_json::to_string(&DirectedGraphSer::from(&graph))?;
+        assert!(str.starts_with(r#"{"mode":"simple","#));
+
+        let graph_ser: DirectedGraphSer = serde_json::from_str(&str)?;
+        assert_eq!(graph, DirectedGraph::from(&graph_ser));
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_mode_is_multi() -> Result<(), serde_json::error::Error> {
+        let graph_ser: DirectedGraphSer = serde_json::from_str(r#"{"vertices":[0,1],"edges":[[0,1],[0,1]]}"#)?;
+        let graph = DirectedGraph::from(&graph_ser);
+
+        assert_eq!(graph.mode(), GraphMode::Multi);
+        assert_eq!(graph.edge_count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_core_serde_compatibility() -> Result<(), serde_json::error::Error> {
+        let mut graph = DirectedGraph::with_mode(GraphMode::Simple);
+        graph.add_edge(Edge(VertexId(0), VertexId(1)));
+        graph.add_edge(Edge(VertexId(1), VertexId(1)));
+
+        // the graph is serialized directly with the `serde` feature of the core
+        let str = serde_json::to_string(&DirectedGraphSer::from(&graph))?;
+        assert_eq!(str, serde_json::to_string(&graph)?);
+
+        let graph_ser: DirectedGraphSer = serde_json::from_str(&serde_json::to_string(&graph)?)?;
+        assert_eq!(graph, DirectedGraph::from(&graph_ser));
+        assert_eq!(graph, serde_json::from_str::<DirectedGraph>(&str)?);
+        Ok(())
+    }
+}
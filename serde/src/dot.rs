@@ -0,0 +1,180 @@
+//! This module implements the export of graphs to the [DOT] language of Graphviz.
+//!
+//! A vertex is written as its id, and an edge is written once for each time it is contained in
+//! the graph. The vertices and the edges are sorted, so that the output only depends on the
+//! content of the graph.
+//!
+//! [DOT]: https://graphviz.org/doc/info/lang.html
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use histo_graph_core::graph::diff::StructureDiff;
+use histo_graph_core::graph::directed_graph::DirectedGraph;
+use histo_graph_core::graph::graph::{Edge, VertexId};
+
+const ADDED: &str = " [color=green, fontcolor=green]";
+const REMOVED: &str = " [color=red, fontcolor=red, style=dashed]";
+
+/// Counts the edges of the graph, sorted.
+fn edge_counts(graph: &DirectedGraph) -> BTreeMap<Edge, usize> {
+    let mut counts: BTreeMap<Edge, usize> = BTreeMap::new();
+    for &edge in graph.edges() {
+        *counts.entry(edge).or_insert(0) += 1;
+    }
+    counts
+}
+
+fn write_vertex<W: Write>(out: &mut W, VertexId(id): VertexId, style: &str) -> io::Result<()> {
+    writeln!(out, "  {}{};", id, style)
+}
+
+fn write_edge<W: Write>(out: &mut W, Edge(VertexId(from), VertexId(to)): Edge, style: &str) -> io::Result<()> {
+    writeln!(out, "  {} -> {}{};", from, to, style)
+}
+
+/// Writes the graph in the DOT language.
+///
+/// # Examples
+///
+/// ```
+/// use histo_graph_core::graph::directed_graph::DirectedGraph;
+/// use histo_graph_core::graph::graph::{VertexId, Edge};
+/// use histo_graph_serde::dot::write_dot;
+///
+/// let mut graph = DirectedGraph::new();
+/// graph.add_edge(Edge(VertexId(2), VertexId(1)));
+///
+/// let mut out: Vec<u8> = Vec::new();
+/// write_dot(&graph, &mut out).unwrap();
+/// assert_eq!(String::from_utf8(out).unwrap(), "digraph G {\n  1;\n  2;\n  2 -> 1;\n}\n");
+/// ```
+pub fn write_dot<W: Write>(graph: &DirectedGraph, out: &mut W) -> io::Result<()> {
+    let mut vertices: Vec<VertexId> = graph.vertices().copied().collect();
+    vertices.sort();
+
+    writeln!(out, "digraph G {{")?;
+    for vertex_id in vertices {
+        write_vertex(out, vertex_id, "")?;
+    }
+    for (edge, count) in edge_counts(graph) {
+        for _ in 0..count {
+            write_edge(out, edge, "")?;
+        }
+    }
+    writeln!(out, "}}")?;
+    Ok(())
+}
+
+/// Writes the changes from the graph `base` in the DOT language: the elements of `base` along
+/// with the elements added by `diff`. The added vertices and edges are green, the removed ones
+/// are red and dashed, and the unchanged ones keep the default style.
+///
+/// `diff` is the diff from `base` to the changed graph, i.e. `diff(base, changed)`.
+///
+/// # Examples
+///
+/// ```
+/// use histo_graph_core::graph::directed_graph::DirectedGraph;
+/// use histo_graph_core::graph::graph::{VertexId, Edge};
+/// use histo_graph_core::graph::diff::diff;
+/// use histo_graph_serde::dot::write_dot_diff;
+///
+/// let mut g1 = DirectedGraph::new();
+/// g1.add_edge(Edge(VertexId(1), VertexId(2)));
+/// let mut g2 = DirectedGraph::new();
+/// g2.add_edge(Edge(VertexId(1), VertexId(3)));
+///
+/// let mut out: Vec<u8> = Vec::new();
+/// write_dot_diff(&g1, &diff(&g1, &g2), &mut out).unwrap();
+/// let dot = String::from_utf8(out).unwrap();
+/// assert!(dot.contains("  1 -> 2 [color=red, fontcolor=red, style=dashed];\n"));
+/// assert!(dot.contains("  1 -> 3 [color=green, fontcolor=green];\n"));
+/// ```
+pub fn write_dot_diff<W: Write>(base: &DirectedGraph, diff: &StructureDiff, out: &mut W) -> io::Result<()> {
+    let mut vertices: BTreeMap<VertexId, &str> = base.vertices().map(|&v| (v, "")).collect();
+    for &vertex_id in diff.extra_vertices() {
+        vertices.insert(vertex_id, REMOVED);
+    }
+    for &vertex_id in diff.missing_vertices() {
+        vertices.insert(vertex_id, ADDED);
+    }
+
+    // (unchanged, removed, added) occurrences of each edge
+    let mut edges: BTreeMap<Edge, (usize, usize, usize)> = edge_counts(base)
+        .into_iter()
+        .map(|(edge, count)| (edge, (count, 0, 0)))
+        .collect();
+    for &edge in diff.extra_edges() {
+        let counts = edges.entry(edge).or_insert((0, 0, 0));
+        counts.0 = counts.0.saturating_sub(1);
+        counts.1 += 1;
+    }
+    for &edge in diff.missing_edges() {
+        edges.entry(edge).or_insert((0, 0, 0)).2 += 1;
+    }
+
+    writeln!(out, "digraph G {{")?;
+    for (vertex_id, style) in vertices {
+        write_vertex(out, vertex_id, style)?;
+    }
+    for (edge, (unchanged, removed, added)) in edges {
+        for _ in 0..unchanged {
+            write_edge(out, edge, "")?;
+        }
+        for _ in 0..removed {
+            write_edge(out, edge, REMOVED)?;
+        }
+        for _ in 0..added {
+            write_edge(out, edge, ADDED)?;
+        }
+    }
+    writeln!(out, "}}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use histo_graph_core::graph::diff::diff;
+    use histo_graph_core::graph::directed_graph::DirectedGraph;
+    use crate::dot::{write_dot, write_dot_diff};
+
+    #[test]
+    fn test_parallel_edges() {
+        let mut out: Vec<u8> = Vec::new();
+        write_dot(&DirectedGraph::from_edges(&[(1, 2), (1, 2), (3, 3)]), &mut out).unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(), "digraph G {\n  1;\n  2;\n  3;\n  1 -> 2;\n  1 -> 2;\n  3 -> 3;\n}\n");
+    }
+
+    #[test]
+    fn test_diff() {
+        let g1 = DirectedGraph::from_edges(&[(1, 2), (1, 2), (2, 3)]);
+        let g2 = DirectedGraph::from_edges(&[(1, 2), (2, 4)]);
+
+        let mut out: Vec<u8> = Vec::new();
+        write_dot_diff(&g1, &diff(&g1, &g2), &mut out).unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(), "digraph G {
+  1;
+  2;
+  3 [color=red, fontcolor=red, style=dashed];
+  4 [color=green, fontcolor=green];
+  1 -> 2;
+  1 -> 2 [color=red, fontcolor=red, style=dashed];
+  2 -> 3 [color=red, fontcolor=red, style=dashed];
+  2 -> 4 [color=green, fontcolor=green];
+}
+");
+    }
+
+    #[test]
+    fn test_empty_diff() {
+        let g = DirectedGraph::from_edges(&[(1, 2)]);
+        let (mut out, mut diff_out): (Vec<u8>, Vec<u8>) = (Vec::new(), Vec::new());
+        write_dot(&g, &mut out).unwrap();
+        write_dot_diff(&g, &diff(&g, &g), &mut diff_out).unwrap();
+
+        assert_eq!(out, diff_out);
+    }
+}
@@ -0,0 +1,495 @@
+//! This module implements the reading and writing of graphs in the [GraphML] format.
+//!
+//! A vertex is written as a node with the id `n<id>`. On import, a node id `n<id>` or `<id>` is
+//! read as the vertex `<id>`, unless a node id read before is another spelling of the same vertex
+//! id, as `1` and `n1`. The other node ids get fresh vertex ids, after the numeric ones, and are
+//! kept in the `original_ids` of the graph.
+//!
+//! The attributes of the nodes and the edges are read as strings, whatever their declared type,
+//! under the name of their key. The defaults of the keys are applied. Edges are always read as
+//! directed from their source to their target, and parallel edges are kept in a multigraph.
+//! Hyperedges, ports and nested graphs are ignored.
+//!
+//! [GraphML]: http://graphml.graphdrawing.org/
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use histo_graph_core::graph::directed_graph::{DirectedGraph, GraphMode};
+use histo_graph_core::graph::graph::{Edge, VertexId};
+
+/// The attributes of a vertex or an edge, by name.
+pub type Attributes = BTreeMap<String, String>;
+
+/// A graph with the attributes of its vertices and edges.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct GraphMl {
+    pub graph: DirectedGraph,
+    /// The node ids of the vertices that have got a fresh vertex id on import: the non-numeric
+    /// ids, and the numeric ones whose vertex id was already taken by another spelling.
+    pub original_ids: BTreeMap<VertexId, String>,
+    pub vertex_attributes: BTreeMap<VertexId, Attributes>,
+    /// The attributes of the edges. Parallel edges have their attributes in the order in which
+    /// they have been read.
+    pub edge_attributes: BTreeMap<Edge, Vec<Attributes>>,
+}
+
+impl From<DirectedGraph> for GraphMl {
+    fn from(graph: DirectedGraph) -> GraphMl {
+        GraphMl {
+            graph,
+            original_ids: BTreeMap::new(),
+            vertex_attributes: BTreeMap::new(),
+            edge_attributes: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GraphMlError {
+    Xml(quick_xml::Error),
+    Io(io::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for GraphMlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphMlError::Xml(e) => write!(f, "invalid xml: {}", e),
+            GraphMlError::Io(e) => write!(f, "io error: {}", e),
+            GraphMlError::Invalid(message) => write!(f, "invalid graphml: {}", message),
+        }
+    }
+}
+
+impl From<quick_xml::Error> for GraphMlError {
+    fn from(e: quick_xml::Error) -> GraphMlError {
+        GraphMlError::Xml(e)
+    }
+}
+
+impl From<quick_xml::events::attributes::AttrError> for GraphMlError {
+    fn from(e: quick_xml::events::attributes::AttrError) -> GraphMlError {
+        GraphMlError::Xml(e.into())
+    }
+}
+
+impl From<io::Error> for GraphMlError {
+    fn from(e: io::Error) -> GraphMlError {
+        GraphMlError::Io(e)
+    }
+}
+
+/// Writes the graph and its attributes as GraphML. The vertices and the edges are sorted, so that
+/// the output only depends on the content of the graph.
+///
+/// # Examples
+///
+/// ```
+/// use histo_graph_core::graph::directed_graph::DirectedGraph;
+/// use histo_graph_core::graph::graph::{VertexId, Edge};
+/// use histo_graph_serde::graphml::{write_graphml, GraphMl};
+///
+/// let mut graph = DirectedGraph::new();
+/// graph.add_edge(Edge(VertexId(1), VertexId(2)));
+///
+/// let mut out: Vec<u8> = Vec::new();
+/// write_graphml(&GraphMl::from(graph), &mut out).unwrap();
+/// assert!(String::from_utf8(out).unwrap().contains(r#"<edge source="n1" target="n2"/>"#));
+/// ```
+pub fn write_graphml<W: Write>(graph_ml: &GraphMl, out: &mut W) -> io::Result<()> {
+    let vertex_keys: BTreeSet<&str> = graph_ml.vertex_attributes
+        .values()
+        .flat_map(|attributes| attributes.keys().map(String::as_str))
+        .collect();
+    let edge_keys: BTreeSet<&str> = graph_ml.edge_attributes
+        .values()
+        .flatten()
+        .flat_map(|attributes| attributes.keys().map(String::as_str))
+        .collect();
+    let vertex_key_ids: HashMap<&str, String> = vertex_keys.iter()
+        .enumerate()
+        .map(|(i, &name)| (name, format!("v{}", i)))
+        .collect();
+    let edge_key_ids: HashMap<&str, String> = edge_keys.iter()
+        .enumerate()
+        .map(|(i, &name)| (name, format!("e{}", i)))
+        .collect();
+
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#)?;
+    for &name in vertex_keys.iter() {
+        writeln!(out, r#"  <key id="{}" for="node" attr.name="{}" attr.type="string"/>"#, vertex_key_ids[name], escape(name))?;
+    }
+    for &name in edge_keys.iter() {
+        writeln!(out, r#"  <key id="{}" for="edge" attr.name="{}" attr.type="string"/>"#, edge_key_ids[name], escape(name))?;
+    }
+    writeln!(out, r#"  <graph id="G" edgedefault="directed">"#)?;
+
+    let mut vertices: Vec<VertexId> = graph_ml.graph.vertices().copied().collect();
+    vertices.sort();
+    for vertex_id in vertices {
+        match graph_ml.vertex_attributes.get(&vertex_id).filter(|a| !a.is_empty()) {
+            None => writeln!(out, r#"    <node id="n{}"/>"#, vertex_id.0)?,
+            Some(attributes) => {
+                writeln!(out, r#"    <node id="n{}">"#, vertex_id.0)?;
+                write_data(out, attributes, &vertex_key_ids)?;
+                writeln!(out, "    </node>")?;
+            }
+        }
+    }
+
+    let mut edges: BTreeMap<Edge, usize> = BTreeMap::new();
+    for &edge in graph_ml.graph.edges() {
+        *edges.entry(edge).or_insert(0) += 1;
+    }
+    let no_attributes = Attributes::new();
+    for (edge, multiplicity) in edges {
+        let Edge(VertexId(source), VertexId(target)) = edge;
+        let edge_attributes = graph_ml.edge_attributes.get(&edge);
+        for i in 0..multiplicity {
+            let attributes = edge_attributes.and_then(|a| a.get(i)).unwrap_or(&no_attributes);
+            if attributes.is_empty() {
+                writeln!(out, r#"    <edge source="n{}" target="n{}"/>"#, source, target)?;
+            } else {
+                writeln!(out, r#"    <edge source="n{}" target="n{}">"#, source, target)?;
+                write_data(out, attributes, &edge_key_ids)?;
+                writeln!(out, "    </edge>")?;
+            }
+        }
+    }
+
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</graphml>")?;
+    Ok(())
+}
+
+fn write_data<W: Write>(out: &mut W, attributes: &Attributes, key_ids: &HashMap<&str, String>) -> io::Result<()> {
+    for (name, value) in attributes {
+        writeln!(out, r#"      <data key="{}">{}</data>"#, key_ids[name.as_str()], escape(value.as_str()))?;
+    }
+    Ok(())
+}
+
+/// A key declared in the document.
+struct Key {
+    name: String,
+    target: String,
+    default: Option<String>,
+}
+
+/// The element whose attributes are being read.
+enum Element {
+    Node(usize),
+    Edge(usize),
+}
+
+/// The content of a document, before the vertex ids are assigned.
+#[derive(Default)]
+struct Document {
+    keys: HashMap<String, Key>,
+    nodes: Vec<(String, Attributes)>,
+    edges: Vec<(String, String, Attributes)>,
+}
+
+/// Reads a graph and the attributes of its vertices and edges from GraphML.
+///
+/// # Examples
+///
+/// ```
+/// use histo_graph_core::graph::directed_graph::GraphMode;
+/// use histo_graph_core::graph::graph::{VertexId, Edge};
+/// use histo_graph_serde::graphml::read_graphml;
+///
+/// let xml = r#"<graphml>
+///   <key id="d0" for="node" attr.name="color"><default>blue</default></key>
+///   <graph edgedefault="directed">
+///     <node id="n0"><data key="d0">red</data></node>
+///     <node id="n1"/>
+///     <edge source="n0" target="n1"/>
+///   </graph>
+/// </graphml>"#;
+///
+/// let graph_ml = read_graphml(xml.as_bytes(), GraphMode::Multi).unwrap();
+/// assert!(graph_ml.graph.contains_edge(Edge(VertexId(0), VertexId(1))));
+/// assert_eq!(graph_ml.vertex_attributes[&VertexId(0)]["color"], "red");
+/// assert_eq!(graph_ml.vertex_attributes[&VertexId(1)]["color"], "blue");
+/// ```
+pub fn read_graphml<R: BufRead>(input: R, mode: GraphMode) -> Result<GraphMl, GraphMlError> {
+    let document = read_document(input)?;
+
+    // the first spelling of a numeric node id keeps it, the other node ids get the next free ids
+    let mut ids: HashMap<&str, VertexId> = HashMap::new();
+    let mut taken: HashSet<VertexId> = HashSet::new();
+    let mut named: Vec<&str> = Vec::new();
+    let mut seen: HashSet<&str> = HashSet::new();
+    let all_ids = document.nodes.iter()
+        .map(|(id, _)| id.as_str())
+        .chain(document.edges.iter().flat_map(|(source, target, _)| vec![source.as_str(), target.as_str()]));
+    for id in all_ids {
+        if !seen.insert(id) {
+            continue;
+        }
+        match numeric_id(id).filter(|&vertex_id| taken.insert(vertex_id)) {
+            Some(vertex_id) => {
+                ids.insert(id, vertex_id);
+            }
+            None => named.push(id),
+        }
+    }
+    let first_free_id = taken.iter().map(|&VertexId(id)| id.saturating_add(1)).max().unwrap_or(0);
+    // after the largest id, then from 0 if there is no id after it
+    let free_ids = (first_free_id..=u64::MAX)
+        .chain(0..first_free_id)
+        .map(VertexId)
+        .filter(|vertex_id| !taken.contains(vertex_id));
+    let mut graph_ml = GraphMl::from(DirectedGraph::with_mode(mode));
+    for (vertex_id, id) in free_ids.zip(named) {
+        ids.insert(id, vertex_id);
+        graph_ml.original_ids.insert(vertex_id, id.to_string());
+    }
+
+    for (id, attributes) in document.nodes.iter() {
+        let vertex_id = ids[id.as_str()];
+        graph_ml.graph.add_vertex(vertex_id);
+        let mut attributes = attributes.clone();
+        apply_defaults(&document.keys, "node", &mut attributes);
+        if !attributes.is_empty() {
+            graph_ml.vertex_attributes.entry(vertex_id).or_default().extend(attributes);
+        }
+    }
+    for (source, target, attributes) in document.edges.iter() {
+        let edge = Edge(ids[source.as_str()], ids[target.as_str()]);
+        if !graph_ml.graph.add_edge(edge) {
+            // an edge that is already contained in a simple graph
+            continue;
+        }
+        let mut attributes = attributes.clone();
+        apply_defaults(&document.keys, "edge", &mut attributes);
+        graph_ml.edge_attributes.entry(edge).or_default().push(attributes);
+    }
+    graph_ml.edge_attributes.retain(|_, attributes| attributes.iter().any(|a| !a.is_empty()));
+    Ok(graph_ml)
+}
+
+fn numeric_id(id: &str) -> Option<VertexId> {
+    id.strip_prefix('n').unwrap_or(id).parse().ok().map(VertexId)
+}
+
+fn apply_defaults(keys: &HashMap<String, Key>, target: &str, attributes: &mut Attributes) {
+    for key in keys.values().filter(|key| key.target == target || key.target == "all") {
+        if let Some(default) = key.default.as_ref() {
+            attributes.entry(key.name.clone()).or_insert_with(|| default.clone());
+        }
+    }
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>, GraphMlError> {
+    match element.try_get_attribute(name)? {
+        None => Ok(None),
+        Some(attribute) => Ok(Some(attribute.unescape_value()?.into_owned())),
+    }
+}
+
+fn required_attribute(element: &BytesStart, name: &str) -> Result<String, GraphMlError> {
+    attribute(element, name)?.ok_or_else(|| {
+        let element_name = String::from_utf8_lossy(element.local_name().as_ref()).into_owned();
+        GraphMlError::Invalid(format!("<{}> without {}", element_name, name))
+    })
+}
+
+fn read_document<R: BufRead>(input: R) -> Result<Document, GraphMlError> {
+    let mut reader = Reader::from_reader(input);
+    let mut buf: Vec<u8> = Vec::new();
+    let mut document = Document::default();
+
+    let mut element: Option<Element> = None;
+    // the key being declared, and the key of the data being read
+    let mut key: Option<String> = None;
+    let mut data: Option<(String, String)> = None;
+    let mut default: Option<String> = None;
+    // nested graphs are ignored
+    let mut graph_depth = 0;
+
+    loop {
+        let event = reader.read_event_into(&mut buf)?;
+        let empty = matches!(event, Event::Empty(_));
+        match event {
+            Event::Start(ref e) | Event::Empty(ref e) => match e.local_name().as_ref() {
+                b"graph" if !empty => graph_depth += 1,
+                b"key" => {
+                    let id = required_attribute(e, "id")?;
+                    let name = attribute(e, "attr.name")?.unwrap_or_else(|| id.clone());
+                    let target = attribute(e, "for")?.unwrap_or_else(|| "all".to_string());
+                    document.keys.insert(id.clone(), Key { name, target, default: None });
+                    if !empty {
+                        key = Some(id);
+                    }
+                }
+                b"default" if key.is_some() && !empty => default = Some(String::new()),
+                b"node" if graph_depth == 1 => {
+                    document.nodes.push((required_attribute(e, "id")?, Attributes::new()));
+                    if !empty {
+                        element = Some(Element::Node(document.nodes.len() - 1));
+                    }
+                }
+                b"edge" if graph_depth == 1 => {
+                    let source = required_attribute(e, "source")?;
+                    let target = required_attribute(e, "target")?;
+                    document.edges.push((source, target, Attributes::new()));
+                    if !empty {
+                        element = Some(Element::Edge(document.edges.len() - 1));
+                    }
+                }
+                b"data" if graph_depth == 1 && element.is_some() && !empty => data = Some((required_attribute(e, "key")?, String::new())),
+                _ => (),
+            },
+            Event::Text(ref e) => {
+                let text = e.unescape()?;
+                if let Some((_, value)) = data.as_mut() {
+                    value.push_str(&text);
+                } else if let Some(value) = default.as_mut() {
+                    value.push_str(&text);
+                }
+            }
+            Event::CData(ref e) => {
+                let text = String::from_utf8_lossy(e.as_ref());
+                if let Some((_, value)) = data.as_mut() {
+                    value.push_str(&text);
+                }
+            }
+            Event::End(ref e) => match e.local_name().as_ref() {
+                b"graph" => graph_depth -= 1,
+                b"key" => key = None,
+                b"default" => {
+                    if let (Some(id), Some(value)) = (key.as_ref(), default.take()) {
+                        if let Some(key) = document.keys.get_mut(id) {
+                            key.default = Some(value);
+                        }
+                    }
+                }
+                b"node" | b"edge" if graph_depth == 1 => element = None,
+                b"data" => {
+                    if let (Some((id, value)), Some(element)) = (data.take(), element.as_ref()) {
+                        let name = document.keys.get(&id).map(|key| key.name.clone()).unwrap_or(id);
+                        let attributes = match *element {
+                            Element::Node(i) => &mut document.nodes[i].1,
+                            Element::Edge(i) => &mut document.edges[i].2,
+                        };
+                        attributes.insert(name, value);
+                    }
+                }
+                _ => (),
+            },
+            Event::Eof => break,
+            _ => (),
+        }
+        buf.clear();
+    }
+    Ok(document)
+}
+
+#[cfg(test)]
+mod test {
+    use histo_graph_core::graph::directed_graph::{DirectedGraph, GraphMode};
+    use histo_graph_core::graph::graph::{Edge, VertexId};
+    use crate::graphml::{read_graphml, write_graphml, Attributes, GraphMl};
+
+    fn attributes(pairs: &[(&str, &str)]) -> Attributes {
+        pairs.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut graph = DirectedGraph::new();
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+        graph.add_edge(Edge(VertexId(3), VertexId(3)));
+        graph.add_vertex(VertexId(4));
+        let mut graph_ml = GraphMl::from(graph);
+        graph_ml.vertex_attributes.insert(VertexId(1), attributes(&[("label", "<one> & \"1\"")]));
+        graph_ml.edge_attributes.insert(Edge(VertexId(1), VertexId(2)), vec![
+            attributes(&[("weight", "1.5")]),
+            attributes(&[("weight", "2"), ("label", "second")]),
+        ]);
+
+        let mut out: Vec<u8> = Vec::new();
+        write_graphml(&graph_ml, &mut out).unwrap();
+        let read = read_graphml(out.as_slice(), GraphMode::Multi).unwrap();
+
+        assert_eq!(read, graph_ml);
+    }
+
+    #[test]
+    fn test_read_named_nodes_and_unknown_keys() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+              <key id="d0" for="node" attr.name="label" attr.type="string"/>
+              <key id="d1" for="edge" attr.name="weight" attr.type="double"/>
+              <graph id="G" edgedefault="directed">
+                <node id="alice"><data key="d0">Alice</data></node>
+                <node id="n3"/>
+                <node id="bob"/>
+                <edge source="alice" target="bob"><data key="d1">0.5</data><data key="d9"><![CDATA[x<y]]></data></edge>
+                <edge source="alice" target="bob"/>
+                <edge source="bob" target="n3"/>
+                <node id="nested"><graph><node id="inner"/></graph></node>
+              </graph>
+            </graphml>"#;
+
+        let graph_ml = read_graphml(xml.as_bytes(), GraphMode::Multi).unwrap();
+        let (alice, bob, nested) = (VertexId(4), VertexId(5), VertexId(6));
+
+        assert_eq!(graph_ml.graph.vertex_count(), 4);
+        assert_eq!(graph_ml.graph.edge_multiplicity(Edge(alice, bob)), 2);
+        assert!(graph_ml.graph.contains_edge(Edge(bob, VertexId(3))));
+        assert_eq!(graph_ml.original_ids[&alice], "alice");
+        assert_eq!(graph_ml.original_ids[&nested], "nested");
+        assert_eq!(graph_ml.vertex_attributes[&alice], attributes(&[("label", "Alice")]));
+        assert!(!graph_ml.vertex_attributes.contains_key(&nested));
+        assert_eq!(graph_ml.edge_attributes[&Edge(alice, bob)], vec![
+            attributes(&[("weight", "0.5"), ("d9", "x<y")]),
+            Attributes::new(),
+        ]);
+
+        let simple = read_graphml(xml.as_bytes(), GraphMode::Simple).unwrap();
+        assert_eq!(simple.graph.edge_multiplicity(Edge(alice, bob)), 1);
+    }
+
+    #[test]
+    fn test_read_colliding_ids() {
+        let xml = r#"<graphml>
+              <key id="d0" for="node" attr.name="id"/>
+              <graph edgedefault="directed">
+                <node id="1"><data key="d0">first</data></node>
+                <node id="n1"/>
+                <node id="18446744073709551615"/>
+                <node id="named"/>
+                <edge source="n1" target="1"/>
+              </graph>
+            </graphml>"#;
+
+        let graph_ml = read_graphml(xml.as_bytes(), GraphMode::Multi).unwrap();
+        let (n1, named) = (VertexId(0), VertexId(2));
+
+        assert_eq!(graph_ml.graph.vertex_count(), 4);
+        assert!(graph_ml.graph.contains_vertex(VertexId(u64::MAX)));
+        assert!(graph_ml.graph.contains_edge(Edge(n1, VertexId(1))));
+        assert_eq!(graph_ml.original_ids[&n1], "n1");
+        assert_eq!(graph_ml.original_ids[&named], "named");
+        assert_eq!(graph_ml.original_ids.len(), 2);
+        // an attribute named id is an attribute like the other ones
+        assert_eq!(graph_ml.vertex_attributes[&VertexId(1)], attributes(&[("id", "first")]));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(read_graphml(r#"<graphml><graph><edge source="n1"/></graph></graphml>"#.as_bytes(), GraphMode::Multi).is_err());
+        assert!(read_graphml(r#"<graphml><graph><node id="n1"></graph></graphml>"#.as_bytes(), GraphMode::Multi).is_err());
+    }
+}
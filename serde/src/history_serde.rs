@@ -0,0 +1,187 @@
+use std::fmt;
+
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::{self, Visitor};
+use histo_graph_core::graph::command::GraphCommand;
+use histo_graph_core::history::hashlist::{HashList, NodeHash};
+use histo_graph_core::history::history::{Author, Comment, Commit, CommitInfo, Hashs, Ref, Timestamp};
+
+use crate::command_serde::GraphCommandSer;
+
+/// Serialized form of a `NodeHash`: its 16-digit hexadecimal representation, as displayed by
+/// `refajo`. A string keeps the 64 bits of the hash in JSON, where numbers are doubles.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct NodeHashSer(pub NodeHash);
+
+impl Serialize for NodeHashSer {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+struct NodeHashVisitor;
+
+impl<'de> Visitor<'de> for NodeHashVisitor {
+    type Value = NodeHashSer;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a hexadecimal commit hash")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<NodeHashSer, E> {
+        value.parse().map(NodeHashSer).map_err(E::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for NodeHashSer {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<NodeHashSer, D::Error> {
+        deserializer.deserialize_str(NodeHashVisitor)
+    }
+}
+
+/// Serialized form of a `Commit` of commands.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct CommitSer {
+    hash: NodeHashSer,
+    author: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    author_email: Option<String>,
+    author_timestamp: u64,
+    comment: String,
+    timestamp: u64,
+    parents: Vec<NodeHashSer>,
+    #[serde(default)]
+    trailers: Vec<(String, String)>,
+    commands: Vec<GraphCommandSer>,
+}
+
+impl From<&Commit<Vec<GraphCommand>>> for CommitSer {
+    fn from(commit: &Commit<Vec<GraphCommand>>) -> CommitSer {
+        CommitSer {
+            hash: NodeHashSer(commit.hash()),
+            author: commit.author().0.clone(),
+            author_email: commit.author_email().map(str::to_string),
+            author_timestamp: commit.author_timestamp().0,
+            comment: commit.comment().0.clone(),
+            timestamp: commit.timestamp().0,
+            parents: commit.parents().iter().map(|&hash| NodeHashSer(hash)).collect(),
+            trailers: commit.trailers().to_vec(),
+            commands: commit.item().iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<&CommitSer> for Commit<Vec<GraphCommand>> {
+    fn from(commit_ser: &CommitSer) -> Commit<Vec<GraphCommand>> {
+        let info = CommitInfo {
+            author: Author(commit_ser.author.clone()),
+            author_email: commit_ser.author_email.clone(),
+            author_timestamp: Some(Timestamp(commit_ser.author_timestamp)),
+            comment: Comment(commit_ser.comment.clone()),
+            trailers: commit_ser.trailers.clone(),
+        };
+        Commit::with_info(
+            info,
+            Timestamp(commit_ser.timestamp),
+            commit_ser.parents.iter().map(|&NodeHashSer(hash)| hash).collect(),
+            commit_ser.hash.0,
+            commit_ser.commands.iter().map(Into::into).collect(),
+        )
+    }
+}
+
+/// Serialized form of a `Ref`, tagged with its kind, e.g.
+/// `{"kind":"branch","name":"master","commits":["00000000000000ff"]}`.
+/// The commits are listed from the most recent to the oldest.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum RefSer {
+    Detached { commits: Vec<NodeHashSer> },
+    Tag { name: String, commits: Vec<NodeHashSer> },
+    Branch { name: String, commits: Vec<NodeHashSer> },
+}
+
+fn commits_ser(hashs: &Hashs) -> Vec<NodeHashSer> {
+    hashs.iter().map(NodeHashSer).collect()
+}
+
+fn commits_de(commits: &[NodeHashSer]) -> Hashs {
+    commits
+        .iter()
+        .rev()
+        .fold(HashList::empty(), |tail, &NodeHashSer(hash)| HashList::cons(hash, tail))
+}
+
+impl From<&Ref> for RefSer {
+    fn from(r: &Ref) -> RefSer {
+        match r {
+            Ref::Detached(hashs) => RefSer::Detached { commits: commits_ser(hashs) },
+            Ref::Tag(hashs, name) => RefSer::Tag { name: name.clone(), commits: commits_ser(hashs) },
+            Ref::Branch(hashs, name) => RefSer::Branch { name: name.clone(), commits: commits_ser(hashs) },
+        }
+    }
+}
+
+impl From<&RefSer> for Ref {
+    fn from(r: &RefSer) -> Ref {
+        match r {
+            RefSer::Detached { commits } => Ref::Detached(commits_de(commits)),
+            RefSer::Tag { name, commits } => Ref::Tag(commits_de(commits), name.clone()),
+            RefSer::Branch { name, commits } => Ref::Branch(commits_de(commits), name.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use histo_graph_core::graph::graph::{Edge, VertexId};
+    use histo_graph_core::historized_graph::{Commands, HistorizedGraph};
+    use histo_graph_core::history::history::{Author, Comment, Commit, CommitInfo, Ref};
+    use crate::history_serde::{CommitSer, NodeHashSer, RefSer};
+
+    #[test]
+    fn test_commit() -> Result<(), serde_json::error::Error> {
+        let mut graph = HistorizedGraph::new();
+        graph.set_commit_info(CommitInfo::new(Author("lauren".to_string()), Comment("edge".to_string()))
+            .with_trailer("Ticket", "GRAPH-12"));
+        graph.add_vertex(VertexId(1));
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+        let commit = graph.repository().head().unwrap();
+
+        let str = serde_json::to_string(&CommitSer::from(commit))?;
+        assert!(str.contains(r#""commands":[{"op":"add_vertex","id":2},{"op":"add_edge","from":1,"to":2}]"#));
+        assert!(str.contains(&format!(r#""hash":"{}""#, commit.hash())));
+        assert!(!str.contains("author_email"));
+
+        let commit_ser: CommitSer = serde_json::from_str(&str)?;
+        let ser_de_commit: Commit<Commands> = (&commit_ser).into();
+        assert_eq!(&ser_de_commit, commit);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ref() -> Result<(), serde_json::error::Error> {
+        let mut graph = HistorizedGraph::new();
+        graph.add_vertex(VertexId(1));
+        graph.add_vertex(VertexId(2));
+        graph.tag("v1").unwrap();
+
+        for r in graph.repository().list_refs() {
+            let str = serde_json::to_string(&RefSer::from(r))?;
+            let ref_ser: RefSer = serde_json::from_str(&str)?;
+            assert_eq!(&Ref::from(&ref_ser), r);
+        }
+
+        let str = serde_json::to_string(&RefSer::from(graph.repository().current()))?;
+        let head = graph.repository().current().hashs().head();
+        assert!(str.starts_with(&format!(r#"{{"kind":"branch","name":"master","commits":["{}","#, head)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_hash() {
+        assert!(serde_json::from_str::<NodeHashSer>(r#""00ff""#).is_ok());
+        assert!(serde_json::from_str::<NodeHashSer>(r#""not a hash""#).is_err());
+        assert!(serde_json::from_str::<NodeHashSer>("255").is_err());
+    }
+}
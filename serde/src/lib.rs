@@ -1 +1,11 @@
 pub mod directed_graph_serde;
+pub mod delimited;
+pub mod dot;
+#[cfg(feature = "history")]
+pub mod command_serde;
+#[cfg(feature = "history")]
+pub mod diff_serde;
+#[cfg(feature = "history")]
+pub mod history_serde;
+#[cfg(feature = "graphml")]
+pub mod graphml;